@@ -0,0 +1,63 @@
+//! Embeds information about the build, so that `clog version` can report
+//! exactly which build is being run.
+
+// Uses
+use std::{env, path::Path, process::Command};
+
+// Constants
+/// Used in place of anything that couldn't be determined at build time.
+const UNKNOWN_STR: &str = "unknown";
+
+// Entry Point
+fn main() {
+	let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("Cargo always sets this");
+	let git_dir = Path::new(manifest_dir.as_str()).join(".git");
+
+	// The commit of the build, which isn't available for builds from crates.io
+	// The `.git` check keeps Git from finding some unrelated repo further up
+	let git_commit = git_dir
+		.exists()
+		.then(|| {
+			run_for_output(
+				Command::new("git")
+					.args(["rev-parse", "HEAD"])
+					.current_dir(manifest_dir.as_str()),
+			)
+		})
+		.flatten()
+		.unwrap_or_else(|| UNKNOWN_STR.to_owned());
+	println!("cargo:rustc-env=CLOG_BUILD_GIT_COMMIT={git_commit}");
+
+	// The target triple and compiler version
+	println!(
+		"cargo:rustc-env=CLOG_BUILD_TARGET={}",
+		env::var("TARGET").unwrap_or_else(|_| UNKNOWN_STR.to_owned())
+	);
+	let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+	let rustc_version = run_for_output(Command::new(rustc).arg("--version"))
+		.unwrap_or_else(|| UNKNOWN_STR.to_owned());
+	println!("cargo:rustc-env=CLOG_BUILD_RUSTC_VERSION={rustc_version}");
+
+	// Only rebuild when the commit changes, instead of after every change to the
+	// tree
+	// Paths that don't exist aren't watched, since Cargo would rerun the script
+	// every time otherwise
+	println!("cargo:rerun-if-changed=build.rs");
+	for watched_path in ["HEAD", "refs/heads", "packed-refs"] {
+		let watched_path = git_dir.join(watched_path);
+		if watched_path.exists() {
+			println!("cargo:rerun-if-changed={}", watched_path.display());
+		}
+	}
+}
+
+/// Runs a command and returns its trimmed stdout, if it succeeded.
+fn run_for_output(command: &mut Command) -> Option<String> {
+	command
+		.output()
+		.ok()
+		.filter(|output| output.status.success())
+		.and_then(|output| String::from_utf8(output.stdout).ok())
+		.map(|output| output.trim().to_owned())
+		.filter(|output| !output.is_empty())
+}
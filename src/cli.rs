@@ -1,9 +1,29 @@
 //! Provides the CLI for the program.
 
 // Uses
-use clap::{builder::NonEmptyStringValueParser, value_parser, Arg, ArgAction, ArgGroup, Command};
+use clap::{
+	builder::{NonEmptyStringValueParser, PossibleValue},
+	value_parser,
+	Arg,
+	ArgAction,
+	ArgGroup,
+	Command,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
 
-use crate::constants::{APPLICATION_PROPER_NAME, SHA1_HASH_ASCII_LENGTH};
+use crate::{
+	collection::{SvnLayout, BOT_AUTHOR_PATTERNS},
+	constants::{
+		APPLICATION_PROPER_NAME,
+		APPLICATION_VERSION,
+		MERGE_COMMIT_MARKER_STR,
+		NO_JIRA_TICKET_STR,
+		SHA1_HASH_ASCII_LENGTH,
+		STDOUT_PATH,
+		TICKET_URL_PLACEHOLDER,
+	},
+};
 
 // Constants
 const HELP_TEMPLATE: &str = "\
@@ -27,8 +47,37 @@ pub fn build_cli() -> Command {
 		.default_value(".")
 		.action(ArgAction::Set)
 		.value_name("PATH")
-		.help("The path to the Git repository to read from.")
+		.help(
+			"The path to the Git repository to read from.\nWhen this isn't provided, the path in \
+			 `$CLOG_REPO` is used if it's set, and the current directory otherwise.",
+		)
+		.value_parser(NonEmptyStringValueParser::new());
+	let ref_scope_arg = Arg::new("ref-scope")
+		.long("ref-scope")
+		.num_args(1)
+		.action(ArgAction::Append)
+		.value_name("GLOB")
+		.help(
+			"Only collect the commits on refs matching GLOB, instead of on all refs. Can be \
+			 provided multiple times.\nThe glob is matched against full ref names like \
+			 `refs/heads/release/*` or `refs/tags/v*`, and `refs/` is added to the start if it's \
+			 missing. `*` doesn't match `/`, and a glob without any wildcards gets `/*` added to \
+			 the end, the same as `git log --glob`.",
+		)
 		.value_parser(NonEmptyStringValueParser::new());
+	let lenient_parsing_arg = Arg::new("lenient-parsing")
+		.long("lenient-parsing")
+		.num_args(0..=1)
+		.default_value("false")
+		.default_missing_value("true")
+		.action(ArgAction::Set)
+		.value_name("TRUE/FALSE")
+		.value_parser(value_parser!(bool))
+		.conflicts_with("strict-parse")
+		.help(
+			"Skip any commits in the log that can't be parsed, with a warning for each, instead \
+			 of stopping at the first one.",
+		);
 	let hash_length_arg = Arg::new("hash-length")
 		.short('l')
 		.long("hash-length")
@@ -39,6 +88,20 @@ pub fn build_cli() -> Command {
 		.value_name("LENGTH")
 		.help("The number of characters to abbreviate Git revision hashes to when displayed.")
 		.value_parser(value_parser!(u32).range(6..=SHA1_HASH_ASCII_LENGTH as i64));
+	let full_hash_arg = Arg::new("full-hash")
+		.long("full-hash")
+		.visible_alias("full-hashes")
+		.num_args(0..=1)
+		.default_value("false")
+		.default_missing_value("true")
+		.action(ArgAction::Set)
+		.value_name("TRUE/FALSE")
+		.value_parser(value_parser!(bool))
+		.conflicts_with("hash-length")
+		.help(format!(
+			"Display Git revision hashes in full. This is the same as `--hash-length \
+			 {SHA1_HASH_ASCII_LENGTH}`."
+		));
 
 	let filepath_arg = Arg::new("filepath")
 		.short('p')
@@ -74,6 +137,21 @@ pub fn build_cli() -> Command {
 			"Include merge commits in the results.\nThis is off by default because they don't add \
 			 much to the resulting data, and tend to bloat the results.",
 		);
+	let resolve_merge_tickets_arg = Arg::new("resolve-merge-tickets")
+		.long("resolve-merge-tickets")
+		.num_args(0..=1)
+		.default_value("false")
+		.default_missing_value("true")
+		.action(ArgAction::Set)
+		.value_name("TRUE/FALSE")
+		.value_parser(value_parser!(bool))
+		.help(
+			"Credit merge commits with the Jira tickets of the commits they merge, so merges \
+			 without a ticket of their own aren't left without one. Merges of other merges get \
+			 those merges' tickets too. Only has an effect alongside \
+			 `--include-merge-commits`.\nA merge that brings in several tickets is listed under \
+			 each of them, so this can list the same merge in many places.",
+		);
 	let include_mentioned_arg = Arg::new("include-mentioned")
 		.short('m')
 		.long("include-mentioned")
@@ -93,6 +171,108 @@ pub fn build_cli() -> Command {
 			 in the commit message, instead of just at the beginning. Please note that if using \
 			 this feature, the same commit may be counted in multiple Jira tickets.",
 		);
+	let no_mention_filtering_arg = Arg::new("no-mention-filtering")
+		.long("no-mention-filtering")
+		.num_args(0..=1)
+		.default_value("false")
+		.default_missing_value("true")
+		.action(ArgAction::Set)
+		.value_name("TRUE/FALSE")
+		.value_parser(value_parser!(bool))
+		.help(
+			"Keep mentioned Jira tickets that appear inside URLs or file names, which are skipped \
+			 by default. Only has an effect alongside `--include-mentioned`.",
+		);
+	let include_notes_arg = Arg::new("include-notes")
+		.long("include-notes")
+		.num_args(0..=1)
+		.default_value("false")
+		.default_missing_value("true")
+		.action(ArgAction::Set)
+		.value_name("TRUE/FALSE")
+		.value_parser(value_parser!(bool))
+		.help(
+			"Also consider a commit to be part of any Jira ticket mentioned in its Git notes \
+			 (from `refs/notes/commits`), which recovers tickets that were recorded after the \
+			 commit was made.",
+		);
+	let svn_hash_refs_arg = Arg::new("svn-hash-refs")
+		.long("svn-hash-refs")
+		.num_args(0..=1)
+		.default_value("false")
+		.default_missing_value("true")
+		.action(ArgAction::Set)
+		.value_name("TRUE/FALSE")
+		.value_parser(value_parser!(bool))
+		.help(
+			"Treat bare `#123` references in commit messages as SVN revisions. They're left out \
+			 by default, since they're usually issues or pull requests instead. References like \
+			 `revision #123` and `Rev. 123` are always SVN revisions.",
+		);
+	let link_kinds_arg = Arg::new("link-kinds")
+		.long("link-kinds")
+		.num_args(1)
+		.value_delimiter(',')
+		.default_value("git,svn")
+		.action(ArgAction::Append)
+		.value_name("KINDS")
+		.value_parser(["git", "svn"])
+		.help(
+			"Which kinds of references in commit messages link commits together, separated by \
+			 commas: Git hashes (`git`), and SVN revisions like `r1500-1520` (`svn`).\nOn repos \
+			 migrated from SVN, the Git hashes are often just noise from pasted logs, so leaving \
+			 them out gives cleaner results.",
+		);
+	let ticket_pattern_exclude_arg = Arg::new("ticket-pattern-exclude")
+		.long("ticket-pattern-exclude")
+		.num_args(1)
+		.action(ArgAction::Append)
+		.value_name("REGEX")
+		.value_parser(parse_ticket_exclusion)
+		.help(
+			"Don't treat text matching REGEX as a Jira ticket, for things that look like one but \
+			 aren't, like `UTF-8`. The regex has to match the whole ticket. Can be provided \
+			 multiple times.",
+		);
+	let exclude_author_arg = Arg::new("exclude-author")
+		.long("exclude-author")
+		.num_args(1)
+		.action(ArgAction::Append)
+		.value_name("PATTERN")
+		.value_parser(NonEmptyStringValueParser::new())
+		.help(
+			"Leave out commits whose author name or email matches PATTERN, ignoring case. \
+			 Patterns with `*` or `?` wildcards have to match the whole name or email, and \
+			 anything else only has to be part of it. Can be provided multiple times.",
+		);
+	let exclude_bots_arg = Arg::new("exclude-bots")
+		.long("exclude-bots")
+		.num_args(0..=1)
+		.default_value("false")
+		.default_missing_value("true")
+		.action(ArgAction::Set)
+		.value_name("TRUE/FALSE")
+		.value_parser(value_parser!(bool))
+		.help(format!(
+			"Leave out commits by common bots, the same as `--exclude-author` with each of: {}",
+			BOT_AUTHOR_PATTERNS
+				.iter()
+				.map(|pattern| format!("`{pattern}`"))
+				.collect::<Vec<_>>()
+				.join(", ")
+		));
+	let min_project_length_arg = Arg::new("min-project-length")
+		.long("min-project-length")
+		.num_args(1)
+		.default_value("2")
+		.action(ArgAction::Set)
+		.value_name("LENGTH")
+		.value_parser(value_parser!(u32).range(2..))
+		.help(
+			"The shortest project key to accept in a Jira ticket. Raising this keeps acronyms \
+			 like `IP-4` from being mistaken for tickets, if all of the real project keys are \
+			 longer.",
+		);
 	let show_commits_arg = Arg::new("show-commits")
 		.short('c')
 		.long("show-commits")
@@ -109,6 +289,17 @@ pub fn build_cli() -> Command {
 			 because it makes the results too noisy and does not help unless checking the commit \
 			 information for technical reasons is required.",
 		);
+	let commit_limit_per_ticket_arg = Arg::new("commit-limit-per-ticket")
+		.long("commit-limit-per-ticket")
+		.num_args(1)
+		.action(ArgAction::Set)
+		.value_name("COUNT")
+		.value_parser(value_parser!(u32).range(1..))
+		.help(
+			"Only show the first COUNT commits of each ticket, followed by a note of how many \
+			 were left out. Only has an effect alongside `--show-commits`, and doesn't apply to \
+			 tickets on both objects in `compare`.",
+		);
 	let ticket_prefix_arg = Arg::new("ticket-prefix")
 		.short('P')
 		.long("ticket-prefix")
@@ -123,6 +314,135 @@ pub fn build_cli() -> Command {
 			 make the output more directly-usable with external tools, like turning each ticket \
 			 into a tag in Obsidian.",
 		);
+	let strip_project_arg = Arg::new("strip-project")
+		.long("strip-project")
+		.num_args(0..=1)
+		.default_value("false")
+		.default_missing_value("true")
+		.action(ArgAction::Set)
+		.value_name("TRUE/FALSE")
+		.value_parser(value_parser!(bool))
+		.help(
+			"Leave the project out of Jira tickets in the output, so `PROJ-123` with a \
+			 `--ticket-prefix` of `#` is shown as `#123`. Meant for repos that only use one \
+			 project, since tickets from different projects can't be told apart otherwise.",
+		);
+	let ticket_url_arg = Arg::new("ticket-url")
+		.long("ticket-url")
+		.num_args(1)
+		.action(ArgAction::Set)
+		.value_name("URL")
+		.value_parser(parse_ticket_url_template)
+		.help(
+			"Where Jira tickets can be viewed, with `{ticket}` standing in for the ticket, like \
+			 `https://jira.example.com/browse/{ticket}`. The link is included in the JSON Lines \
+			 output alongside the ticket.",
+		);
+	let show_svn_path_arg = Arg::new("show-svn-path")
+		.long("show-svn-path")
+		.visible_alias("svn-path")
+		.num_args(0..=1)
+		.default_value("false")
+		.default_missing_value("true")
+		.action(ArgAction::Set)
+		.value_name("TRUE/FALSE")
+		.value_parser(value_parser!(bool))
+		.help(
+			"Show where in the SVN repo each commit came from (like `[trunk]` or `[branch foo]`) \
+			 next to its hash, for commits that were migrated from SVN. This follows \
+			 `--svn-layout`.",
+		);
+	let show_svn_revision_arg = Arg::new("show-svn-revision")
+		.long("show-svn-revision")
+		.visible_alias("svn-revision")
+		.num_args(0..=1)
+		.default_value("false")
+		.default_missing_value("true")
+		.action(ArgAction::Set)
+		.value_name("TRUE/FALSE")
+		.value_parser(value_parser!(bool))
+		.help(
+			"Show the SVN revision each commit came from (like `(r12345)`) next to its hash, for \
+			 commits that were migrated from SVN.",
+		);
+	let hide_unreferenced_arg = Arg::new("hide-unreferenced")
+		.long("hide-unreferenced")
+		.visible_alias("hide-ghosts")
+		.num_args(0..=1)
+		.default_value("false")
+		.default_missing_value("true")
+		.action(ArgAction::Set)
+		.value_name("TRUE/FALSE")
+		.value_parser(value_parser!(bool))
+		.help(
+			"Hide commits that aren't on any branch or tag (such as ones only kept by the reflog) \
+			 from the displayed results. They are still used for resolving references between \
+			 commits. Note that this requires a containment check for every displayed commit, \
+			 which can be slow.",
+		);
+	let exclude_ticket_arg = Arg::new("exclude-ticket")
+		.short('x')
+		.long("exclude-ticket")
+		.visible_alias("exclude")
+		.num_args(1)
+		.action(ArgAction::Append)
+		.value_name("TICKET")
+		.help(
+			"Exclude a Jira ticket from the results, such as an epic that would otherwise \
+			 dominate them.\nCommits that also belong to a ticket that isn't excluded are still \
+			 listed under that ticket. Multiple tickets can be provided, separated by spaces, or \
+			 this argument can be provided multiple times.",
+		)
+		.value_parser(NonEmptyStringValueParser::new());
+	let no_ticket_as_arg = Arg::new("no-ticket-as")
+		.long("no-ticket-as")
+		.visible_alias("unticketed-as")
+		.num_args(1)
+		.action(ArgAction::Set)
+		.value_name("TICKET")
+		.help(format!(
+			"List commits without a Jira ticket under TICKET (like `MISC-0`) instead of under \
+			 `{NO_JIRA_TICKET_STR}`.\nThis is useful for importing the results into tools where \
+			 every entry needs a ticket.",
+		))
+		.value_parser(parse_jira_ticket);
+	let compact_arg = Arg::new("compact")
+		.long("compact")
+		.visible_alias("narrow")
+		.num_args(0..=1)
+		.default_value("false")
+		.default_missing_value("true")
+		.action(ArgAction::Set)
+		.value_name("TRUE/FALSE")
+		.value_parser(value_parser!(bool))
+		.help(
+			"Use a compact form of the output, with less indentation and long commit subjects cut \
+			 short, so that it wraps less in narrow terminals.\nThis is also used automatically \
+			 when the output is going straight to a narrow terminal, without being paged or \
+			 copied to the clipboard.",
+		);
+	let indent_arg = Arg::new("indent")
+		.long("indent")
+		.num_args(1)
+		.action(ArgAction::Set)
+		.value_name("STRING")
+		.conflicts_with("compact")
+		.help(
+			"The string to indent each level of nested output with, like two spaces. This \
+			 replaces the default tab, and the automatic compact form for narrow terminals.",
+		)
+		.value_parser(parse_display_string);
+	let merge_marker_arg = Arg::new("merge-marker")
+		.long("merge-marker")
+		.num_args(1)
+		.default_value(MERGE_COMMIT_MARKER_STR)
+		.action(ArgAction::Set)
+		.value_name("STRING")
+		.help(
+			"The string added after merge commits when they're displayed, including any leading \
+			 space.",
+		)
+		.value_parser(parse_display_string);
 	let copy_to_clipboard_arg = Arg::new("copy-to-clipboard")
 		.short('C')
 		.long("copy-to-clipboard")
@@ -144,14 +464,14 @@ pub fn build_cli() -> Command {
 
 	let list_subcommand = Command::new("list")
 		.about("Generates lists of information based on a provided revspec.")
-		.arg_required_else_help(true)
 		.arg(repo_arg.clone())
+		.arg(ref_scope_arg.clone())
+		.arg(lenient_parsing_arg.clone())
 		.arg(
 			Arg::new("revspec")
 				.num_args(1)
 				.action(ArgAction::Set)
 				.value_name("REVSPEC")
-				.required(true)
 				.help(format!(
 					"The revision(s)/reference(s) to inspect. This is passed verbatim to `git \
 					 log`.\nFor a simple revision range, use `A..B` (without quotes) where A \
@@ -160,109 +480,112 @@ pub fn build_cli() -> Command {
 					 two branches being merged. Note the 3 dots in this case, instead of 2.\nFor \
 					 a list of all changes that will be merged into another branch, use `A ^B` \
 					 where B is the base branch, and A is the branch to be merged into B.\nFor \
-					 more information, review: {}",
+					 more information, review: {}\nIf omitted, this defaults to \
+					 `@{{upstream}}..HEAD` (everything on the current branch that isn't on its \
+					 upstream yet), the same as `git log @{{u}}..`. It's an error to omit it if \
+					 the current branch doesn't have an upstream.",
 					"https://git-scm.com/book/en/v2/Git-Tools-Revision-Selection"
 				))
 				.value_parser(NonEmptyStringValueParser::new()),
 		)
-		.arg(filepath_arg.clone())
-		.arg(include_merge_commits_arg.clone())
-		.arg(include_mentioned_arg.clone())
-		.arg(show_commits_arg.clone())
-		.arg(hash_length_arg.clone())
-		.arg(ticket_prefix_arg.clone())
-		.arg(copy_to_clipboard_arg.clone());
-
-	let compare_subcommand = Command::new("compare")
-		.about(
-			"Compares two objects and generates a comprehensive list of differences (as best as \
-			 possible).",
-		)
-		.arg_required_else_help(true)
-		.arg(repo_arg.clone())
 		.arg(
-			Arg::new("object-a")
-				.num_args(1)
+			Arg::new("between-tags")
+				.long("between-tags")
+				.visible_alias("tags")
+				.num_args(2)
 				.action(ArgAction::Set)
-				.value_name("OBJECT_A")
-				.required(true)
-				.help("The first reference to compare.")
+				.value_names(["FROM_TAG", "TO_TAG"])
+				.conflicts_with("revspec")
+				.help(
+					"List the changes between two release tags, from the older one to the newer \
+					 one.\nThis is a shortcut for the revspec `FROM_TAG..TO_TAG`, but it checks \
+					 that both tags exist and that they're in the right order first.",
+				)
 				.value_parser(NonEmptyStringValueParser::new()),
 		)
 		.arg(
-			Arg::new("object-b")
-				.num_args(1)
+			Arg::new("since-tag")
+				.long("since-tag")
+				.visible_alias("since-last-tag")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
 				.action(ArgAction::Set)
-				.value_name("OBJECT_B")
-				.required(true)
-				.help("The second reference to compare.")
-				.value_parser(NonEmptyStringValueParser::new()),
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.conflicts_with_all(["revspec", "between-tags"])
+				.help(
+					"List the changes since the most recent tag reachable from HEAD, using the \
+					 revspec `<TAG>..HEAD`.\nIf there's no such tag, the full history of HEAD is \
+					 listed instead.",
+				),
 		)
-		.arg(filepath_arg)
-		.arg(include_merge_commits_arg)
 		.arg(
-			Arg::new("include-cherry-picks")
-				.long("include-cherry-picks")
-				.visible_alias("cherry-picks")
+			Arg::new("stdin-revspec")
+				.long("stdin-revspec")
 				.num_args(0..=1)
 				.default_value("false")
 				.default_missing_value("true")
 				.action(ArgAction::Set)
 				.value_name("TRUE/FALSE")
 				.value_parser(value_parser!(bool))
+				.conflicts_with_all(["revspec", "between-tags", "since-tag"])
 				.help(
-					"When this is false (default), the results will be filtered so that \
-					 cherry-picks of commits on the other object are removed. This cleans up the \
-					 results by removing changes that are on both objects, just under different \
-					 commits.\nUnfortunately, to do this, a heuristic is used that is not \
-					 perfect. As a result, this option provides the ability to disable the \
-					 functionality in case of issues.",
+					"Read the revspec from the first line of stdin instead, which avoids any \
+					 trouble with quoting characters like `^` and `{` in a shell.",
 				),
 		)
+		.arg(filepath_arg.clone())
+		.arg(include_merge_commits_arg.clone())
+		.arg(resolve_merge_tickets_arg.clone())
 		.arg(include_mentioned_arg.clone())
-		.arg(show_commits_arg)
-		.arg(hash_length_arg.clone())
-		.arg(ticket_prefix_arg.clone())
-		.arg(copy_to_clipboard_arg.clone());
-
-	let search_subcommand = Command::new("search")
-		.about(
-			"Searches for tickets, displaying all locations where any of their commits were \
-			 merged.",
-		)
-		.arg_required_else_help(true)
-		.arg(repo_arg.clone())
+		.arg(no_mention_filtering_arg.clone())
+		.arg(include_notes_arg.clone())
+		.arg(svn_hash_refs_arg.clone())
 		.arg(
-			Arg::new("jira-ticket")
-				.num_args(1..)
+			Arg::new("mention-impact")
+				.long("mention-impact")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
 				.action(ArgAction::Set)
-				.value_name("TICKET")
-				.required(true)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
 				.help(
-					"A Jira ticket to search. Can be specified multiple times to search multiple \
-					 tickets.",
-				)
-				.value_parser(NonEmptyStringValueParser::new()),
+					"Report (to stderr) how many tickets and commits are only in the results 					 \
+					 because of tickets mentioned in commit bodies, along with the tickets that 					 \
+					 gain the most commits that way. Useful for deciding whether 					 \
+					 `--include-mentioned` is worth the noise.",
+				),
 		)
+		.arg(ticket_pattern_exclude_arg.clone())
+		.arg(exclude_author_arg.clone())
+		.arg(exclude_bots_arg.clone())
+		.arg(min_project_length_arg.clone())
+		.arg(link_kinds_arg.clone())
+		.arg(show_commits_arg.clone())
+		.arg(commit_limit_per_ticket_arg.clone())
 		.arg(
-			Arg::new("search-tags")
-				.short('t')
-				.long("search-tags")
-				.visible_alias("tags")
+			Arg::new("group-by-file")
+				.long("group-by-file")
 				.num_args(0..=1)
 				.default_value("false")
 				.default_missing_value("true")
 				.action(ArgAction::Set)
 				.value_name("TRUE/FALSE")
 				.value_parser(value_parser!(bool))
-				.help("List tags in the search results as well as branches."),
+				.help(
+					"Group the commits under each ticket by the top-level directory they touched \
+					 the most files in, which helps with routing a ticket's changes to the right \
+					 reviewers. Only has an effect alongside `--show-commits`.\nThis needs the \
+					 changed files of every displayed commit, which takes an extra Git call.",
+				),
 		)
 		.arg(
-			Arg::new("local-branches")
-				.short('L')
-				.long("local-branches")
-				.visible_alias("search-local-branches")
-				.visible_alias("local")
+			Arg::new("collapse-duplicates")
+				.long("collapse-duplicates")
+				.visible_alias("collapse-dupes")
+				.visible_alias("dedup")
 				.num_args(0..=1)
 				.default_value("false")
 				.default_missing_value("true")
@@ -270,40 +593,724 @@ pub fn build_cli() -> Command {
 				.value_name("TRUE/FALSE")
 				.value_parser(value_parser!(bool))
 				.help(
-					"Search local branches instead of remote ones. Note that this requires all \
-					 branches to be up-to-date in order to get accurate results, which is why \
-					 it's off by default.",
+					"Collapse commits that make the same change into a single entry under each \
+					 Jira ticket.\nThis is useful when the revspec includes both an original \
+					 commit and a cherry-pick of it. Commits are considered the same if their \
+					 patch IDs match, or failing that, if their subjects match. The collapsed \
+					 entries are reflected in the commit counts.",
 				),
 		)
-		.arg(include_mentioned_arg)
-		.arg(hash_length_arg.clone())
-		.arg(ticket_prefix_arg)
-		.arg(copy_to_clipboard_arg);
-
-	let revmap_subcommand = Command::new("revmap")
-		.visible_alias("build-revmap") // Since `clog` started as `build-revmap`
-		.visible_alias("svn-revmap")
-		.about(
-			"Generates an SVN to Git revision map based on the metadata in commit messages, \
-			 provided by `git-svn` with the `--metadata` flag.",
-		)
-		.arg_required_else_help(true)
-		.arg(repo_arg)
-		.group(
-			ArgGroup::new("outputs")
-				.args(["binary", "markdown"])
-				.required(true)
-				.multiple(true),
-		)
 		.arg(
-			Arg::new("binary")
+			Arg::new("link-traversal")
+				.long("link-traversal")
+				.visible_alias("links")
+				.num_args(1)
+				.default_value("forward")
+				.action(ArgAction::Set)
+				.value_name("DIRECTION")
+				.value_parser(["forward", "backward", "none"])
+				.help(
+					"Which references to follow when nesting linked commits under each result: \
+					 the commits it references, like the ones it merged or cherry-picked \
+					 (`forward`), the commits that reference it, like merges of it (`backward`), \
+					 or none at all (`none`).\nUsing `none` gives flat results, and is faster on \
+					 large repos.",
+				),
+		)
+		.arg(
+			Arg::new("link-only-merges")
+				.long("link-only-merges")
+				.visible_alias("only-merge-links")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"Only nest linked commits that are likely to be merges, ignoring plain \
+					 mentions of commit hashes in commit messages.",
+				),
+		)
+		.arg(
+			Arg::new("max-tickets")
+				.long("max-tickets")
+				.visible_alias("page-size")
+				.num_args(1)
+				.action(ArgAction::Set)
+				.value_name("COUNT")
+				.value_parser(value_parser!(u32).range(1..))
+				.help(
+					"Split the Jira tickets into pages of COUNT tickets, and only display one of \
+					 them (see `--page`).\nThe totals still reflect all of the results. This is \
+					 useful for extremely large reports.",
+				),
+		)
+		.arg(
+			Arg::new("page")
+				.long("page")
+				.num_args(1)
+				.default_value("1")
+				.action(ArgAction::Set)
+				.value_name("PAGE")
+				.value_parser(value_parser!(u32).range(1..))
+				.requires("max-tickets")
+				.help(
+					"The page of Jira tickets to display when `--max-tickets` is used, starting \
+					 at 1.",
+				),
+		)
+		.arg(
+			Arg::new("count-only")
+				.long("count-only")
+				.visible_alias("counts")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.conflicts_with("show-commits")
+				.help(
+					"Only print the number of Jira tickets and commits in the results, skipping \
+					 the full listing. All filters still apply. This is useful for quick metrics \
+					 and in shell pipelines.",
+				),
+		)
+		.arg(
+			Arg::new("sort-by")
+				.long("sort-by")
+				.num_args(1)
+				.default_value("ticket")
+				.action(ArgAction::Set)
+				.value_name("ORDER")
+				.value_parser(["ticket", "date"])
+				.help(
+					"How to order the Jira tickets: by project and issue number (`ticket`), or by \
+					 when the earliest of their commits was committed (`date`), which reads like \
+					 a timeline.",
+				),
+		)
+		.arg(
+			Arg::new("format")
+				.long("format")
+				.num_args(1)
+				.default_value("text")
+				.action(ArgAction::Set)
+				.value_name("FORMAT")
+				.value_parser([
+					PossibleValue::new("text"),
+					PossibleValue::new("jsonl").alias("json-lines"),
+				])
+				.help(
+					"The format to print the results in: readable text (`text`), or JSON Lines \
+					 (`jsonl`) for feeding into other tools.\nWith JSON Lines, the first line is \
+					 a `meta` object describing the search, followed by one `ticket` object per \
+					 Jira ticket as soon as it's ready, and a final `summary` object.\nThe `meta` \
+					 object includes the Git version (`git_version`) and the arguments of every \
+					 Git command that was run to produce the results (`commands`), so that they \
+					 can be re-derived later.\nEach `ticket` object has the raw ticket (`key`), \
+					 the ticket as it's displayed with `--ticket-prefix` (`display`), and its \
+					 link from `--ticket-url` (`url`).\nThe `summary` object has the number of \
+					 Jira tickets in the results (`jira_ticket_total`), and the number of tickets \
+					 and commits on the lines above it (`displayed_jira_ticket_count` and \
+					 `displayed_commit_count`), which only differ from the results when they're \
+					 paged.",
+				),
+		)
+		.arg(
+			Arg::new("plain")
+				.long("plain")
+				.visible_alias("tsv")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.conflicts_with_all(["format", "count-only"])
+				.help(
+					"Print the results as tab-separated values for column-oriented parsers, \
+					 without any headers, bullets, markers, or backticks.\nEach line is \
+					 `<TICKET>\\t<COMMIT COUNT>`, or `<HASH>\\t<SUBJECT>` for every commit with \
+					 `--show-commits`.",
+				),
+		)
+		.arg(exclude_ticket_arg.clone())
+		.arg(no_ticket_as_arg.clone())
+		.arg(
+			Arg::new("merge-ticket-variants")
+				.long("merge-ticket-variants")
+				.visible_alias("merge-variants")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"Fold Jira tickets that look like variants of the same ticket (like \
+					 `MY_PROJ-12` and `MYPROJ-12`) into the one with the most commits.\nVariants \
+					 are always warned about, even without this option.",
+				),
+		)
+		.arg(show_svn_path_arg.clone())
+		.arg(show_svn_revision_arg.clone())
+		.arg(compact_arg.clone())
+		.arg(indent_arg.clone())
+		.arg(merge_marker_arg.clone())
+		.arg(hide_unreferenced_arg.clone())
+		.arg(hash_length_arg.clone())
+		.arg(full_hash_arg.clone())
+		.arg(ticket_prefix_arg.clone())
+		.arg(strip_project_arg.clone())
+		.arg(ticket_url_arg.clone())
+		.arg(copy_to_clipboard_arg.clone());
+
+	let compare_subcommand = Command::new("compare")
+		.about(
+			"Compares two objects and generates a comprehensive list of differences (as best as \
+			 possible).",
+		)
+		.arg_required_else_help(true)
+		.arg(repo_arg.clone())
+		.arg(ref_scope_arg.clone())
+		.arg(lenient_parsing_arg.clone())
+		.arg(
+			Arg::new("object-a")
+				.num_args(1)
+				.action(ArgAction::Set)
+				.value_name("OBJECT_A")
+				.required_unless_present_any(["stdin-objects", "releases"])
+				.help("The first reference to compare.")
+				.value_parser(NonEmptyStringValueParser::new()),
+		)
+		.arg(
+			Arg::new("object-b")
+				.num_args(1)
+				.action(ArgAction::Set)
+				.value_name("OBJECT_B")
+				.required_unless_present_any(["stdin-objects", "releases"])
+				.help("The second reference to compare.")
+				.value_parser(NonEmptyStringValueParser::new()),
+		)
+		.arg(
+			Arg::new("stdin-objects")
+				.long("stdin-objects")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.conflicts_with_all(["object-a", "object-b"])
+				.help(
+					"Read the two references to compare from the first two lines of stdin \
+					 instead, which avoids any trouble with quoting characters like `^` and `{` \
+					 in a shell.",
+				),
+		)
+		.arg(
+			Arg::new("releases")
+				.long("releases")
+				.num_args(1)
+				.action(ArgAction::Set)
+				.value_name("PATTERN")
+				.value_parser(NonEmptyStringValueParser::new())
+				.conflicts_with_all(["object-a", "object-b", "stdin-objects"])
+				.help(
+					"Compare the two most recent tags matching PATTERN (a glob, like `v*`) \
+					 instead of two provided references. The tags are ordered by the version \
+					 numbers in their names, and the older one is used as the first reference.",
+				),
+		)
+		.arg(
+			Arg::new("release-count")
+				.long("release-count")
+				.num_args(1)
+				.default_value("2")
+				.action(ArgAction::Set)
+				.value_name("COUNT")
+				.value_parser(value_parser!(u32).range(2..))
+				.requires("releases")
+				.help(
+					"With `--releases`, compare the COUNT-th most recent matching tag against the \
+					 most recent one, instead of the two most recent.",
+				),
+		)
+		.arg(filepath_arg)
+		.arg(include_merge_commits_arg.clone())
+		.arg(resolve_merge_tickets_arg)
+		.arg(
+			Arg::new("include-cherry-picks")
+				.long("include-cherry-picks")
+				.visible_alias("cherry-picks")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"When this is false (default), the results will be filtered so that \
+					 cherry-picks of commits on the other object are removed. This cleans up the \
+					 results by removing changes that are on both objects, just under different \
+					 commits.\nUnfortunately, to do this, a heuristic is used that is not \
+					 perfect. As a result, this option provides the ability to disable the \
+					 functionality in case of issues.",
+				),
+		)
+		.arg(
+			Arg::new("date-based-cherry-pick-detection")
+				.long("date-based-cherry-pick-detection")
+				.visible_alias("date-based-cherry-picks")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"Also filter out cherry-picks that don't reference the commit they were \
+					 picked from, by matching commits on the two objects that have the same \
+					 author and author date, where at least one was committed more than \
+					 `--backport-window` days after it was authored.\nCherry-picks keep the \
+					 original author date, so a late commit date is a sign of a back-port. \
+					 Rebases move the commit date too, which is what the window is for.",
+				),
+		)
+		.arg(
+			Arg::new("backport-window")
+				.long("backport-window")
+				.num_args(1)
+				.default_value("14")
+				.action(ArgAction::Set)
+				.value_name("DAYS")
+				.value_parser(value_parser!(u32))
+				.requires("date-based-cherry-pick-detection")
+				.help(
+					"How many days after being authored a commit has to be committed to count as \
+					 a probable back-port for `--date-based-cherry-pick-detection`.",
+				),
+		)
+		.arg(
+			Arg::new("ignore-trivial")
+				.long("ignore-trivial")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"Leave out commits that don't change anything meaningful, because their \
+					 changes are only whitespace or are confined to the `--generated-path` \
+					 paths.\nThis needs the changes of every commit in the results, so it's off \
+					 by default.",
+				),
+		)
+		.arg(
+			Arg::new("generated-path")
+				.long("generated-path")
+				.num_args(1)
+				.action(ArgAction::Append)
+				.value_name("GLOB")
+				.value_parser(NonEmptyStringValueParser::new())
+				.requires("ignore-trivial")
+				.help(
+					"A path (or glob, like `gen/**` or `**/*.lock`) relative to the root of the \
+					 repo that only holds generated files, so changes to it don't count for \
+					 `--ignore-trivial`. Can be specified multiple times.",
+				),
+		)
+		.arg(
+			Arg::new("equivalence")
+				.long("equivalence")
+				.num_args(1)
+				.default_value("clog")
+				.action(ArgAction::Set)
+				.value_name("ENGINE")
+				.value_parser(["clog", "git-cherry"])
+				.help(
+					"How cherry-picks between the two objects are detected: by the commit message \
+					 references that link them (`clog`), or by Git's own patch comparison, like \
+					 `git log --cherry-pick --right-only` (`git-cherry`).\nThe `git-cherry` \
+					 engine only finds cherry-picks that made the exact same change, so picks \
+					 with resolved conflicts are still listed, but it doesn't need the commit \
+					 messages to reference anything.",
+				),
+		)
+		.arg(include_mentioned_arg.clone())
+		.arg(no_mention_filtering_arg.clone())
+		.arg(include_notes_arg.clone())
+		.arg(svn_hash_refs_arg.clone())
+		.arg(ticket_pattern_exclude_arg.clone())
+		.arg(exclude_author_arg.clone())
+		.arg(exclude_bots_arg.clone())
+		.arg(min_project_length_arg.clone())
+		.arg(show_commits_arg)
+		.arg(commit_limit_per_ticket_arg)
+		.arg(exclude_ticket_arg)
+		.arg(no_ticket_as_arg)
+		.arg(show_svn_path_arg.clone())
+		.arg(show_svn_revision_arg.clone())
+		.arg(compact_arg.clone())
+		.arg(indent_arg.clone())
+		.arg(merge_marker_arg.clone())
+		.arg(hash_length_arg.clone())
+		.arg(full_hash_arg.clone())
+		.arg(ticket_prefix_arg.clone())
+		.arg(strip_project_arg.clone())
+		.arg(ticket_url_arg.clone())
+		.arg(copy_to_clipboard_arg.clone())
+		.arg(
+			Arg::new("summary-only")
+				.long("summary-only")
+				.visible_alias("summary")
+				.visible_alias("ticket-count-only")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.conflicts_with("show-commits")
+				.help(
+					"Only display the totals line of each section, skipping the lists of Jira \
+					 tickets. This is useful for status updates that only need the headline \
+					 numbers.",
+				),
+		)
+		.arg(
+			Arg::new("sections")
+				.long("sections")
+				.visible_alias("section")
+				.num_args(1)
+				.value_delimiter(',')
+				.default_value("only-a,only-b,both")
+				.action(ArgAction::Append)
+				.value_name("SECTIONS")
+				.value_parser(["only-a", "only-b", "both"])
+				.help(
+					"Which sections of the comparison to display, separated by commas: the \
+					 tickets only on object A (`only-a`), only on object B (`only-b`), and on \
+					 both objects (`both`).\nSections that aren't displayed aren't computed \
+					 either, where possible. Filtering out cherry-picks and the section on both \
+					 objects need the results for both objects, though.",
+				),
+		)
+		.arg(
+			Arg::new("both-only")
+				.long("both-only")
+				.visible_alias("intersection-only")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.conflicts_with("sections")
+				.help(
+					"Only display the tickets on both objects, like when confirming that a \
+					 backport landed everywhere. This is the same as `--sections both`.",
+				),
+		)
+		.arg(
+			Arg::new("clipboard-section")
+				.long("clipboard-section")
+				.num_args(1)
+				.default_value("all")
+				.action(ArgAction::Set)
+				.value_name("SECTION")
+				.value_parser(["a", "b", "both", "all"])
+				.help(
+					"Which part of the output to copy to the clipboard when `--copy-to-clipboard` \
+					 is used: the tickets only on object A (`a`), only on object B (`b`), on both \
+					 objects (`both`), or everything (`all`). The full output is always displayed.",
+				),
+		)
+		.arg(
+			Arg::new("expected-commits")
+				.long("expected-commits")
+				.num_args(1)
+				.action(ArgAction::Set)
+				.value_name("FILE")
+				.help(
+					"Check that the commits listed in FILE (one hash per line, which can be \
+					 abbreviated) made it onto object B, like the commits a hotfix was supposed \
+					 to back-port.\nEach commit is reported as present, present through a \
+					 cherry-pick or merge of it, or absent. Hashes that can't be resolved are \
+					 reported separately.",
+				)
+				.value_parser(NonEmptyStringValueParser::new()),
+		)
+		.arg(
+			Arg::new("strict")
+				.long("strict")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.requires("expected-commits")
+				.help(
+					"Fail if any of the expected commits are absent from object B, or can't be \
+					 resolved, so that the comparison can be used as a release gate.",
+				),
+		)
+		.arg(
+			Arg::new("fail-if-empty")
+				.long("fail-if-empty")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"Fail if there's nothing in the comparison, either because both objects \
+					 resolve to the same commit or because no Jira tickets were found on either \
+					 of them.",
+				),
+		);
+
+	let search_subcommand = Command::new("search")
+		.about(
+			"Searches for tickets, displaying all locations where any of their commits were \
+			 merged.",
+		)
+		.long_about(
+			"Searches for tickets, displaying all locations where any of their commits were \
+			 merged.\nThe result sets are ordered by how many locations they have (most first), \
+			 then by the first of their locations alphabetically, then by their commit hashes, so \
+			 the same search always gives the same output. The commits within a set are listed in \
+			 the same order as the commit list being searched.",
+		)
+		.arg_required_else_help(true)
+		.arg(repo_arg.clone())
+		.arg(ref_scope_arg.clone())
+		.arg(lenient_parsing_arg.clone())
+		.arg(
+			Arg::new("jira-ticket")
+				.num_args(1..)
+				.action(ArgAction::Set)
+				.value_name("TICKET")
+				.required(true)
+				.help(
+					"A Jira ticket to search. Can be specified multiple times to search multiple \
+					 tickets.",
+				)
+				.value_parser(NonEmptyStringValueParser::new()),
+		)
+		.arg(
+			Arg::new("search-tags")
+				.short('t')
+				.long("search-tags")
+				.visible_alias("tags")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help("List tags in the search results as well as branches."),
+		)
+		.arg(
+			Arg::new("local-branches")
+				.short('L')
+				.long("local-branches")
+				.visible_alias("search-local-branches")
+				.visible_alias("local")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"Search local branches instead of remote ones. Note that this requires all \
+					 branches to be up-to-date in order to get accurate results, which is why \
+					 it's off by default.",
+				),
+		)
+		.arg(
+			Arg::new("containment-strategy")
+				.long("containment-strategy")
+				.num_args(1)
+				.default_value("git")
+				.action(ArgAction::Set)
+				.value_name("STRATEGY")
+				.value_parser(["git", "graph"])
+				.help(
+					"How to find the branches and tags containing each commit: by asking Git once \
+					 per commit (`git`), or by walking the commit graph once from every branch \
+					 and tag tip (`graph`).\nThe `graph` strategy is much faster when there are \
+					 many commits to search, and both should give the same results.",
+				),
+		)
+		.arg(
+			Arg::new("all-of")
+				.long("all-of")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"Only list the locations that contain commits from every ticket searched, \
+					 instead of from any of them.\nThis answers questions like \"which releases \
+					 have all of these tickets?\"",
+				),
+		)
+		.arg(
+			Arg::new("collapse-identical-branches")
+				.long("collapse-identical-branches")
+				.visible_alias("collapse-branches")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"List branches that point to the same commit once, noting the others next to \
+					 it, and leave out symbolic branches like `origin/HEAD`.\nThis helps with \
+					 mirrored remotes, where every branch would otherwise be listed several times.",
+				),
+		)
+		.arg(
+			Arg::new("strip-remote-prefix")
+				.long("strip-remote-prefix")
+				.visible_alias("abbrev-refs")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.conflicts_with("local-branches")
+				.help(
+					"Remove the leading `<remote>/` from displayed remote branch names. If two \
+					 branches in the same set would become indistinguishable (`origin/foo` and \
+					 `mirror/foo`), both are left fully-qualified.",
+				),
+		)
+		.arg(
+			Arg::new("sort-branches-by")
+				.long("sort-branches-by")
+				.num_args(1)
+				.default_value("name")
+				.action(ArgAction::Set)
+				.value_name("ORDER")
+				.value_parser(["name", "date"])
+				.help(
+					"How to order the branches in each set of the results: by name (`name`), or \
+					 by the date of the commit at the tip of each branch, newest first (`date`), \
+					 so the active branches come first.",
+				),
+		)
+		.arg(
+			Arg::new("max-branches")
+				.long("max-branches")
+				.num_args(1)
+				.action(ArgAction::Set)
+				.value_name("COUNT")
+				.value_parser(value_parser!(u32).range(1..))
+				.help(
+					"Only show the first COUNT branches (in sorted order) of each set in the \
+					 results, followed by a note of how many were left out. The sets are still \
+					 ordered by their full branch counts.",
+				),
+		)
+		.arg(
+			Arg::new("no-back-tree")
+				.long("no-back-tree")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"Don't display the list of commits being searched (and the commits that merge \
+					 them elsewhere) before the results. It's still used to find the locations.",
+				),
+		)
+		.arg(
+			Arg::new("show-provenance")
+				.long("show-provenance")
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"Note which of the commits being searched each commit in the results merges, \
+					 and how many links away from it the commit is.",
+				),
+		)
+		.arg(include_merge_commits_arg)
+		.arg(include_mentioned_arg)
+		.arg(no_mention_filtering_arg)
+		.arg(include_notes_arg)
+		.arg(svn_hash_refs_arg)
+		.arg(ticket_pattern_exclude_arg)
+		.arg(exclude_author_arg)
+		.arg(exclude_bots_arg)
+		.arg(min_project_length_arg)
+		.arg(link_kinds_arg)
+		.arg(hide_unreferenced_arg)
+		.arg(show_svn_path_arg)
+		.arg(show_svn_revision_arg)
+		.arg(compact_arg)
+		.arg(indent_arg)
+		.arg(merge_marker_arg)
+		.arg(hash_length_arg.clone())
+		.arg(full_hash_arg.clone())
+		.arg(ticket_prefix_arg)
+		.arg(strip_project_arg)
+		.arg(ticket_url_arg)
+		.arg(copy_to_clipboard_arg);
+
+	let revmap_subcommand = Command::new("revmap")
+		.visible_alias("build-revmap") // Since `clog` started as `build-revmap`
+		.visible_alias("svn-revmap")
+		.about(
+			"Generates an SVN to Git revision map based on the metadata in commit messages, \
+			 provided by `git-svn` with the `--metadata` flag.",
+		)
+		.arg_required_else_help(true)
+		.arg(repo_arg.clone())
+		.arg(ref_scope_arg)
+		.arg(lenient_parsing_arg)
+		.group(
+			ArgGroup::new("outputs")
+				.args([
+					"binary",
+					"markdown",
+					"markdown-table",
+					"svn-fanin",
+					"output-dir",
+				])
+				.required(true)
+				.multiple(true),
+		)
+		.arg(
+			Arg::new("binary")
 				.short('b')
 				.long("binary")
 				.visible_alias("bin")
 				.num_args(1)
 				.action(ArgAction::Set)
 				.value_name("PATH")
-				.help("Write the results to a binary file at PATH.")
+				.help(format!(
+					"Write the results to a binary file at PATH, or to stdout if PATH is \
+					 `{STDOUT_PATH}`.\nThe file uses the same layout as git-svn's `.rev_map` \
+					 files, with one record per SVN revision."
+				))
 				.value_parser(NonEmptyStringValueParser::new()),
 		)
 		.arg(
@@ -314,20 +1321,320 @@ pub fn build_cli() -> Command {
 				.num_args(1)
 				.action(ArgAction::Set)
 				.value_name("PATH")
-				.help("Write the results to a Markdown file at PATH.")
+				.help(format!(
+					"Write the results to a Markdown file at PATH, or to stdout if PATH is \
+					 `{STDOUT_PATH}`."
+				))
+				.value_parser(NonEmptyStringValueParser::new()),
+		)
+		.arg(
+			Arg::new("markdown-style")
+				.long("markdown-style")
+				.visible_alias("md-style")
+				.num_args(1)
+				.default_value("list")
+				.action(ArgAction::Set)
+				.value_name("STYLE")
+				.value_parser(["list", "table", "split"])
+				.help(
+					"How to lay out the Markdown output: as a flat list (`list`), as a table \
+					 (`table`), or as a list with one section per chunk of SVN revisions \
+					 (`split`), so that large maps can be paginated.",
+				),
+		)
+		.arg(
+			Arg::new("markdown-chunk-size")
+				.long("markdown-chunk-size")
+				.visible_alias("chunk-size")
+				.num_args(1)
+				.default_value("1000")
+				.action(ArgAction::Set)
+				.value_name("REVISIONS")
+				.value_parser(value_parser!(u32).range(1..))
+				.help("The number of SVN revisions per section with `--markdown-style split`."),
+		)
+		.arg(
+			Arg::new("markdown-table")
+				.short('t')
+				.long("markdown-table")
+				.visible_alias("md-table")
+				.num_args(1)
+				.action(ArgAction::Set)
+				.value_name("PATH")
+				.help(format!(
+					"Write the results to a Markdown file at PATH, formatted as a table \
+					 regardless of `--markdown-style`. Use `{STDOUT_PATH}` to write to stdout."
+				))
+				.value_parser(NonEmptyStringValueParser::new()),
+		)
+		.arg(
+			Arg::new("svn-fanin")
+				.long("svn-fanin")
+				.visible_alias("fanin")
+				.num_args(1)
+				.action(ArgAction::Set)
+				.value_name("PATH")
+				.help(format!(
+					"Write a Markdown report of the Git commits associated with more than one SVN \
+					 revision to PATH, or to stdout if PATH is `{STDOUT_PATH}`.\nA commit is \
+					 associated with its own SVN revision and any it references, so this \
+					 highlights the squashes and merges in a migration."
+				))
 				.value_parser(NonEmptyStringValueParser::new()),
 		)
-		.arg(hash_length_arg);
+		.arg(
+			Arg::new("output-dir")
+				.short('o')
+				.long("output-dir")
+				.num_args(1)
+				.action(ArgAction::Set)
+				.value_name("DIR")
+				.help(
+					"Write each of the `--formats` to a `revmap` file in DIR, which is created if \
+					 it doesn't exist. This can be combined with the individual output paths.",
+				)
+				.value_parser(NonEmptyStringValueParser::new()),
+		)
+		.arg(
+			Arg::new("formats")
+				.long("formats")
+				.num_args(1)
+				.value_delimiter(',')
+				.default_value("bin,md")
+				.action(ArgAction::Append)
+				.value_name("FORMATS")
+				.value_parser(["bin", "md", "md-table", "fanin"])
+				.requires("output-dir")
+				.help(
+					"Which formats to write to `--output-dir`, separated by commas: the binary \
+					 revision map (`bin`, as `revmap.bin`), the Markdown revision map (`md`, as \
+					 `revmap.md`), the Markdown table (`md-table`, as `revmap-table.md`), and the \
+					 SVN fan-in report (`fanin`, as `revmap-fanin.md`).",
+				),
+		)
+		.arg(hash_length_arg)
+		.arg(full_hash_arg);
+
+	let doctor_subcommand = Command::new("doctor")
+		.about(
+			"Checks that Git and the repo are set up the way the other subcommands need, and \
+			 explains anything that will limit the results. Nothing is changed.",
+		)
+		.arg(repo_arg);
+
+	let version_subcommand = Command::new("version").about(
+		"Prints detailed information about this build and the installed Git, for including in bug \
+		 reports.",
+	);
 
 	Command::new(APPLICATION_PROPER_NAME)
-		.version(env!("CARGO_PKG_VERSION"))
+		.version(APPLICATION_VERSION)
 		.author(env!("CARGO_PKG_AUTHORS"))
 		.about(env!("CARGO_PKG_DESCRIPTION"))
 		.help_template(HELP_TEMPLATE)
 		.arg_required_else_help(true)
 		.help_expected(true)
+		.arg(
+			Arg::new("verbose")
+				.short('v')
+				.long("verbose")
+				.global(true)
+				// Since this can be placed before the subcommand, a value has to be attached with
+				// `=` so that the subcommand isn't mistaken for it
+				.require_equals(true)
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"Print additional diagnostic information to stderr, such as every Git command \
+					 that was run to produce the results.",
+				),
+		)
+		.arg(
+			Arg::new("no-pager")
+				.long("no-pager")
+				.global(true)
+				// Since this can be placed before the subcommand, a value has to be attached with
+				// `=` so that the subcommand isn't mistaken for it
+				.require_equals(true)
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"Don't show the output through a pager.\nOtherwise, when stdout is a \
+					 terminal, the output goes through the pager in `$CLOG_PAGER` or `$PAGER` \
+					 (`less -FRX` if neither is set), which only kicks in when the output doesn't \
+					 fit on the screen. Files and the clipboard always get the output directly.",
+				),
+		)
+		.arg(
+			Arg::new("git-retries")
+				.long("git-retries")
+				.visible_alias("retries")
+				.global(true)
+				.num_args(1)
+				.default_value("0")
+				.action(ArgAction::Set)
+				.value_name("COUNT")
+				.value_parser(value_parser!(u32))
+				.help(
+					"Retry Git commands that fail in a way that looks transient (like a lock held \
+					 by a concurrent `git gc`, or an I/O error on a network filesystem) up to \
+					 COUNT times, waiting a little longer before each retry.\nFailures that will \
+					 happen every time, like a bad revision, are never retried. Every Git command \
+					 that's run only reads from the repo, so retrying one is always safe.",
+				),
+		)
+		.arg(
+			Arg::new("strict-parse")
+				.long("strict-parse")
+				.global(true)
+				.num_args(0..=1)
+				.default_value("false")
+				.default_missing_value("true")
+				.action(ArgAction::Set)
+				.value_name("TRUE/FALSE")
+				.value_parser(value_parser!(bool))
+				.help(
+					"Stop with an error at anything unexpected in the repo data, instead of \
+					 working around it.\nThis covers malformed or repeated `git-svn-id` lines, \
+					 several commits with the same SVN revision, invalid UTF-8, and references \
+					 that look real but can't be found (for SVN revisions, only ones within the \
+					 mirrored range). It's meant for checking that a repo is clean, like after a \
+					 migration.",
+				),
+		)
+		.arg(
+			Arg::new("log-file")
+				.long("log-file")
+				.global(true)
+				.num_args(1)
+				.action(ArgAction::Set)
+				.value_name("PATH")
+				.value_parser(NonEmptyStringValueParser::new())
+				.help(
+					"Append a record of the run to the file at PATH, as a single line of JSON \
+					 with the arguments, what was looked at, summary counts of the results, how \
+					 long it took, and whether it succeeded (with the error if it didn't).\nThe \
+					 record is written even when the run fails, and concurrent runs can share the \
+					 same file.",
+				),
+		)
+		.arg(
+			Arg::new("svn-layout")
+				.long("svn-layout")
+				.global(true)
+				.num_args(1)
+				.default_value("trunk,branches,tags")
+				.action(ArgAction::Set)
+				.value_name("TRUNK,BRANCHES,TAGS")
+				.value_parser(parse_svn_layout)
+				.help(
+					"The directories the SVN repo keeps its trunk, branches, and tags in, which \
+					 are used to work out the branch or tag of each SVN commit from its \
+					 `git-svn-id` URL.\nAnything after the branches or tags directory is the \
+					 name, so nested names like `branches/team/feature` are kept whole.",
+				),
+		)
 		.subcommand(list_subcommand)
 		.subcommand(compare_subcommand)
 		.subcommand(search_subcommand)
 		.subcommand(revmap_subcommand)
+		.subcommand(doctor_subcommand)
+		.subcommand(version_subcommand)
+}
+
+/// Parses a Jira ticket provided on the command line, making sure that it's in
+/// the usual `PROJECT-123` format.
+fn parse_jira_ticket(value: &str) -> Result<String, String> {
+	lazy_static! {
+		static ref JIRA_TICKET_REGEX: Regex = Regex::new(r"^[A-Z][A-Z0-9_]+-[0-9]+$").unwrap();
+	}
+
+	if JIRA_TICKET_REGEX.is_match(value) {
+		Ok(value.to_owned())
+	} else {
+		Err("Jira tickets should be in the format `PROJECT-123`".to_owned())
+	}
+}
+
+/// Parses an SVN layout provided on the command line, which is the trunk,
+/// branches, and tags directories separated by commas.
+fn parse_svn_layout(value: &str) -> Result<SvnLayout, String> {
+	let directories = value
+		.split(',')
+		.map(|directory| directory.trim().trim_matches('/'))
+		.collect::<Vec<_>>();
+	match directories.as_slice() {
+		[trunk, branches, tags] if directories.iter().all(|directory| !directory.is_empty()) => {
+			Ok(SvnLayout {
+				trunk:    (*trunk).to_owned(),
+				branches: (*branches).to_owned(),
+				tags:     (*tags).to_owned(),
+			})
+		}
+		_ => Err(
+			"the layout should be the trunk, branches, and tags directories, like \
+			 `trunk,branches,tags`"
+				.to_owned(),
+		),
+	}
+}
+
+/// Parses a URL template for Jira tickets, making sure that it has somewhere to
+/// put the ticket.
+fn parse_ticket_url_template(value: &str) -> Result<String, String> {
+	if value.contains(TICKET_URL_PLACEHOLDER) {
+		Ok(value.to_owned())
+	} else {
+		Err(format!(
+			"the URL needs a `{TICKET_URL_PLACEHOLDER}` where the ticket goes"
+		))
+	}
+}
+
+/// Parses a string that's inserted into the displayed output, making sure that
+/// it's not empty and that it doesn't contain any control characters (other
+/// than tabs).
+fn parse_display_string(value: &str) -> Result<String, String> {
+	if value.is_empty() {
+		Err("the value can't be empty".to_owned())
+	} else if value.chars().any(|c| c.is_control() && c != '\t') {
+		Err("the value can't contain control characters other than tabs".to_owned())
+	} else {
+		Ok(value.to_owned())
+	}
+}
+
+/// Parses a regex for text that shouldn't be treated as a Jira ticket,
+/// anchoring it so that it has to match the whole ticket.
+fn parse_ticket_exclusion(value: &str) -> Result<Regex, String> {
+	Regex::new(format!("^(?:{value})$").as_str()).map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ticket_exclusions_match_whole_tickets() {
+		let ticket_exclusion = parse_ticket_exclusion("UTF-8").expect("the regex is valid");
+
+		assert!(ticket_exclusion.is_match("UTF-8"));
+		assert!(!ticket_exclusion.is_match("PROJ-8"));
+		assert!(!ticket_exclusion.is_match("UTF-80"));
+		assert!(!ticket_exclusion.is_match("XUTF-8"));
+
+		// Alternations are grouped, so the anchors apply to every branch
+		let ticket_exclusion = parse_ticket_exclusion("UTF-8|HTTP-2").expect("the regex is valid");
+		assert!(ticket_exclusion.is_match("HTTP-2"));
+		assert!(!ticket_exclusion.is_match("UTF-80"));
+		assert!(!ticket_exclusion.is_match("HTTP-20"));
+	}
 }
@@ -26,7 +26,7 @@ pub fn copy_str_to_clipboard(contents: &str) -> Result<()> {
 		.map_err(|_| anyhow!("unable to set clipboard contents"))?;
 
 	// https://github.com/alacritty/copypasta/issues/49
-	if NON_PERSISTENT_CLIPBOARD_OSES.contains(&OS) {
+	if clipboard_reads_stdin() {
 		eprintln!();
 		eprintln!(
 			"On this OS, the clipboard contents will be lost when the application that set them \
@@ -42,6 +42,12 @@ pub fn copy_str_to_clipboard(contents: &str) -> Result<()> {
 	Ok(())
 }
 
+/// Whether copying to the clipboard waits for input on stdin, which means
+/// stdin can't be used for anything else.
+pub fn clipboard_reads_stdin() -> bool {
+	NON_PERSISTENT_CLIPBOARD_OSES.contains(&OS)
+}
+
 fn await_user_input() {
 	// Throw away the result because it does not matter in this case
 	stdin().read_exact(&mut [0]).ok();
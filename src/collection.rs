@@ -3,6 +3,7 @@
 // Uses
 use std::{
 	collections::HashSet,
+	fmt::{Display, Formatter, Result as FmtResult},
 	hash::{Hash, Hasher},
 	path::Path,
 	process::Command,
@@ -15,34 +16,269 @@ use regex::Regex;
 
 use crate::{
 	constants::{GIT_SVN_ID_STR, SHA1_HASH_ASCII_LENGTH},
-	util::run_command,
+	ids::CommitId,
+	util::{
+		glob_matches,
+		is_strict_parsing,
+		output_to_string,
+		percent_decode,
+		run_command,
+		run_command_for_bytes,
+		sanitize_for_display,
+	},
 };
 
 // Constants
 const LOG_COMMIT_DELIMITER: &str = "CLOG-COMMIT-DELIMITER\n";
+/// Separates the commit message from its notes, when they're included.
+const LOG_NOTES_DELIMITER: &str = "CLOG-NOTES-DELIMITER\n";
+/// The authors that `--exclude-bots` leaves out, matched against the name and
+/// email.
+pub const BOT_AUTHOR_PATTERNS: &[&str] = &[
+	"*[bot]",
+	"*[bot]@users.noreply.github.com",
+	"dependabot*",
+	"renovate*",
+];
+/// The shortest project key the Jira ticket regexes match on their own.
+pub const MIN_JIRA_PROJECT_LENGTH: usize = 2;
 
 #[derive(Debug)]
 pub struct Commit {
-	pub git_revision:       String,
-	pub parent_revisions:   Vec<String>,
-	pub svn_info:           Option<SvnInfo>,
-	pub jira_tickets:       Vec<String>,
-	pub referenced_commits: ReferencedCommits,
-	pub is_likely_a_merge:  bool,
+	pub git_revision:           String,
+	pub parent_revisions:       Vec<String>,
+	pub author:                 Signature,
+	pub committer:              Signature,
+	pub subject:                String,
+	pub svn_info:               Option<SvnInfo>,
+	pub jira_tickets:           Vec<String>,
+	/// The tickets that are only mentioned in the message, rather than being at
+	/// the start of the subject. This is only filled in when
+	/// `TicketParsingOptions::track_mentions` is set.
+	pub mentioned_jira_tickets: Vec<String>,
+	pub referenced_commits:     ReferencedCommits,
+	pub is_likely_a_merge:      bool,
+}
+
+/// Who made a commit (or applied it), and when.
+///
+/// Cherry-picks and rebases keep the original author, but have a new
+/// committer.
+#[derive(Debug)]
+pub struct Signature {
+	pub name:      String,
+	pub email:     String,
+	/// Seconds since the Unix epoch.
+	pub timestamp: i64,
+}
+
+impl Signature {
+	/// Whether the name or email matches a pattern, ignoring case.
+	///
+	/// Patterns with `*` or `?` wildcards have to match the whole name or
+	/// email, and anything else only has to be part of it.
+	pub fn matches_pattern(&self, pattern: &str) -> bool {
+		[self.name.as_str(), self.email.as_str()]
+			.iter()
+			.any(|text| {
+				let text = text.to_lowercase();
+				let pattern = pattern.to_lowercase();
+				if pattern.contains(['*', '?']) {
+					glob_matches(pattern.as_str(), text.as_str())
+				} else {
+					text.contains(pattern.as_str())
+				}
+			})
+	}
 }
 
 #[derive(Debug)]
 pub struct SvnInfo {
-	pub svn_url:      String,
-	pub svn_revision: u32,
+	pub svn_url:         String,
+	pub svn_revision:    u32,
+	/// The UUID of the SVN repository the commit was migrated from.
+	pub repository_uuid: String,
+}
+
+impl SvnInfo {
+	/// Works out where in the SVN repo the commit was made, according to the
+	/// layout.
+	pub fn location(&self, svn_layout: &SvnLayout) -> SvnLocation {
+		svn_layout.locate(self.svn_url.as_str())
+	}
+}
+
+/// The directories an SVN repo keeps its trunk, branches, and tags in.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SvnLayout {
+	pub trunk:    String,
+	pub branches: String,
+	pub tags:     String,
+}
+
+impl Default for SvnLayout {
+	fn default() -> Self {
+		Self {
+			trunk:    "trunk".to_owned(),
+			branches: "branches".to_owned(),
+			tags:     "tags".to_owned(),
+		}
+	}
+}
+
+impl SvnLayout {
+	/// Works out where in the SVN repo a `git-svn-id` URL points.
+	///
+	/// The last path component that starts one of the layout directories is
+	/// used, so that a repo hosted under a path that happens to contain one of
+	/// them still works. Everything after the branches or tags directory is
+	/// the name, so nested names like `branches/team/feature` are kept whole.
+	/// URLs that don't fit the layout fall back to their last component.
+	pub fn locate(&self, svn_url: &str) -> SvnLocation {
+		let svn_url = svn_url.trim_end_matches('/');
+
+		let location = svn_url
+			.match_indices('/')
+			.map(|(separator_index, _)| &svn_url[separator_index + 1..])
+			.filter_map(|rest| {
+				if rest == self.trunk || rest.starts_with(format!("{}/", self.trunk).as_str()) {
+					Some((SvnPathKind::Trunk, self.trunk.as_str()))
+				} else if let Some(name) = rest
+					.strip_prefix(self.branches.as_str())
+					.and_then(|rest| rest.strip_prefix('/'))
+				{
+					Some((SvnPathKind::Branch, name))
+				} else {
+					rest.strip_prefix(self.tags.as_str())
+						.and_then(|rest| rest.strip_prefix('/'))
+						.map(|name| (SvnPathKind::Tag, name))
+				}
+			})
+			.filter(|(_, name)| !name.is_empty())
+			.last();
+
+		let (kind, name) = location.unwrap_or_else(|| {
+			(
+				SvnPathKind::Other,
+				svn_url.rsplit('/').next().unwrap_or(svn_url),
+			)
+		});
+		SvnLocation {
+			kind,
+			name: percent_decode(name),
+		}
+	}
+}
+
+/// What kind of path in the SVN repo a commit was made to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SvnPathKind {
+	Trunk,
+	Branch,
+	Tag,
+	/// Anything that doesn't fit the layout.
+	Other,
+}
+
+/// Where in the SVN repo a commit was made.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SvnLocation {
+	pub kind: SvnPathKind,
+	/// The name of the branch or tag, with any URL escapes decoded. For trunk,
+	/// it's the name of the trunk directory.
+	pub name: String,
+}
+
+impl Display for SvnLocation {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match self.kind {
+			SvnPathKind::Trunk | SvnPathKind::Other => f.write_str(self.name.as_str()),
+			SvnPathKind::Branch => write!(f, "branch {}", self.name),
+			SvnPathKind::Tag => write!(f, "tag {}", self.name),
+		}
+	}
 }
 
 #[derive(Debug)]
 pub struct ReferencedCommits {
-	pub git_commits: Vec<String>,
+	pub git_commits: Vec<CommitId>,
 	pub svn_commits: Vec<u32>,
 }
 
+/// One entry in a revision map, relating an SVN revision to the Git commit it
+/// was migrated to.
+#[derive(Clone, Debug)]
+pub struct RevisionMapEntry<'a> {
+	pub svn_revision: u32,
+	pub svn_url:      &'a str,
+	pub svn_location: SvnLocation,
+	pub svn_uuid:     &'a str,
+	pub git_revision: &'a str,
+}
+
+/// How Jira tickets are picked out of commit messages.
+// The options are all independent switches, so they don't fit an enum
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug)]
+pub struct TicketParsingOptions<'a> {
+	/// Look for tickets anywhere in the message, instead of just at the start
+	/// of the subject.
+	pub include_mentioned:   bool,
+	/// Skip mentioned tickets that are only part of a URL or a file name.
+	pub filter_mentioned:    bool,
+	/// Text matching any of these is never a ticket.
+	pub exclusions:          &'a [Regex],
+	/// The shortest project key to accept.
+	pub min_project_length:  usize,
+	/// Record the mentioned tickets separately, whether or not they're
+	/// included.
+	pub track_mentions:      bool,
+	/// Also look for tickets anywhere in the commit's notes, which are usually
+	/// added after the commit was made.
+	pub include_notes:       bool,
+	/// Treat bare `#123` references as SVN revisions, instead of leaving them
+	/// to issue trackers.
+	pub svn_hash_references: bool,
+}
+
+impl<'a> TicketParsingOptions<'a> {
+	/// Options for when the tickets don't matter, like when building a
+	/// revision map.
+	pub const fn ignored() -> Self {
+		Self {
+			include_mentioned:   false,
+			filter_mentioned:    false,
+			exclusions:          &[],
+			min_project_length:  MIN_JIRA_PROJECT_LENGTH,
+			track_mentions:      false,
+			include_notes:       false,
+			svn_hash_references: false,
+		}
+	}
+
+	/// Whether a matched ticket should be kept. Known false positives (like
+	/// `UTF-8`) are left out, along with acronyms that are shorter than any
+	/// real project key (like `IP-4`).
+	fn accepts(&self, jira_ticket: &str) -> bool {
+		!self
+			.exclusions
+			.iter()
+			.any(|ticket_exclusion| ticket_exclusion.is_match(jira_ticket))
+			&& jira_ticket
+				.split_once('-')
+				.map_or(0, |(project, _)| project.len())
+				>= self.min_project_length
+	}
+}
+
+/// An annotated tag object, which has its own hash separate from the commit
+/// it points to.
+#[derive(Debug)]
+pub struct AnnotatedTag {
+	pub tag_revision:    String,
+	pub commit_revision: String,
+}
+
 // Since the Git revision is already a hash and will be unique, this
 // implementation just forwards to it.
 impl Eq for Commit {}
@@ -53,6 +289,17 @@ impl PartialEq for Commit {
 	}
 }
 
+impl Commit {
+	/// Whether the commit was committed more than `window_seconds` after it was
+	/// authored, which usually means it was cherry-picked (back-ported) long
+	/// after the original was made.
+	///
+	/// Rebases also move the commit date, but usually by much less.
+	pub fn is_probably_backported(&self, window_seconds: i64) -> bool {
+		self.committer.timestamp - self.author.timestamp > window_seconds
+	}
+}
+
 impl Hash for Commit {
 	fn hash<H: Hasher>(&self, state: &mut H) {
 		self.git_revision.hash(state);
@@ -61,38 +308,206 @@ impl Hash for Commit {
 
 pub fn get_complete_commit_list<P>(
 	repo_dir: P,
-	include_mentioned_jira_tickets: bool,
+	ticket_parsing_options: &TicketParsingOptions,
+	ref_scopes: &[String],
+	lenient_parsing: bool,
 ) -> Result<Vec<Commit>>
 where
 	P: AsRef<Path>,
 {
-	// Prepare the `git log` command for collecting all commits in the repo
+	// Prepare the `git log` command for collecting all commits in the repo, or just
+	// the ones on the refs in scope
 	let mut command = Command::new("git");
+	command.arg("log");
+	if ref_scopes.is_empty() {
+		command.arg("--all");
+	} else {
+		command.args(
+			ref_scopes
+				.iter()
+				.map(|ref_scope| format!("--glob={ref_scope}")),
+		);
+	}
+	command.arg("--full-history");
+	// The notes are part of the same log output, so they don't need any extra Git
+	// calls
+	if ticket_parsing_options.include_notes {
+		command.arg("--notes");
+	}
 	command
-		.arg("log")
-		.arg("--all")
-		.arg("--full-history")
 		.arg(format!(
-			"--pretty=format:{LOG_COMMIT_DELIMITER}%H\n%P\n%s\n%b"
+			"--pretty=format:{}",
+			get_log_format(ticket_parsing_options.include_notes)
 		))
 		.current_dir(repo_dir);
 
 	// Run the command
-	run_command(command)
-		.with_context(|| "unable to get the repo log")?
-		// Split the output by the delimiter to get one entry per commit
-		.split(LOG_COMMIT_DELIMITER)
-		// Since it's a split() operation, the first delimiter at the beginning leads to an empty
-		// entry at the top
-		.skip(1)
-		// Process each entry into a usable commit
-		.map(|entry| process_commit_entry(entry, include_mentioned_jira_tickets))
-		.collect::<Result<Vec<_>>>()
-		.with_context(|| "unable to process log entries")
-}
-
-fn process_commit_entry(entry: &str, include_mentioned_jira_tickets: bool) -> Result<Commit> {
-	let lines = entry.lines().collect::<Vec<_>>();
+	let log_output =
+		run_command_for_bytes(&mut command).with_context(|| "unable to get the repo log")?;
+	let log_output = decode_log_output(&command, log_output, is_strict_parsing())?;
+	// Split the output by the delimiter to get one entry per commit
+	// Since it's a split() operation, the first delimiter at the beginning leads to
+	// an empty entry at the top
+	let entries = log_output.split(LOG_COMMIT_DELIMITER).skip(1);
+
+	// Process each entry into a usable commit
+	if !lenient_parsing {
+		return entries
+			.map(|entry| {
+				process_commit_entry(entry, ticket_parsing_options).with_context(|| {
+					format!(
+						"unable to process the log entry starting with `{}`",
+						sanitize_for_display(entry.lines().next().unwrap_or_default())
+					)
+				})
+			})
+			.collect::<Result<Vec<_>>>()
+			.with_context(|| "unable to process log entries");
+	}
+	let mut commits = Vec::new();
+	let mut skipped_entry_count = 0_usize;
+	for entry in entries {
+		match process_commit_entry(entry, ticket_parsing_options) {
+			Ok(commit) => commits.push(commit),
+			Err(error) => {
+				eprintln!(
+					"[WARNING] Skipping the log entry starting with `{}`, since it couldn't be \
+					 parsed: {error:#}",
+					sanitize_for_display(entry.lines().next().unwrap_or_default())
+				);
+				skipped_entry_count += 1;
+			}
+		}
+	}
+	if skipped_entry_count > 0 {
+		eprintln!(
+			"[WARNING] Skipped {skipped_entry_count} log entries that couldn't be parsed, so the \
+			 results are missing those commits"
+		);
+	}
+
+	Ok(commits)
+}
+
+/// Gets the `git log` format that `get_complete_commit_list` reads, with the
+/// notes after the message when they're included.
+fn get_log_format(include_notes: bool) -> String {
+	let commit_format =
+		format!("{LOG_COMMIT_DELIMITER}%H\n%P\n%an\t%ae\t%at\n%cn\t%ce\t%ct\n%s\n%b");
+	if include_notes {
+		// The delimiter has to be on a line of its own, so it can't be mistaken for
+		// part of the message
+		format!("{commit_format}\n{LOG_NOTES_DELIMITER}%N")
+	} else {
+		commit_format
+	}
+}
+
+/// Converts the output of the `git log` command from `get_complete_commit_list`
+/// into a string.
+///
+/// With strict parsing, invalid UTF-8 is an error that names the commit it's
+/// in, which is the last one to start before it. Otherwise it's replaced.
+fn decode_log_output(
+	command: &Command,
+	log_output: Vec<u8>,
+	strict_parsing: bool,
+) -> Result<String> {
+	if strict_parsing {
+		if let Err(error) = std::str::from_utf8(log_output.as_slice()) {
+			let valid_log_output = std::str::from_utf8(&log_output[..error.valid_up_to()])
+				.expect("everything before the error is valid");
+			let git_revision = valid_log_output
+				.rsplit(LOG_COMMIT_DELIMITER)
+				.next()
+				.and_then(|entry| entry.get(..SHA1_HASH_ASCII_LENGTH))
+				.unwrap_or("unknown");
+			return Err(anyhow!(
+				"the log entry for commit `{git_revision}` isn't valid UTF-8"
+			));
+		}
+	}
+	output_to_string(command, log_output)
+}
+
+/// Builds a revision map from the commits that have SVN info, sorted by SVN
+/// revision.
+///
+/// The sort is stable, so commits with the same SVN revision stay in log order.
+pub fn build_revision_map<'a>(
+	commits: &'a [Commit],
+	svn_layout: &SvnLayout,
+) -> Vec<RevisionMapEntry<'a>> {
+	let mut revision_map = commits
+		.iter()
+		.filter_map(|commit| {
+			commit.svn_info.as_ref().map(|svn_info| RevisionMapEntry {
+				svn_revision: svn_info.svn_revision,
+				svn_url:      svn_info.svn_url.as_str(),
+				svn_location: svn_info.location(svn_layout),
+				svn_uuid:     svn_info.repository_uuid.as_str(),
+				git_revision: commit.git_revision.as_str(),
+			})
+		})
+		.collect::<Vec<_>>();
+	revision_map.sort_by_key(|entry| entry.svn_revision);
+
+	revision_map
+}
+
+/// Collects all annotated tags in the repo, along with the commits they point
+/// to.
+///
+/// Lightweight tags are skipped, since they point directly to a commit and
+/// don't have a hash of their own.
+pub fn get_annotated_tags<P>(repo_dir: P) -> Result<Vec<AnnotatedTag>>
+where
+	P: AsRef<Path>,
+{
+	// Prepare the `git for-each-ref` command for collecting the tags
+	// The `*` fields are for the object the tag points to, and are only populated
+	// for annotated tags
+	let mut command = Command::new("git");
+	command
+		.arg("for-each-ref")
+		.arg("--format=%(objecttype) %(objectname) %(*objecttype) %(*objectname)")
+		.arg("refs/tags")
+		.current_dir(repo_dir);
+
+	// Run the command
+	let tag_list_raw = run_command(command).with_context(|| "unable to get the repo tags")?;
+	let annotated_tags = tag_list_raw
+		.lines()
+		.filter_map(|line| {
+			let mut line_parts = line.split(' ');
+			match (
+				line_parts.next(),
+				line_parts.next(),
+				line_parts.next(),
+				line_parts.next(),
+			) {
+				(Some("tag"), Some(tag_revision), Some("commit"), Some(commit_revision)) => {
+					Some(AnnotatedTag {
+						tag_revision:    tag_revision.to_owned(),
+						commit_revision: commit_revision.to_owned(),
+					})
+				}
+				_ => None,
+			}
+		})
+		.collect();
+
+	Ok(annotated_tags)
+}
+
+fn process_commit_entry(
+	entry: &str,
+	ticket_parsing_options: &TicketParsingOptions,
+) -> Result<Commit> {
+	// The notes are only present when they're included
+	let (message, notes) = entry.split_once(LOG_NOTES_DELIMITER).unwrap_or((entry, ""));
+
+	let lines = message.lines().collect::<Vec<_>>();
 	if lines.is_empty() {
 		return Err(anyhow!(
 			"commit entry is missing the commit hash (impossible)"
@@ -110,15 +525,51 @@ fn process_commit_entry(entry: &str, include_mentioned_jira_tickets: bool) -> Re
 		.map(ToOwned::to_owned)
 		.collect::<Vec<_>>();
 
+	let author = parse_signature(lines.get(2).copied().unwrap_or_default())
+		.with_context(|| "unable to parse the commit author")?;
+	let committer = parse_signature(lines.get(3).copied().unwrap_or_default())
+		.with_context(|| "unable to parse the commit committer")?;
+
+	let subject = lines.get(4).copied().unwrap_or_default().to_owned();
+
+	lazy_static! {
+		/// Looks for a Jira ticket right at the start, skipping "Pull request #..."
+		static ref JIRA_TICKET_START_REGEX: Regex =
+			Regex::new(r"^\s*(?:Pull request #\d+.*?)?([A-Z][A-Z0-9_]+-[1-9][0-9]*)\b").unwrap();
+		/// Looks for a Jira ticket anywhere on the line
+		static ref JIRA_TICKET_REFERENCED_REGEX: Regex =
+			Regex::new(r"\b([A-Z][A-Z0-9_]+-[1-9][0-9]*)\b").unwrap();
+		/// Matches any Git commit hashes 7 characters or longer (to avoid matching small numbers that show up for other reasons)
+		static ref GIT_COMMIT_REFERENCE_REGEX: Regex =
+			Regex::new(r"(?i)\b([0-9a-f]{7,40})\b").unwrap();
+		/// Finds (hopefully) all references to SVN revisions, but returns them as a group, not individually
+		static ref SVN_COMMIT_REFERENCE_REGEX: Regex =
+			Regex::new(r"(?i)\b(?:(?:commit|revision|rev)(?:s|\(s\))?\.? #?|r)(\d+(?:-\d+)?(?:, ?\d+(?:-\d+)?)*)\b").unwrap();
+		/// Finds bare `#123` references, which are only SVN revisions in some repos (elsewhere they're usually issues)
+		/// Anything attached to the front, like `PR#123` or `&#123;`, is left out
+		static ref SVN_HASH_REFERENCE_REGEX: Regex =
+			Regex::new(r"(?:^|[^\w#&/])#(\d+)\b").unwrap();
+		/// Finds mentions of merging or cherry-picking
+		static ref MERGE_MENTION_REGEX: Regex =
+			Regex::new(r"(?i)(merg(?:e|ing)|cherry.?pick)").unwrap();
+	}
+
 	// Search the commit message content for information
 	let mut svn_info = None;
-	let mut jira_tickets_set = HashSet::new();
+	let mut subject_jira_tickets_set = HashSet::new();
+	let mut referenced_jira_tickets_set = HashSet::new();
 	let mut referenced_git_commits_set = LinkedHashSet::new();
 	let mut referenced_svn_commits_set = LinkedHashSet::new();
 	let mut mentions_merging = false;
 	let mut first_line = true;
-	for line in lines.iter().skip(2) {
+	for line in lines.iter().skip(4) {
 		// Search for the SVN metadata string
+		// Only the first one is used, since any others are usually quoted from another
+		// commit's message, but that's an anomaly worth stopping for when parsing
+		// strictly
+		if svn_info.is_some() && line.starts_with(GIT_SVN_ID_STR) && is_strict_parsing() {
+			return Err(anyhow!("there's more than one {GIT_SVN_ID_STR} line"));
+		}
 		if svn_info.is_none() && line.starts_with(GIT_SVN_ID_STR) {
 			// The SVN metadata looks like this (without quotes):
 			// `git-svn-id: <URL>@<REVISION> <UUID>`
@@ -134,10 +585,12 @@ fn process_commit_entry(entry: &str, include_mentioned_jira_tickets: bool) -> Re
 			let svn_url = svn_url_str.to_owned();
 			let svn_revision = str::parse(svn_revision_str)
 				.with_context(|| "unable to parse SVN revision number as an integer")?;
+			let repository_uuid = line_parts[2].to_owned();
 
 			svn_info = Some(SvnInfo {
 				svn_url,
 				svn_revision,
+				repository_uuid,
 			});
 
 			// If we don't continue here, the UUID in the SVN metadata may be mistaken for a
@@ -146,39 +599,48 @@ fn process_commit_entry(entry: &str, include_mentioned_jira_tickets: bool) -> Re
 		}
 
 		// Search for Jira tickets
-		lazy_static! {
-			/// Looks for a Jira ticket right at the start, skipping "Pull request #..."
-			static ref JIRA_TICKET_START_REGEX: Regex =
-				Regex::new(r"^\s*(?:Pull request #\d+.*?)?([A-Z][A-Z0-9_]+-[1-9][0-9]*)\b").unwrap();
-			/// Looks for a Jira ticket anywhere on the line
-			static ref JIRA_TICKET_REFERENCED_REGEX: Regex =
-				Regex::new(r"\b([A-Z][A-Z0-9_]+-[1-9][0-9]*)\b").unwrap();
-			/// Matches any Git commit hashes 7 characters or longer (to avoid matching small numbers that show up for other reasons)
-			static ref GIT_COMMIT_REFERENCE_REGEX: Regex =
-				Regex::new(r"(?i)\b([0-9a-f]{7,40})\b").unwrap();
-			/// Finds (hopefully) all references to SVN revisions, but returns them as a group, not individually
-			static ref SVN_COMMIT_REFERENCE_REGEX: Regex =
-				Regex::new(r"(?i)\b(?:(?:commit|revision|rev)(?:s|\(s\))? |r)(\d+(?:-\d+)?(?:, ?\d+(?:-\d+)?)*)\b").unwrap();
-			/// Finds mentions of merging or cherry-picking
-			static ref MERGE_MENTION_REGEX: Regex =
-				Regex::new(r"(?i)(merg(?:e|ing)|cherry.?pick)").unwrap();
-		}
-		let jira_ticket_regex = if include_mentioned_jira_tickets {
-			&*JIRA_TICKET_REFERENCED_REGEX
-		} else {
-			&*JIRA_TICKET_START_REGEX
-		};
-		// Only check the first line for Jira tickets, unless we're supposed to look for
-		// all mentioned tickets
-		if include_mentioned_jira_tickets || first_line {
-			for jira_ticket in jira_ticket_regex.captures_iter(line) {
-				jira_tickets_set.insert(jira_ticket[1].to_owned());
+		// The tickets at the start of the subject are always the ones in use unless
+		// mentions are included
+		// When mentions are tracked, both kinds are collected in the same pass
+		if first_line
+			&& (!ticket_parsing_options.include_mentioned || ticket_parsing_options.track_mentions)
+		{
+			for jira_ticket in JIRA_TICKET_START_REGEX.captures_iter(line) {
+				let jira_ticket_match = jira_ticket
+					.get(1)
+					.expect("the regex always has the ticket capture group");
+				if ticket_parsing_options.accepts(jira_ticket_match.as_str()) {
+					subject_jira_tickets_set.insert(jira_ticket_match.as_str().to_owned());
+				}
+			}
+		}
+		if ticket_parsing_options.include_mentioned || ticket_parsing_options.track_mentions {
+			for jira_ticket in JIRA_TICKET_REFERENCED_REGEX.captures_iter(line) {
+				let jira_ticket_match = jira_ticket
+					.get(1)
+					.expect("the regex always has the ticket capture group");
+				// Mentions inside URLs and file names aren't real references to the ticket
+				if ticket_parsing_options.filter_mentioned
+					&& is_incidental_jira_ticket_mention(
+						line,
+						jira_ticket_match.start(),
+						jira_ticket_match.end(),
+					) {
+					continue;
+				}
+				if ticket_parsing_options.accepts(jira_ticket_match.as_str()) {
+					referenced_jira_tickets_set.insert(jira_ticket_match.as_str().to_owned());
+				}
 			}
 		}
 
 		// Search for referenced commits (merges, etc.)
 		for git_commit_reference in GIT_COMMIT_REFERENCE_REGEX.captures_iter(line) {
-			referenced_git_commits_set.insert(git_commit_reference[1].to_owned());
+			referenced_git_commits_set.insert(
+				git_commit_reference[1]
+					.parse::<CommitId>()
+					.expect("the regex only matches valid commit hashes"),
+			);
 		}
 		for svn_commit_reference_group in SVN_COMMIT_REFERENCE_REGEX.captures_iter(line) {
 			// The result of the Regex will be a comma-delimited list of continuous
@@ -202,6 +664,14 @@ fn process_commit_entry(entry: &str, include_mentioned_jira_tickets: bool) -> Re
 				}
 			}
 		}
+		if ticket_parsing_options.svn_hash_references {
+			for svn_hash_reference in SVN_HASH_REFERENCE_REGEX.captures_iter(line) {
+				// Numbers too big to be a revision aren't one
+				if let Ok(revision) = str::parse::<u32>(&svn_hash_reference[1]) {
+					referenced_svn_commits_set.insert(revision);
+				}
+			}
+		}
 
 		if MERGE_MENTION_REGEX.is_match(line) {
 			mentions_merging = true;
@@ -210,6 +680,28 @@ fn process_commit_entry(entry: &str, include_mentioned_jira_tickets: bool) -> Re
 		first_line = false;
 	}
 
+	// Tickets in the notes were added after the fact, so they're always used no
+	// matter where in the notes they are
+	let mut notes_jira_tickets_set = HashSet::new();
+	for line in notes.lines() {
+		for jira_ticket in JIRA_TICKET_REFERENCED_REGEX.captures_iter(line) {
+			let jira_ticket_match = jira_ticket
+				.get(1)
+				.expect("the regex always has the ticket capture group");
+			if ticket_parsing_options.filter_mentioned
+				&& is_incidental_jira_ticket_mention(
+					line,
+					jira_ticket_match.start(),
+					jira_ticket_match.end(),
+				) {
+				continue;
+			}
+			if ticket_parsing_options.accepts(jira_ticket_match.as_str()) {
+				notes_jira_tickets_set.insert(jira_ticket_match.as_str().to_owned());
+			}
+		}
+	}
+
 	// This is a heuristic that determines whether it is likely that the commit is a
 	// merge of other commits. It is not perfect.
 	// Conditions:
@@ -224,16 +716,38 @@ fn process_commit_entry(entry: &str, include_mentioned_jira_tickets: bool) -> Re
 		|| (mentions_merging
 			&& (!referenced_git_commits_set.is_empty() || !referenced_svn_commits_set.is_empty()))
 		|| (referenced_git_commits_set.len() == 1
-			&& referenced_git_commits_set
-				.iter()
-				.all(|commit_reference| commit_reference.len() == SHA1_HASH_ASCII_LENGTH))
+			&& referenced_git_commits_set.iter().all(CommitId::is_full))
 		|| referenced_svn_commits_set.len() > 1;
 
+	let mentioned_jira_tickets = if ticket_parsing_options.track_mentions {
+		referenced_jira_tickets_set
+			.iter()
+			.filter(|jira_ticket| {
+				!subject_jira_tickets_set.contains(*jira_ticket)
+					&& !notes_jira_tickets_set.contains(*jira_ticket)
+			})
+			.cloned()
+			.collect()
+	} else {
+		Vec::new()
+	};
+	let mut jira_tickets_set = if ticket_parsing_options.include_mentioned {
+		referenced_jira_tickets_set
+	} else {
+		subject_jira_tickets_set
+	};
+	jira_tickets_set.extend(notes_jira_tickets_set);
+	let jira_tickets = Vec::from_iter(jira_tickets_set);
+
 	Ok(Commit {
 		git_revision,
 		parent_revisions,
+		author,
+		committer,
+		subject,
 		svn_info,
-		jira_tickets: Vec::from_iter(jira_tickets_set),
+		jira_tickets,
+		mentioned_jira_tickets,
 		referenced_commits: ReferencedCommits {
 			git_commits: Vec::from_iter(referenced_git_commits_set),
 			svn_commits: Vec::from_iter(referenced_svn_commits_set),
@@ -241,3 +755,441 @@ fn process_commit_entry(entry: &str, include_mentioned_jira_tickets: bool) -> Re
 		is_likely_a_merge,
 	})
 }
+
+/// Parses a signature line, which looks like this (without quotes):
+/// `<NAME>\t<EMAIL>\t<TIMESTAMP>`
+///
+/// It's split from the end, since only the name could possibly contain a tab.
+fn parse_signature(line: &str) -> Result<Signature> {
+	let mut line_parts = line.rsplitn(3, '\t');
+	match (line_parts.next(), line_parts.next(), line_parts.next()) {
+		(Some(timestamp_str), Some(email), Some(name)) => Ok(Signature {
+			name:      name.to_owned(),
+			email:     email.to_owned(),
+			timestamp: str::parse(timestamp_str)
+				.with_context(|| "unable to parse the timestamp as an integer")?,
+		}),
+		_ => Err(anyhow!("signature is missing fields")),
+	}
+}
+
+/// Checks whether a Jira ticket mention at `start..end` in `line` is only part
+/// of a URL or a file name, like `https://ci.example.com/JOB-1234/console` or
+/// `docs/ABC-123-design.md`.
+fn is_incidental_jira_ticket_mention(line: &str, start: usize, end: usize) -> bool {
+	lazy_static! {
+		/// Matches the rest of a file name, ending in an extension
+		static ref FILE_NAME_REST_REGEX: Regex =
+			Regex::new(r"^[\w\-./]*\.[A-Za-z][A-Za-z0-9]{0,4}$").unwrap();
+	}
+
+	// Find the whitespace-delimited token the mention is in
+	let token_start = line[..start]
+		.rfind(char::is_whitespace)
+		.map_or(0, |index| index + 1);
+	let token_end = line[end..]
+		.find(char::is_whitespace)
+		.map_or(line.len(), |index| end + index);
+
+	// Inside a URL
+	if line[token_start..start].contains("://") {
+		return true;
+	}
+
+	// Part of a file name, ignoring any punctuation that ends the sentence
+	let token_rest = line[end..token_end]
+		.trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '"', '\'', '`']);
+	FILE_NAME_REST_REGEX.is_match(token_rest)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_util::{full_hash, FixtureRepo};
+
+	/// Builds a log entry the way `get_complete_commit_list` splits them out of
+	/// the `git log` output.
+	fn log_entry(message: &str) -> String {
+		format!(
+			"{}\n{}\nAuthor\tauthor@example.com\t0\nCommitter\tcommitter@example.com\t0\n{message}",
+			full_hash("a1"),
+			full_hash("b2"),
+		)
+	}
+
+	fn parse_jira_tickets(
+		message: &str,
+		ticket_parsing_options: &TicketParsingOptions,
+	) -> Vec<String> {
+		let mut jira_tickets =
+			process_commit_entry(log_entry(message).as_str(), ticket_parsing_options)
+				.expect("the log entry is valid")
+				.jira_tickets;
+		jira_tickets.sort_unstable();
+		jira_tickets
+	}
+
+	#[test]
+	fn incidental_jira_ticket_mentions_are_classified() {
+		// The line, the ticket in it, and whether the (first) mention is incidental
+		let cases = [
+			// URLs
+			(
+				"See https://ci.example.com/JOB-1234/console",
+				"JOB-1234",
+				true,
+			),
+			("Logs at http://ci/JOB-1234.", "JOB-1234", true),
+			(
+				"[PROJ-12](https://jira.example.com/browse/PROJ-12)",
+				"PROJ-12",
+				false,
+			),
+			// File names
+			("Wrote up docs/ABC-123-design.md", "ABC-123", true),
+			("Attached ABC-123.txt.", "ABC-123", true),
+			("Moved `src/ABC-123/notes.md`", "ABC-123", true),
+			// Prose
+			("Fixes ABC-123.", "ABC-123", false),
+			("Follow-up to ABC-123, see the thread", "ABC-123", false),
+			("(ABC-123) is done", "ABC-123", false),
+			("ABC-123: Fix the build", "ABC-123", false),
+		];
+
+		for (line, jira_ticket, expected) in cases {
+			let start = line
+				.find(jira_ticket)
+				.expect("the case contains the ticket");
+			assert_eq!(
+				is_incidental_jira_ticket_mention(line, start, start + jira_ticket.len()),
+				expected,
+				"{line}"
+			);
+		}
+	}
+
+	#[test]
+	fn mentioned_jira_tickets_skip_urls_and_file_names() {
+		let mut ticket_parsing_options = TicketParsingOptions::ignored();
+		ticket_parsing_options.include_mentioned = true;
+		ticket_parsing_options.filter_mentioned = true;
+
+		// The commit message and the tickets it should be attributed to
+		let cases: [(&str, &[&str]); 6] = [
+			(
+				"Fix the build\n\nSee https://ci.example.com/JOB-1234/console",
+				&[],
+			),
+			(
+				"Fix the build\n\nSee [PROJ-12](https://jira.example.com/browse/PROJ-12)",
+				&["PROJ-12"],
+			),
+			("Add the design\n\nIt's in docs/ABC-123-design.md", &[]),
+			(
+				"ABC-123: Add the design\n\nIt's in docs/ABC-123-design.md",
+				&["ABC-123"],
+			),
+			(
+				"Fix the build\n\nThis finishes ABC-123 and DEF-45.",
+				&["ABC-123", "DEF-45"],
+			),
+			(
+				"Fix the build\n\nRelated to ABC-123 (see ABC-124.log)",
+				&["ABC-123"],
+			),
+		];
+
+		for (message, expected) in cases {
+			assert_eq!(
+				parse_jira_tickets(message, &ticket_parsing_options),
+				expected,
+				"{message}"
+			);
+		}
+	}
+
+	#[test]
+	fn mentioned_jira_tickets_are_kept_without_filtering() {
+		let mut ticket_parsing_options = TicketParsingOptions::ignored();
+		ticket_parsing_options.include_mentioned = true;
+
+		assert_eq!(
+			parse_jira_tickets(
+				"Fix the build\n\nSee https://ci.example.com/JOB-1234/console and \
+				 docs/ABC-123-design.md",
+				&ticket_parsing_options
+			),
+			["ABC-123", "JOB-1234"]
+		);
+	}
+
+	fn parse_svn_references(
+		message: &str,
+		ticket_parsing_options: &TicketParsingOptions,
+	) -> Vec<u32> {
+		process_commit_entry(log_entry(message).as_str(), ticket_parsing_options)
+			.expect("the log entry is valid")
+			.referenced_commits
+			.svn_commits
+	}
+
+	#[test]
+	fn svn_revision_references_are_found() {
+		let ticket_parsing_options = TicketParsingOptions::ignored();
+		// The line, and the SVN revisions it references
+		let cases: [(&str, &[u32]); 9] = [
+			("Merged r1234 from trunk", &[1234]),
+			("Reverts Rev. 123", &[123]),
+			("Back-port of revision #123", &[123]),
+			("Fixes the bug from revisions 10, 12-14", &[10, 12, 13, 14]),
+			("See commit(s) 57,59", &[57, 59]),
+			("rev 8 and REV 9", &[8, 9]),
+			// Bare `#123`s are usually issues, so they're left alone by default
+			("Fixes #123", &[]),
+			("Closes PR#123", &[]),
+			// Words that only end with an `r` aren't revisions
+			("Lower 4 of them", &[]),
+		];
+		for (line, expected) in cases {
+			assert_eq!(
+				parse_svn_references(line, &ticket_parsing_options),
+				expected,
+				"{line}"
+			);
+		}
+	}
+
+	#[test]
+	fn svn_hash_references_are_opt_in() {
+		let mut ticket_parsing_options = TicketParsingOptions::ignored();
+		assert!(parse_svn_references("Fixes #123", &ticket_parsing_options).is_empty());
+
+		ticket_parsing_options.svn_hash_references = true;
+		// The line, and the SVN revisions it references
+		let cases: [(&str, &[u32]); 4] = [
+			("Fixes #123", &[123]),
+			("(#45) and #46", &[45, 46]),
+			// Anything attached to the front is something else
+			("Closes PR#123", &[]),
+			("An &#123; entity", &[]),
+		];
+		for (line, expected) in cases {
+			assert_eq!(
+				parse_svn_references(line, &ticket_parsing_options),
+				expected,
+				"{line}"
+			);
+		}
+	}
+
+	#[test]
+	fn svn_locations_are_parsed_from_urls() {
+		let svn_layout = SvnLayout::default();
+		// The URL, and where it points in the SVN repo
+		let cases = [
+			(
+				"https://svn.example.com/repo/trunk",
+				SvnPathKind::Trunk,
+				"trunk",
+			),
+			(
+				"https://svn.example.com/repo/trunk/sub/dir",
+				SvnPathKind::Trunk,
+				"trunk",
+			),
+			(
+				"https://svn.example.com/repo/branches/release-1.2",
+				SvnPathKind::Branch,
+				"release-1.2",
+			),
+			// Nested names are kept whole
+			(
+				"https://svn.example.com/repo/branches/team/feature",
+				SvnPathKind::Branch,
+				"team/feature",
+			),
+			(
+				"https://svn.example.com/repo/tags/v1.0/",
+				SvnPathKind::Tag,
+				"v1.0",
+			),
+			// Escapes are decoded in the name
+			(
+				"https://svn.example.com/repo/branches/release%201.2",
+				SvnPathKind::Branch,
+				"release 1.2",
+			),
+			(
+				"https://svn.example.com/repo/tags/team%2Fv1.0",
+				SvnPathKind::Tag,
+				"team/v1.0",
+			),
+			(
+				"https://svn.example.com/repo/branches/caf%C3%A9/fix",
+				SvnPathKind::Branch,
+				"caf\u{e9}/fix",
+			),
+			// The last layout directory wins, in case the repo is hosted under one
+			(
+				"https://svn.example.com/branches/repo/trunk",
+				SvnPathKind::Trunk,
+				"trunk",
+			),
+			// A layout directory without a name after it isn't a branch
+			(
+				"https://svn.example.com/repo/branches",
+				SvnPathKind::Other,
+				"branches",
+			),
+			(
+				"https://svn.example.com/repo/sandbox%20area",
+				SvnPathKind::Other,
+				"sandbox area",
+			),
+		];
+
+		for (svn_url, kind, name) in cases {
+			assert_eq!(
+				svn_layout.locate(svn_url),
+				SvnLocation {
+					kind,
+					name: name.to_owned(),
+				},
+				"{svn_url}"
+			);
+		}
+	}
+
+	#[test]
+	fn svn_locations_follow_custom_layouts() {
+		let svn_layout = SvnLayout {
+			trunk:    "main".to_owned(),
+			branches: "dev".to_owned(),
+			tags:     "releases".to_owned(),
+		};
+
+		assert_eq!(
+			svn_layout.locate("https://svn.example.com/repo/dev/team/feature"),
+			SvnLocation {
+				kind: SvnPathKind::Branch,
+				name: "team/feature".to_owned(),
+			}
+		);
+		assert_eq!(
+			svn_layout.locate("https://svn.example.com/repo/releases/1.0"),
+			SvnLocation {
+				kind: SvnPathKind::Tag,
+				name: "1.0".to_owned(),
+			}
+		);
+		assert_eq!(
+			svn_layout.locate("https://svn.example.com/repo/trunk").kind,
+			SvnPathKind::Other
+		);
+	}
+
+	#[test]
+	fn excluded_jira_tickets_are_dropped() {
+		// The same anchoring that `--ticket-pattern-exclude` applies
+		let ticket_exclusions = [Regex::new("^(?:UTF-8)$").unwrap()];
+		let mut ticket_parsing_options = TicketParsingOptions::ignored();
+		ticket_parsing_options.exclusions = ticket_exclusions.as_slice();
+
+		assert_eq!(
+			parse_jira_tickets("PROJ-8: Read the file as UTF-8", &ticket_parsing_options),
+			["PROJ-8"]
+		);
+		assert!(parse_jira_tickets("UTF-8 handling", &ticket_parsing_options).is_empty());
+
+		ticket_parsing_options.include_mentioned = true;
+		assert_eq!(
+			parse_jira_tickets(
+				"Read the file as UTF-8\n\nThis is for PROJ-8, not UTF-16.",
+				&ticket_parsing_options
+			),
+			["PROJ-8", "UTF-16"]
+		);
+	}
+
+	#[test]
+	fn jira_tickets_are_found_in_notes_without_changing_the_message() {
+		let fixture_repo = FixtureRepo::new("notes");
+		fixture_repo.commit("PROJ-1: Fix the build\n\nThe cache was stale.");
+		fixture_repo.git(&["notes", "add", "--message", "Back-ported for PROJ-2"]);
+
+		// The message comes out the same as without the notes (apart from the
+		// trailing newlines, which Git adds after a body), with the delimiter on a line
+		// of its own after it
+		let log_entry = |include_notes: bool| {
+			fixture_repo.git(&[
+				"log",
+				"--max-count=1",
+				"--notes",
+				format!("--pretty=format:{}", get_log_format(include_notes)).as_str(),
+			])
+		};
+		let log_entry_with_notes = log_entry(true);
+		let (message, notes) = log_entry_with_notes
+			.split_once(LOG_NOTES_DELIMITER)
+			.expect("the notes delimiter is in the log entry");
+		assert!(message.ends_with('\n'), "{message:?}");
+		assert_eq!(message.trim_end(), log_entry(false));
+		assert_eq!(notes, "Back-ported for PROJ-2");
+
+		let mut ticket_parsing_options = TicketParsingOptions::ignored();
+		ticket_parsing_options.include_notes = true;
+		let commits =
+			get_complete_commit_list(fixture_repo.path(), &ticket_parsing_options, &[], false)
+				.expect("unable to collect the commits");
+		let mut jira_tickets = commits[0].jira_tickets.clone();
+		jira_tickets.sort_unstable();
+		assert_eq!(jira_tickets, ["PROJ-1", "PROJ-2"]);
+	}
+
+	#[test]
+	fn invalid_utf8_in_the_log_is_replaced_or_named() {
+		let good_revision = full_hash("c3");
+		let bad_revision = full_hash("d4");
+		// Two entries, the second of which has a Latin-1 `\u{e9}` in its message
+		let mut log_output = Vec::new();
+		for (git_revision, message) in [
+			(&good_revision, b"Plain subject".as_slice()),
+			(&bad_revision, b"Caf\xe9 menu\n\nFor PROJ-1".as_slice()),
+		] {
+			let header = [
+				format!("{LOG_COMMIT_DELIMITER}{git_revision}"),
+				String::new(),
+				"Author\tauthor@example.com\t0".to_owned(),
+				"Committer\tcommitter@example.com\t0".to_owned(),
+				String::new(),
+			]
+			.join("\n");
+			log_output.extend_from_slice(header.as_bytes());
+			log_output.extend_from_slice(message);
+		}
+		let mut command = Command::new("git");
+		command.arg("log");
+
+		let lossy_log_output = decode_log_output(&command, log_output.clone(), false)
+			.expect("the output is decoded lossily");
+		let commits = lossy_log_output
+			.split(LOG_COMMIT_DELIMITER)
+			.skip(1)
+			.map(|entry| {
+				process_commit_entry(entry, &TicketParsingOptions::ignored())
+					.expect("the log entry is valid")
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(commits.len(), 2);
+		assert_eq!(commits[0].subject, "Plain subject");
+		assert_eq!(commits[1].git_revision, bad_revision);
+		assert_eq!(commits[1].subject, "Caf\u{fffd} menu");
+
+		let error = decode_log_output(&command, log_output, true)
+			.expect_err("strict parsing rejects invalid UTF-8");
+		assert_eq!(
+			error.to_string(),
+			format!("the log entry for commit `{bad_revision}` isn't valid UTF-8")
+		);
+	}
+}
@@ -4,6 +4,16 @@
 // Constants
 pub const APPLICATION_PROPER_NAME: &str = "CLog";
 pub const APPLICATION_BIN_NAME: &str = env!("CARGO_PKG_NAME");
+pub const APPLICATION_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The commit this build was made from, or `unknown` if it wasn't built from a
+/// Git checkout (like when installed from crates.io).
+///
+/// This and the other build information are set by `build.rs`.
+pub const BUILD_GIT_COMMIT: &str = env!("CLOG_BUILD_GIT_COMMIT");
+/// The target triple this build was made for.
+pub const BUILD_TARGET: &str = env!("CLOG_BUILD_TARGET");
+/// The output of `rustc --version` for the compiler this build was made with.
+pub const BUILD_RUSTC_VERSION: &str = env!("CLOG_BUILD_RUSTC_VERSION");
 pub const SHA1_HASH_LENGTH: usize = 20;
 pub const SHA1_HASH_ASCII_LENGTH: usize = SHA1_HASH_LENGTH * 2;
 /// This value comes from a Git SVN migration, and prefixes the data about the
@@ -11,3 +21,11 @@ pub const SHA1_HASH_ASCII_LENGTH: usize = SHA1_HASH_LENGTH * 2;
 ///
 /// https://github.com/git/git/blob/master/git-svn.perl
 pub const GIT_SVN_ID_STR: &str = "git-svn-id";
+/// The path that means stdout instead of a file, by the usual convention.
+pub const STDOUT_PATH: &str = "-";
+/// How the group of commits without a Jira ticket is labelled.
+pub const NO_JIRA_TICKET_STR: &str = "*No Jira Ticket*";
+/// Stands in for the ticket in `--ticket-url`.
+pub const TICKET_URL_PLACEHOLDER: &str = "{ticket}";
+/// Marks merge commits when they're displayed.
+pub const MERGE_COMMIT_MARKER_STR: &str = " (M)";
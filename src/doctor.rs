@@ -0,0 +1,229 @@
+//! The module for checking that the environment and repo are set up the way
+//! the other subcommands need.
+
+// Uses
+use std::{
+	fmt::{Display, Formatter, Result as FmtResult},
+	path::Path,
+};
+
+use anyhow::Result;
+
+use crate::{
+	git_version::{
+		get_git_version,
+		GitVersion,
+		FOR_EACH_REF_CONTAINS_VERSION,
+		MERGE_BASE_IS_ANCESTOR_VERSION,
+		PATCH_ID_STABLE_VERSION,
+		REFNAME_LSTRIP_VERSION,
+	},
+	search::{
+		get_head_state,
+		get_remotes,
+		has_svn_metadata,
+		is_git_repo,
+		rev_parse_flag,
+		upstream_ref_if_possible,
+		HeadState,
+	},
+};
+
+// Constants
+/// The features that need a newer Git than the oldest one that works at all,
+/// so that an old Git can be reported with what it's missing.
+const VERSIONED_FEATURES: &[(&str, GitVersion)] = &[
+	("`--between-tags`", MERGE_BASE_IS_ANCESTOR_VERSION),
+	("`--collapse-duplicates`", PATCH_ID_STABLE_VERSION),
+	("`--hide-unreferenced`", FOR_EACH_REF_CONTAINS_VERSION),
+	("`--containment-strategy graph`", REFNAME_LSTRIP_VERSION),
+];
+
+// Types and Structures
+/// How a check turned out.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CheckStatus {
+	/// Everything is as expected.
+	Pass,
+	/// Something will limit or change the results, but nothing is broken.
+	Warn,
+	/// Something will keep the other subcommands from working at all.
+	Fail,
+}
+
+impl Display for CheckStatus {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str(match self {
+			Self::Pass => "PASS",
+			Self::Warn => "WARN",
+			Self::Fail => "FAIL",
+		})
+	}
+}
+
+/// The outcome of a single check.
+#[derive(Clone, Debug)]
+pub struct CheckResult {
+	pub status: CheckStatus,
+	pub name:   &'static str,
+	pub detail: String,
+}
+
+impl CheckResult {
+	fn new(status: CheckStatus, name: &'static str, detail: impl Into<String>) -> Self {
+		Self {
+			status,
+			name,
+			detail: detail.into(),
+		}
+	}
+}
+
+/// Runs every check against the environment and the repo, in order.
+///
+/// Checks that depend on an earlier one passing (like everything that needs
+/// Git to be installed) are left out when it doesn't, so the first failure is
+/// the one to fix.
+pub fn run_checks(repo_dir: &str) -> Result<Vec<CheckResult>> {
+	let mut results = Vec::new();
+
+	// Git itself
+	match get_git_version() {
+		Ok(git_version) => {
+			let unsupported_features = VERSIONED_FEATURES
+				.iter()
+				.filter(|(_, minimum_version)| git_version < *minimum_version)
+				.map(|(feature, minimum_version)| format!("{feature} (needs {minimum_version})"))
+				.collect::<Vec<_>>();
+			results.push(if unsupported_features.is_empty() {
+				CheckResult::new(CheckStatus::Pass, "Git", format!("version {git_version}"))
+			} else {
+				CheckResult::new(
+					CheckStatus::Warn,
+					"Git",
+					format!(
+						"version {git_version} is too old for: {}",
+						unsupported_features.join(", ")
+					),
+				)
+			});
+		}
+		Err(error) => {
+			results.push(CheckResult::new(
+				CheckStatus::Fail,
+				"Git",
+				format!("{error:#}"),
+			));
+			return Ok(results);
+		}
+	}
+
+	// The repo
+	if !Path::new(repo_dir).is_dir() {
+		results.push(CheckResult::new(
+			CheckStatus::Fail,
+			"Repo",
+			format!("`{repo_dir}` isn't a directory"),
+		));
+		return Ok(results);
+	}
+	if !is_git_repo(repo_dir)? {
+		results.push(CheckResult::new(
+			CheckStatus::Fail,
+			"Repo",
+			format!("`{repo_dir}` isn't inside a Git repo"),
+		));
+		return Ok(results);
+	}
+	results.push(CheckResult::new(
+		CheckStatus::Pass,
+		"Repo",
+		format!("`{repo_dir}` is a Git repo"),
+	));
+	results.push(if rev_parse_flag(repo_dir, "--is-bare-repository")? {
+		CheckResult::new(
+			CheckStatus::Warn,
+			"Working tree",
+			"the repo is bare, so filepaths can't be resolved against a working tree",
+		)
+	} else {
+		CheckResult::new(
+			CheckStatus::Pass,
+			"Working tree",
+			"the repo has a working tree",
+		)
+	});
+	results.push(if rev_parse_flag(repo_dir, "--is-shallow-repository")? {
+		CheckResult::new(
+			CheckStatus::Warn,
+			"History",
+			"the repo is a shallow clone, so older commits (and the links to them) are missing; \
+			 run `git fetch --unshallow` for complete results",
+		)
+	} else {
+		CheckResult::new(
+			CheckStatus::Pass,
+			"History",
+			"the full history is available",
+		)
+	});
+
+	// What the default revspec needs
+	results.push(match get_head_state(repo_dir)? {
+		HeadState::Branch(branch) => {
+			if let Some(upstream) = upstream_ref_if_possible(repo_dir, "HEAD")? {
+				CheckResult::new(
+					CheckStatus::Pass,
+					"HEAD",
+					format!("on `{branch}`, which tracks `{upstream}`"),
+				)
+			} else {
+				CheckResult::new(
+					CheckStatus::Warn,
+					"HEAD",
+					format!(
+						"on `{branch}`, which doesn't have an upstream, so `list` needs an \
+						 explicit revspec"
+					),
+				)
+			}
+		}
+		HeadState::Detached(_) => CheckResult::new(
+			CheckStatus::Warn,
+			"HEAD",
+			"detached, so `list` needs an explicit revspec",
+		),
+		HeadState::Unborn(branch) => CheckResult::new(
+			CheckStatus::Warn,
+			"HEAD",
+			format!("on `{branch}`, which doesn't have any commits yet"),
+		),
+	});
+	let remotes = get_remotes(repo_dir)?;
+	results.push(if remotes.is_empty() {
+		CheckResult::new(
+			CheckStatus::Warn,
+			"Remotes",
+			"there aren't any, so `search` finds nothing without `--local-branches`",
+		)
+	} else {
+		CheckResult::new(CheckStatus::Pass, "Remotes", remotes.join(", "))
+	});
+
+	// What `revmap` needs
+	results.push(if has_svn_metadata(repo_dir)? {
+		CheckResult::new(
+			CheckStatus::Pass,
+			"SVN metadata",
+			"found `git-svn-id` lines, so `revmap` has something to map",
+		)
+	} else {
+		CheckResult::new(
+			CheckStatus::Warn,
+			"SVN metadata",
+			"no commits have `git-svn-id` lines, so `revmap` will produce an empty map",
+		)
+	});
+
+	Ok(results)
+}
@@ -0,0 +1,174 @@
+//! The module for pieces of output that are written differently depending on
+//! where they're going, so that user-provided text (like a ticket prefix or a
+//! branch name) can't change the meaning of the text around it.
+
+// Uses
+use crate::util::sanitize_for_display;
+
+// Types and Structures
+/// Where rendered output is going.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RenderTarget {
+	/// The readable output, which is Markdown.
+	Markdown,
+	/// Output for other tools, which is written exactly as it is.
+	Plain,
+}
+
+/// A piece of an output line.
+#[derive(Clone, Copy, Debug)]
+pub enum Fragment<'a> {
+	/// Text that's shown as it is, apart from anything that would start a code
+	/// span reaching into the fragments around it.
+	///
+	/// Other Markdown is left alone, so that a `#` prefix can still make a
+	/// ticket into a tag.
+	Text(&'a str),
+	/// Inline code, like a hash or a branch name.
+	Code(&'a str),
+	/// Text that links somewhere.
+	Link { text: &'a str, url: &'a str },
+}
+
+impl<'a> Fragment<'a> {
+	/// Renders the fragment for the target, stripping anything that isn't
+	/// safe to print.
+	pub fn render(&self, target: RenderTarget) -> String {
+		match (self, target) {
+			(Self::Text(text) | Self::Code(text), RenderTarget::Plain) => {
+				sanitize_for_display(text).into_owned()
+			}
+			(Self::Link { text, url }, RenderTarget::Plain) => format!(
+				"{} ({})",
+				sanitize_for_display(text),
+				sanitize_for_display(url)
+			),
+			(Self::Text(text), RenderTarget::Markdown) => escape_code_span_delimiters(text),
+			(Self::Code(code), RenderTarget::Markdown) => render_code_span(code),
+			(Self::Link { text, url }, RenderTarget::Markdown) => format!(
+				"[{}](<{}>)",
+				escape_code_span_delimiters(text)
+					.replace('[', "\\[")
+					.replace(']', "\\]"),
+				sanitize_for_display(url)
+					.replace('<', "%3C")
+					.replace('>', "%3E")
+			),
+		}
+	}
+}
+
+/// Renders a series of fragments for the target, one after the other.
+pub fn render_fragments(fragments: &[Fragment], target: RenderTarget) -> String {
+	fragments
+		.iter()
+		.map(|fragment| fragment.render(target))
+		.collect()
+}
+
+/// Escapes the backticks in text, so they can't pair up with the backticks of
+/// a nearby code span.
+fn escape_code_span_delimiters(text: &str) -> String {
+	sanitize_for_display(text).replace('`', "\\`")
+}
+
+/// Wraps code in a code span that's delimited by more backticks than any run
+/// of them inside it, and padded if it starts or ends with one, the way
+/// CommonMark expects.
+fn render_code_span(code: &str) -> String {
+	let code = sanitize_for_display(code);
+	let longest_backtick_run = code
+		.split(|character| character != '`')
+		.map(str::len)
+		.max()
+		.unwrap_or(0);
+	let delimiter = "`".repeat(longest_backtick_run + 1);
+	if code.starts_with('`') || code.ends_with('`') {
+		format!("{delimiter} {code} {delimiter}")
+	} else {
+		format!("{delimiter}{code}{delimiter}")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn code_spans_outlast_the_backticks_inside_them() {
+		// The code, and the code span it's rendered as
+		let cases = [
+			("a1b2c3d4", "`a1b2c3d4`"),
+			("feature/a`b", "``feature/a`b``"),
+			("a``b", "```a``b```"),
+			// Backticks at the edges need padding, or they'd merge with the delimiter
+			("`start", "`` `start ``"),
+			("end`", "`` end` ``"),
+			("`", "`` ` ``"),
+			("``both``", "``` ``both`` ```"),
+		];
+		for (code, expected) in cases {
+			assert_eq!(render_code_span(code), expected, "{code}");
+			assert_eq!(
+				Fragment::Code(code).render(RenderTarget::Plain),
+				code,
+				"{code}"
+			);
+		}
+	}
+
+	#[test]
+	fn text_and_links_escape_code_span_delimiters() {
+		// The fragment, and how it's rendered as Markdown and as plain text
+		let cases = [
+			(Fragment::Text("PROJ-1"), "PROJ-1", "PROJ-1"),
+			(Fragment::Text("a`b``c"), "a\\`b\\`\\`c", "a`b``c"),
+			// Other Markdown is left alone
+			(
+				Fragment::Text("#PROJ-1 *note*"),
+				"#PROJ-1 *note*",
+				"#PROJ-1 *note*",
+			),
+			(
+				Fragment::Link {
+					text: "[`PROJ-1`]",
+					url:  "https://jira.example.com/browse/<PROJ-1>",
+				},
+				"[\\[\\`PROJ-1\\`\\]](<https://jira.example.com/browse/%3CPROJ-1%3E>)",
+				"[`PROJ-1`] (https://jira.example.com/browse/<PROJ-1>)",
+			),
+		];
+		for (fragment, expected_markdown, expected_plain) in cases {
+			assert_eq!(
+				fragment.render(RenderTarget::Markdown),
+				expected_markdown,
+				"{fragment:?}"
+			);
+			assert_eq!(
+				fragment.render(RenderTarget::Plain),
+				expected_plain,
+				"{fragment:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn prefixes_with_backticks_stay_out_of_code_spans() {
+		// A `#` prefix with a stray backtick, on a line with a hash after it
+		let fragments = [
+			Fragment::Text("- #`PROJ-1 on "),
+			Fragment::Code("release`1"),
+			Fragment::Text(" at "),
+			Fragment::Code("a1b2c3d4"),
+		];
+
+		assert_eq!(
+			render_fragments(&fragments, RenderTarget::Markdown),
+			"- #\\`PROJ-1 on ``release`1`` at `a1b2c3d4`"
+		);
+		assert_eq!(
+			render_fragments(&fragments, RenderTarget::Plain),
+			"- #`PROJ-1 on release`1 at a1b2c3d4"
+		);
+	}
+}
@@ -0,0 +1,103 @@
+//! The module for detecting the installed Git version, so that features that
+//! need a newer version can fail up-front instead of partway through a run.
+
+// Uses
+use std::{
+	fmt::{Display, Formatter, Result as FmtResult},
+	process::Command,
+	sync::OnceLock,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::util::run_command;
+
+// Constants
+/// `git for-each-ref --contains`, used for hiding unreferenced commits.
+pub const FOR_EACH_REF_CONTAINS_VERSION: GitVersion = GitVersion::new(2, 7, 0);
+/// `git patch-id --stable`, used for collapsing duplicate commits.
+pub const PATCH_ID_STABLE_VERSION: GitVersion = GitVersion::new(2, 0, 0);
+/// `git merge-base --is-ancestor`, used for resolving the range between tags.
+pub const MERGE_BASE_IS_ANCESTOR_VERSION: GitVersion = GitVersion::new(1, 8, 0);
+/// `git for-each-ref --format=%(refname:lstrip=2)`, used for the graph
+/// containment strategy.
+pub const REFNAME_LSTRIP_VERSION: GitVersion = GitVersion::new(2, 13, 0);
+
+// Statics
+static GIT_VERSION: OnceLock<GitVersion> = OnceLock::new();
+
+// Types and Structures
+/// A Git version, without any platform-specific suffixes.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct GitVersion {
+	pub major: u32,
+	pub minor: u32,
+	pub patch: u32,
+}
+
+impl GitVersion {
+	pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+		Self {
+			major,
+			minor,
+			patch,
+		}
+	}
+
+	/// Parses the output of `git --version`, which looks like `git version
+	/// 2.39.2`, possibly with a suffix like `.windows.1` or ` (Apple Git-143)`.
+	fn parse(version_output: &str) -> Option<Self> {
+		let version_str = version_output
+			.trim()
+			.strip_prefix("git version ")?
+			.split(' ')
+			.next()?;
+		let mut version_parts = version_str
+			.split('.')
+			.map(|version_part| version_part.parse::<u32>().ok());
+
+		Some(Self::new(
+			version_parts.next()??,
+			version_parts.next()??,
+			version_parts.next().flatten().unwrap_or(0),
+		))
+	}
+}
+
+impl Display for GitVersion {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+	}
+}
+
+/// Gets the installed Git version, which is only detected once per run.
+pub fn get_git_version() -> Result<GitVersion> {
+	if let Some(git_version) = GIT_VERSION.get() {
+		return Ok(*git_version);
+	}
+
+	let mut command = Command::new("git");
+	command.arg("--version");
+	let version_output = run_command(command)
+		.with_context(|| "unable to run Git, which needs to be installed and on the PATH")?;
+	let git_version = GitVersion::parse(version_output.as_str()).ok_or_else(|| {
+		anyhow!(
+			"unable to parse the Git version from `{}`",
+			version_output.trim()
+		)
+	})?;
+
+	Ok(*GIT_VERSION.get_or_init(|| git_version))
+}
+
+/// Ensures that the installed Git version is new enough for a feature.
+pub fn require_git_version(feature: &str, minimum_version: GitVersion) -> Result<()> {
+	let git_version = get_git_version()?;
+	if git_version < minimum_version {
+		return Err(anyhow!(
+			"{feature} needs Git {minimum_version} or newer, but Git {git_version} is installed"
+		));
+	}
+
+	Ok(())
+}
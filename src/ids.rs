@@ -0,0 +1,140 @@
+//! The module for identifiers that users (and commit messages) provide, so
+//! they're validated the same way everywhere.
+
+// Uses
+use std::{
+	fmt::{Display, Formatter, Result as FmtResult},
+	str::FromStr,
+};
+
+use anyhow::{anyhow, Error, Result};
+
+use crate::{constants::SHA1_HASH_ASCII_LENGTH, util::abbreviate};
+
+// Constants
+/// The shortest abbreviated hash that's accepted, which is the same minimum Git
+/// uses.
+pub const MIN_COMMIT_ID_LENGTH: usize = 4;
+
+// Types and Structures
+/// A full or abbreviated Git commit hash.
+///
+/// It's always lowercase, since that's how Git outputs hashes, but uppercase
+/// input is accepted.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CommitId(String);
+
+impl CommitId {
+	pub fn as_str(&self) -> &str {
+		self.0.as_str()
+	}
+
+	/// Whether this is a full hash, rather than an abbreviated one.
+	pub fn is_full(&self) -> bool {
+		self.0.len() == SHA1_HASH_ASCII_LENGTH
+	}
+
+	/// Whether this could be an abbreviation of `revision` (or is the same
+	/// hash). The comparison ignores case.
+	pub fn matches_prefix(&self, revision: &str) -> bool {
+		revision
+			.get(0..self.0.len())
+			.map_or(false, |revision_prefix| {
+				revision_prefix.eq_ignore_ascii_case(self.0.as_str())
+			})
+	}
+}
+
+impl FromStr for CommitId {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		if !(MIN_COMMIT_ID_LENGTH..=SHA1_HASH_ASCII_LENGTH).contains(&s.len()) {
+			return Err(anyhow!(
+				"`{s}` isn't a commit hash, since it has to be between {MIN_COMMIT_ID_LENGTH} and \
+				 {SHA1_HASH_ASCII_LENGTH} characters long"
+			));
+		}
+		if !s.chars().all(|c| c.is_ascii_hexdigit()) {
+			return Err(anyhow!(
+				"`{s}` isn't a commit hash, since it has characters that aren't hexadecimal"
+			));
+		}
+
+		Ok(Self(s.to_ascii_lowercase()))
+	}
+}
+
+/// A precision (like `{:.8}`) abbreviates the hash to that many characters.
+impl Display for CommitId {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match f.precision() {
+			Some(length) if length < self.0.len() => f.write_str(
+				abbreviate(self.0.as_str(), length).expect("the length is always within the hash"),
+			),
+			_ => f.write_str(self.0.as_str()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn commit_ids_reject_non_hex_input() {
+		for s in ["abcdefg", "12 34", "0x1234", "abcd-ef", ""] {
+			assert!(s.parse::<CommitId>().is_err(), "{s}");
+		}
+	}
+
+	#[test]
+	fn commit_ids_are_lowercased() {
+		let commit_id = "ABCDEF0123".parse::<CommitId>().expect("the hash is valid");
+
+		assert_eq!(commit_id.as_str(), "abcdef0123");
+	}
+
+	#[test]
+	fn commit_id_lengths_are_checked() {
+		let cases = [(3, false), (4, true), (40, true), (41, false)];
+
+		for (length, expected) in cases {
+			let s = "a".repeat(length);
+			assert_eq!(s.parse::<CommitId>().is_ok(), expected, "length {length}");
+		}
+		assert!("a".repeat(40).parse::<CommitId>().unwrap().is_full());
+		assert!(!"a".repeat(39).parse::<CommitId>().unwrap().is_full());
+	}
+
+	#[test]
+	fn commit_ids_match_prefixes() {
+		let revision = "0123456789abcdef0123456789abcdef01234567";
+		// The ID and whether it's an abbreviation of the revision
+		let cases = [
+			("0123", true),
+			("0123456789ABCDEF", true),
+			(revision, true),
+			("1234", false),
+			("0124", false),
+		];
+
+		for (s, expected) in cases {
+			let commit_id = s.parse::<CommitId>().expect("the hash is valid");
+			assert_eq!(commit_id.matches_prefix(revision), expected, "{s}");
+		}
+		// A revision that's shorter than the ID can't match it
+		assert!(!"01234".parse::<CommitId>().unwrap().matches_prefix("0123"));
+		// The revision can be in either case too
+		assert!("abcd".parse::<CommitId>().unwrap().matches_prefix("ABCDEF"));
+	}
+
+	#[test]
+	fn commit_ids_display_abbreviated() {
+		let commit_id = "0123456789abcdef".parse::<CommitId>().unwrap();
+
+		assert_eq!(format!("{commit_id:.7}"), "0123456");
+		assert_eq!(format!("{commit_id:.20}"), "0123456789abcdef");
+		assert_eq!(format!("{commit_id}"), "0123456789abcdef");
+	}
+}
@@ -5,49 +5,161 @@ use std::collections::{BTreeMap, HashMap};
 
 use anyhow::{anyhow, Result};
 
-use crate::collection::Commit;
+use crate::{
+	collection::{AnnotatedTag, Commit},
+	ids::CommitId,
+	util::{is_strict_parsing, is_verbose},
+};
+
+// Constants
+/// Every kind of link, for when there's no reason to leave any out.
+pub const ALL_LINK_KINDS: &[LinkKind] = &[LinkKind::Git, LinkKind::Svn];
 
 // Types and Structures
 // pub type Sha1Hash = [u8; SHA1_HASH_LENGTH];
 
+/// The kinds of references in commit messages that link commits together.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LinkKind {
+	/// Git hashes, like the ones in cherry-pick notes.
+	Git,
+	/// SVN revisions, like `merged r1500-1520`.
+	Svn,
+}
+
+/// Counts of what was noticed while building an index.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IndexStatistics {
+	/// References from commits to themselves, which were dropped.
+	pub self_references_dropped:   usize,
+	/// Git hash references from commits to their own parents, which were
+	/// dropped.
+	pub parent_references_dropped: usize,
+}
+
 #[derive(Debug)]
 pub struct Index<'a> {
 	git_revision_map:        BTreeMap<&'a str, &'a Commit>,
+	log_position_map:        HashMap<&'a str, usize>,
+	annotated_tag_map:       BTreeMap<&'a str, &'a str>,
 	svn_to_git_revision_map: HashMap<u32, &'a str>,
+	jira_ticket_map:         HashMap<&'a str, Vec<&'a Commit>>,
 	forward_references:      HashMap<&'a Commit, Vec<&'a Commit>>,
 	backward_references:     HashMap<&'a Commit, Vec<&'a Commit>>,
+	statistics:              IndexStatistics,
 }
 
 impl<'a> Index<'a> {
-	pub fn new(commits: &'a [Commit]) -> Result<Self> {
+	/// Builds the index, only linking commits through the kinds of references
+	/// in `link_kinds`. The other kinds are skipped entirely.
+	///
+	/// The commits are expected to be in log order, which is what
+	/// `log_position` reports.
+	pub fn new(
+		commits: &'a [Commit],
+		annotated_tags: &'a [AnnotatedTag],
+		link_kinds: &[LinkKind],
+	) -> Result<Self> {
 		// Build the lookup maps
 		let mut git_revision_map = BTreeMap::new();
+		let mut log_position_map = HashMap::with_capacity(commits.len());
 		let mut svn_to_git_revision_map = HashMap::new();
-		for commit in commits {
+		let mut jira_ticket_map: HashMap<&str, Vec<&Commit>> = HashMap::new();
+		for (log_position, commit) in commits.iter().enumerate() {
 			// Cache the Git revision number for partial lookup later
 			git_revision_map.insert(commit.git_revision.as_str(), commit);
+			log_position_map.insert(commit.git_revision.as_str(), log_position);
 
 			// Cache the SVN to Git revision relationship
+			// Later commits replace earlier ones with the same SVN revision, unless parsing
+			// strictly
 			if let Some(svn_info) = &commit.svn_info {
-				svn_to_git_revision_map.insert(svn_info.svn_revision, commit.git_revision.as_str());
+				if let Some(previous_git_revision) = svn_to_git_revision_map
+					.insert(svn_info.svn_revision, commit.git_revision.as_str())
+				{
+					if is_strict_parsing() && previous_git_revision != commit.git_revision {
+						return Err(anyhow!(
+							"SVN revision `{}` belongs to both commit `{previous_git_revision}` \
+							 and commit `{}`",
+							svn_info.svn_revision,
+							commit.git_revision
+						));
+					}
+				}
+			}
+
+			// Cache the Jira tickets, in log order
+			for jira_ticket in &commit.jira_tickets {
+				jira_ticket_map
+					.entry(jira_ticket.as_str())
+					.or_default()
+					.push(commit);
 			}
 		}
 
+		// Cache the annotated tag hashes, so that references to them can be followed
+		// to the commits they point to
+		let annotated_tag_map = annotated_tags
+			.iter()
+			.map(|annotated_tag| {
+				(
+					annotated_tag.tag_revision.as_str(),
+					annotated_tag.commit_revision.as_str(),
+				)
+			})
+			.collect();
+
 		let mut index = Self {
 			git_revision_map,
+			log_position_map,
+			annotated_tag_map,
 			svn_to_git_revision_map,
+			jira_ticket_map,
 			forward_references: HashMap::new(),
 			backward_references: HashMap::new(),
+			statistics: IndexStatistics::default(),
 		};
 
 		// Build the reference maps using the functionality provided by the first stage
 		let mut forward_references: HashMap<&Commit, Vec<&Commit>> = HashMap::new();
 		let mut backward_references: HashMap<&Commit, Vec<&Commit>> = HashMap::new();
+		let mut statistics = IndexStatistics::default();
+		let strict_parsing = is_strict_parsing();
+		let svn_revision_range = index
+			.svn_to_git_revision_map
+			.keys()
+			.min()
+			.zip(index.svn_to_git_revision_map.keys().max())
+			.map(|(first_svn_revision, last_svn_revision)| {
+				*first_svn_revision..=*last_svn_revision
+			});
+		let follow_git_references = link_kinds.contains(&LinkKind::Git);
+		let follow_svn_references = link_kinds.contains(&LinkKind::Svn);
 		for commit in commits {
 			// Follow Git revision references
-			for git_revision in &commit.referenced_commits.git_commits {
+			let git_revisions = if follow_git_references {
+				commit.referenced_commits.git_commits.as_slice()
+			} else {
+				&[]
+			};
+			for git_revision in git_revisions {
 				// Lookup the reference
-				if let Ok(referenced_commit) = index.lookup_git_revision(git_revision.as_str()) {
+				if let Ok(referenced_commit) = index.lookup_git_revision(git_revision) {
+					// A commit can quote its own hash (like in a rewritten cherry-pick note),
+					// but it should never be linked to itself
+					if referenced_commit == commit {
+						statistics.self_references_dropped += 1;
+						continue;
+					}
+					// Merges often name the commits they merge, but the parent relationship
+					// already covers that, and the link would only add useless nesting
+					if commit
+						.parent_revisions
+						.contains(&referenced_commit.git_revision)
+					{
+						statistics.parent_references_dropped += 1;
+						continue;
+					}
 					forward_references
 						.entry(commit)
 						.and_modify(|referenced_commits| referenced_commits.push(referenced_commit))
@@ -57,8 +169,16 @@ impl<'a> Index<'a> {
 						.and_modify(|referencing_commits| referencing_commits.push(commit))
 						.or_insert_with(|| vec![commit]);
 				} else {
+					// With strict parsing, anything that looks like a real hash has to resolve
+					if strict_parsing && is_likely_a_real_git_revision(git_revision.as_str()) {
+						return Err(anyhow!(
+							"Git revision `{git_revision}` referenced by commit `{}` could not be \
+							 found",
+							commit.git_revision
+						));
+					}
 					#[cfg(debug_assertions)]
-					if is_likely_a_real_git_revision(git_revision) {
+					if is_likely_a_real_git_revision(git_revision.as_str()) {
 						eprintln!(
 							"[WARNING] Git revision `{git_revision}` referenced by commit `{}` \
 							 could not be found.",
@@ -69,9 +189,18 @@ impl<'a> Index<'a> {
 			}
 
 			// Follow SVN revision references
-			for svn_revision in &commit.referenced_commits.svn_commits {
+			let svn_revisions = if follow_svn_references {
+				commit.referenced_commits.svn_commits.as_slice()
+			} else {
+				&[]
+			};
+			for svn_revision in svn_revisions {
 				// Lookup the reference
 				if let Ok(referenced_commit) = index.lookup_svn_revision(*svn_revision) {
+					if referenced_commit == commit {
+						statistics.self_references_dropped += 1;
+						continue;
+					}
 					forward_references
 						.entry(commit)
 						.and_modify(|referenced_commits| referenced_commits.push(referenced_commit))
@@ -81,6 +210,20 @@ impl<'a> Index<'a> {
 						.and_modify(|referencing_commits| referencing_commits.push(commit))
 						.or_insert_with(|| vec![commit]);
 				} else {
+					// With strict parsing, a revision within the mirrored range has to resolve
+					// Ones outside of it are usually from paths that weren't mirrored
+					if strict_parsing
+						&& svn_revision_range
+							.as_ref()
+							.map_or(false, |svn_revision_range| {
+								svn_revision_range.contains(svn_revision)
+							}) {
+						return Err(anyhow!(
+							"SVN revision `{svn_revision}` referenced by commit `{}` could not be \
+							 found, even though it's within the mirrored revisions",
+							commit.git_revision
+						));
+					}
 					#[cfg(debug_assertions)]
 					{
 						eprintln!(
@@ -94,36 +237,90 @@ impl<'a> Index<'a> {
 		}
 		index.forward_references = forward_references;
 		index.backward_references = backward_references;
+		index.statistics = statistics;
+		if is_verbose() {
+			eprintln!(
+				"[VERBOSE] Dropped {} self-reference(s) and {} reference(s) to parent commits \
+				 while indexing",
+				statistics.self_references_dropped, statistics.parent_references_dropped
+			);
+		}
 
 		// Return the completed index
 		Ok(index)
 	}
 
-	pub fn lookup_git_revision(&self, partial_revision: &str) -> Result<&'a Commit> {
+	pub fn statistics(&self) -> IndexStatistics {
+		self.statistics
+	}
+
+	pub fn lookup_git_revision(&self, commit_id: &CommitId) -> Result<&'a Commit> {
 		// This is a little complicated, but it uses the binary tree to quickly find
 		// full revisions that match the provided partial one
+		let partial_revision = commit_id.as_str();
 		let matching_revisions = self
 			.git_revision_map
 			.range(partial_revision..)
 			.take_while(|full_git_revision_entry| {
-				full_git_revision_entry.0.starts_with(partial_revision)
+				commit_id.matches_prefix(full_git_revision_entry.0)
 			})
 			.collect::<Vec<_>>();
 
 		// Handle the different cases for the number of potential matches
 		match matching_revisions.len() {
+			0 => self.lookup_annotated_tag(commit_id),
+			1 => Ok(matching_revisions[0].1),
+			_ => Err(anyhow!(
+				"multiple matching full revisions for the provided partial revision \
+				 \"{partial_revision}\" and no way to tell which is the correct one"
+			)),
+		}
+	}
+
+	/// Looks up an annotated tag's hash, dereferencing it to the commit it
+	/// points to.
+	fn lookup_annotated_tag(&self, commit_id: &CommitId) -> Result<&'a Commit> {
+		// This works the same way as the lookup for commits
+		let partial_revision = commit_id.as_str();
+		let matching_tags = self
+			.annotated_tag_map
+			.range(partial_revision..)
+			.take_while(|full_tag_revision_entry| {
+				commit_id.matches_prefix(full_tag_revision_entry.0)
+			})
+			.collect::<Vec<_>>();
+
+		match matching_tags.len() {
 			0 => Err(anyhow!(
 				"no matching full revision for the provided partial revision \
 				 \"{partial_revision}\""
 			)),
-			1 => Ok(matching_revisions[0].1),
+			1 => self
+				.git_revision_map
+				.get(matching_tags[0].1)
+				.copied()
+				.ok_or_else(|| {
+					anyhow!(
+						"the annotated tag matching the provided partial revision \
+						 \"{partial_revision}\" points to a commit that isn't in the index"
+					)
+				}),
 			_ => Err(anyhow!(
-				"multiple matching full revisions for the provided partial revision \
+				"multiple matching annotated tags for the provided partial revision \
 				 \"{partial_revision}\" and no way to tell which is the correct one"
 			)),
 		}
 	}
 
+	/// Looks up a full hash that's known to be a commit, like one straight from
+	/// Git's output.
+	///
+	/// This skips the validation and prefix matching of `lookup_git_revision`,
+	/// since it's used in hot loops over the commit graph.
+	pub fn lookup_full_git_revision(&self, git_revision: &str) -> Option<&'a Commit> {
+		self.git_revision_map.get(git_revision).copied()
+	}
+
 	pub fn lookup_svn_revision(&self, svn_revision: u32) -> Result<&'a Commit> {
 		// Lookup the SVN revision and get the corresponding Git revision
 		let git_revision = self
@@ -140,6 +337,41 @@ impl<'a> Index<'a> {
 		))
 	}
 
+	/// Gets where a commit is in the log the index was built from, so that
+	/// commits gathered from different places can be put back in log order.
+	pub fn log_position(&self, commit: &Commit) -> Option<usize> {
+		self.log_position_map
+			.get(commit.git_revision.as_str())
+			.copied()
+	}
+
+	/// Gets all commits that belong to a Jira ticket, in log order.
+	pub fn lookup_jira_ticket(&self, jira_ticket: &str) -> &[&'a Commit] {
+		self.jira_ticket_map
+			.get(jira_ticket)
+			.map_or(&[], Vec::as_slice)
+	}
+
+	/// Gets all commits that belong to any of the Jira tickets, in log order.
+	///
+	/// Commits that belong to more than one of them are only listed once.
+	pub fn lookup_jira_tickets<S>(&self, jira_tickets: &[S]) -> Vec<&'a Commit>
+	where
+		S: AsRef<str>,
+	{
+		// Each ticket's commits are in log order already, but they have to be put
+		// back in log order when there are several tickets
+		let mut commits = jira_tickets
+			.iter()
+			.flat_map(|jira_ticket| self.lookup_jira_ticket(jira_ticket.as_ref()))
+			.copied()
+			.collect::<Vec<_>>();
+		commits.sort_unstable_by_key(|commit| self.log_position(commit));
+		commits.dedup();
+
+		commits
+	}
+
 	pub fn get_commit_forward_references(&self, commit: &'a Commit) -> Vec<&'a Commit> {
 		self.forward_references
 			.get(commit)
@@ -171,3 +403,224 @@ fn is_likely_a_real_git_revision(potential_git_revision: &str) -> bool {
 	potential_git_revision.contains(ASCII_HEX_ALPHA_CHARS)
 		&& !is_repeated_char(potential_git_revision)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		collection::SvnInfo,
+		test_util::{commit, commit_id, full_hash},
+	};
+
+	fn with_svn_revision(mut commit: Commit, svn_revision: u32) -> Commit {
+		commit.svn_info = Some(SvnInfo {
+			svn_url: "https://svn.example.com/repo/trunk".to_owned(),
+			svn_revision,
+			repository_uuid: "00000000-0000-0000-0000-000000000000".to_owned(),
+		});
+		commit
+	}
+
+	#[test]
+	fn self_references_are_dropped() {
+		let first_revision = full_hash("a1");
+		let second_revision = full_hash("b2");
+		// The second commit quotes its own abbreviated hash and its own SVN revision,
+		// along with a real reference to the first commit
+		let first_commit = with_svn_revision(commit(first_revision.as_str(), &[]), 100);
+		let mut second_commit = with_svn_revision(
+			commit(second_revision.as_str(), &[first_revision.as_str()]),
+			101,
+		);
+		second_commit.referenced_commits.git_commits = vec![commit_id(&second_revision[..8])];
+		second_commit.referenced_commits.svn_commits = vec![101, 100];
+		let commits = [second_commit, first_commit];
+
+		let index = Index::new(&commits, &[], ALL_LINK_KINDS).expect("the index builds");
+
+		assert_eq!(index.statistics().self_references_dropped, 2);
+		assert_eq!(index.statistics().parent_references_dropped, 0);
+		assert_eq!(
+			index.get_commit_forward_references(&commits[0]),
+			[&commits[1]]
+		);
+		assert_eq!(
+			index.get_commit_backward_references(&commits[0]),
+			Vec::<&Commit>::new()
+		);
+		assert_eq!(
+			index.get_commit_backward_references(&commits[1]),
+			[&commits[0]]
+		);
+	}
+
+	#[test]
+	fn self_references_leave_the_maps_empty() {
+		let revision = full_hash("c3");
+		let mut commit = with_svn_revision(commit(revision.as_str(), &[]), 200);
+		commit.referenced_commits.git_commits = vec![commit_id(&revision[..7])];
+		commit.referenced_commits.svn_commits = vec![200];
+		let commits = [commit];
+
+		let index = Index::new(&commits, &[], ALL_LINK_KINDS).expect("the index builds");
+
+		assert_eq!(index.statistics().self_references_dropped, 2);
+		assert!(index.forward_references.is_empty());
+		assert!(index.backward_references.is_empty());
+	}
+
+	#[test]
+	fn parent_references_are_dropped() {
+		let base_revision = full_hash("d4");
+		let feature_revision = full_hash("e5");
+		let merge_revision = full_hash("f6");
+		let cherry_picked_revision = full_hash("0a7");
+		// The merge names the feature commit it merges (one of its parents), and the
+		// commit it was cherry-picked from (which isn't)
+		let base_commit = commit(base_revision.as_str(), &[]);
+		let feature_commit = commit(feature_revision.as_str(), &[base_revision.as_str()]);
+		let cherry_picked_commit = commit(cherry_picked_revision.as_str(), &[]);
+		let mut merge_commit = commit(
+			merge_revision.as_str(),
+			&[base_revision.as_str(), feature_revision.as_str()],
+		);
+		merge_commit.referenced_commits.git_commits = vec![
+			commit_id(feature_revision.as_str()),
+			commit_id(&cherry_picked_revision[..10]),
+		];
+		let commits = [
+			merge_commit,
+			feature_commit,
+			cherry_picked_commit,
+			base_commit,
+		];
+
+		let index = Index::new(&commits, &[], ALL_LINK_KINDS).expect("the index builds");
+
+		assert_eq!(index.statistics().parent_references_dropped, 1);
+		assert_eq!(index.statistics().self_references_dropped, 0);
+		assert_eq!(
+			index.get_commit_forward_references(&commits[0]),
+			[&commits[2]]
+		);
+		assert_eq!(
+			index.get_commit_backward_references(&commits[1]),
+			Vec::<&Commit>::new()
+		);
+		assert_eq!(
+			index.get_commit_backward_references(&commits[2]),
+			[&commits[0]]
+		);
+	}
+
+	#[test]
+	fn jira_ticket_lookups_are_in_log_order() {
+		// The commits are in log order, and the last one belongs to both tickets
+		let mut commits = ["a1", "b2", "c3", "d4"]
+			.iter()
+			.map(|prefix| commit(full_hash(prefix).as_str(), &[]))
+			.collect::<Vec<_>>();
+		commits[0].jira_tickets = vec!["PROJ-2".to_owned()];
+		commits[1].jira_tickets = vec!["PROJ-1".to_owned()];
+		commits[3].jira_tickets = vec!["PROJ-1".to_owned(), "PROJ-2".to_owned()];
+
+		let index = Index::new(&commits, &[], ALL_LINK_KINDS).expect("the index builds");
+
+		assert_eq!(
+			index.lookup_jira_ticket("PROJ-1"),
+			[&commits[1], &commits[3]]
+		);
+		assert_eq!(index.lookup_jira_ticket("PROJ-3"), Vec::<&Commit>::new());
+		// The order the tickets are given in doesn't matter
+		for jira_tickets in [["PROJ-1", "PROJ-2"], ["PROJ-2", "PROJ-1"]] {
+			assert_eq!(
+				index.lookup_jira_tickets(&jira_tickets),
+				[&commits[0], &commits[1], &commits[3]]
+			);
+		}
+	}
+
+	#[test]
+	fn annotated_tag_references_resolve_to_the_tagged_commit() {
+		let tagged_revision = full_hash("a1");
+		let tag_object_revision = full_hash("9f8");
+		let referencing_revision = full_hash("b2");
+		// The second commit quotes the tag object's hash, not the commit it points to
+		let tagged_commit = commit(tagged_revision.as_str(), &[]);
+		let mut referencing_commit = commit(referencing_revision.as_str(), &[]);
+		referencing_commit.referenced_commits.git_commits =
+			vec![commit_id(&tag_object_revision[..10])];
+		let commits = [referencing_commit, tagged_commit];
+		let annotated_tags = [AnnotatedTag {
+			tag_revision:    tag_object_revision.clone(),
+			commit_revision: tagged_revision.clone(),
+		}];
+
+		let index =
+			Index::new(&commits, &annotated_tags, ALL_LINK_KINDS).expect("the index builds");
+
+		assert_eq!(
+			index
+				.lookup_git_revision(&commit_id(&tag_object_revision[..10]))
+				.expect("the tag resolves"),
+			&commits[1]
+		);
+		assert_eq!(
+			index
+				.lookup_git_revision(&commit_id(tag_object_revision.as_str()))
+				.expect("the full tag hash resolves"),
+			&commits[1]
+		);
+		assert_eq!(
+			index.get_commit_forward_references(&commits[0]),
+			[&commits[1]]
+		);
+		// Without the tag, the hash doesn't resolve to anything
+		let untagged_index = Index::new(&commits, &[], ALL_LINK_KINDS).expect("the index builds");
+		assert!(untagged_index
+			.lookup_git_revision(&commit_id(&tag_object_revision[..10]))
+			.is_err());
+	}
+
+	#[test]
+	fn only_the_requested_link_kinds_are_followed() {
+		let first_revision = full_hash("a1");
+		let second_revision = full_hash("b2");
+		let third_revision = full_hash("c3");
+		let fourth_revision = full_hash("d4");
+		// The last commit references the first by hash, and the middle two by SVN
+		// revision
+		let first_commit = with_svn_revision(commit(first_revision.as_str(), &[]), 100);
+		let second_commit = with_svn_revision(commit(second_revision.as_str(), &[]), 101);
+		let third_commit = with_svn_revision(commit(third_revision.as_str(), &[]), 102);
+		let mut fourth_commit = with_svn_revision(commit(fourth_revision.as_str(), &[]), 103);
+		fourth_commit.referenced_commits.git_commits = vec![commit_id(&first_revision[..8])];
+		fourth_commit.referenced_commits.svn_commits = vec![101, 102];
+		let commits = [fourth_commit, third_commit, second_commit, first_commit];
+
+		// The kinds of links to follow, and the number of edges each way
+		let cases: [(&[LinkKind], usize); 4] = [
+			(ALL_LINK_KINDS, 3),
+			(&[LinkKind::Git], 1),
+			(&[LinkKind::Svn], 2),
+			(&[], 0),
+		];
+		for (link_kinds, expected) in cases {
+			let index = Index::new(&commits, &[], link_kinds).expect("the index builds");
+
+			let count_edges = |references: &HashMap<&Commit, Vec<&Commit>>| {
+				references.values().map(Vec::len).sum::<usize>()
+			};
+			assert_eq!(
+				count_edges(&index.forward_references),
+				expected,
+				"{link_kinds:?}"
+			);
+			assert_eq!(
+				count_edges(&index.backward_references),
+				expected,
+				"{link_kinds:?}"
+			);
+		}
+	}
+}
@@ -41,98 +41,646 @@ mod cli;
 mod clipboard;
 mod collection;
 mod constants;
+mod doctor;
+mod fragment;
+mod git_version;
+mod ids;
 mod index;
 mod multi_writer;
+mod pager;
+mod run_log;
 mod search;
+#[cfg(test)]
+mod test_util;
 mod util;
 mod writing;
 
 // Uses
 use std::{
-	collections::{HashMap, HashSet},
-	io::{stdout, Write},
+	borrow::Cow,
+	cmp::Reverse,
+	collections::{BTreeSet, HashMap, HashSet},
+	env,
+	fs::{canonicalize, create_dir_all, read_to_string},
+	io::{stdout, IsTerminal, Write},
+	iter,
+	ops::Range,
+	path::PathBuf,
 	str::from_utf8,
+	thread,
+	time::{Instant, SystemTime},
 };
 
-use anyhow::{Context, Result};
-use clap::parser::ValuesRef;
+use anyhow::{anyhow, Context, Result};
+use clap::{
+	parser::{ValueSource, ValuesRef},
+	ArgMatches,
+};
+use regex::Regex;
 use shell_words::split as split_shell_words;
+use terminal_size::{terminal_size, Width};
 
 use crate::{
 	cli::build_cli,
-	clipboard::copy_str_to_clipboard,
-	collection::{get_complete_commit_list, Commit},
-	index::Index,
+	clipboard::{clipboard_reads_stdin, copy_str_to_clipboard},
+	collection::{
+		build_revision_map,
+		get_annotated_tags,
+		get_complete_commit_list,
+		AnnotatedTag,
+		Commit,
+		SvnLayout,
+		TicketParsingOptions,
+		BOT_AUTHOR_PATTERNS,
+	},
+	constants::{
+		APPLICATION_PROPER_NAME,
+		APPLICATION_VERSION,
+		BUILD_GIT_COMMIT,
+		BUILD_RUSTC_VERSION,
+		BUILD_TARGET,
+		NO_JIRA_TICKET_STR,
+		SHA1_HASH_ASCII_LENGTH,
+		STDOUT_PATH,
+		TICKET_URL_PLACEHOLDER,
+	},
+	doctor::{run_checks, CheckStatus},
+	fragment::{render_fragments, Fragment, RenderTarget},
+	git_version::{
+		get_git_version,
+		require_git_version,
+		FOR_EACH_REF_CONTAINS_VERSION,
+		MERGE_BASE_IS_ANCESTOR_VERSION,
+		PATCH_ID_STABLE_VERSION,
+		REFNAME_LSTRIP_VERSION,
+	},
+	ids::CommitId,
+	index::{Index, LinkKind, ALL_LINK_KINDS},
 	multi_writer::MultiWriter,
+	pager::Pager,
+	run_log::{append_run_record, RunDetails, RunRecord},
 	search::{
 		build_commit_inclusion_tree,
+		collapse_duplicate_commits,
+		count_divergent_commits,
 		flatten_inclusion_tree,
+		get_branch_tip_dates,
+		get_branch_tips,
+		get_branch_tips_for_containment,
 		get_branches_containing,
+		get_head_state,
+		get_latest_tag,
+		get_locations_containing,
+		get_newest_merge_base,
+		get_patch_ids,
+		get_primary_directories,
+		get_remotes,
+		get_repo_root,
 		get_search_results,
+		get_tag_kind,
+		get_tag_tips,
+		get_tags_by_version,
 		get_tags_containing,
+		get_trivial_commits,
+		group_identical_branches,
+		group_locations_by_commit_set,
+		is_ancestor,
+		peel_to_commit,
+		remove_unreferenced_commits,
+		strip_remote_prefixes,
+		upstream_ref_if_possible,
+		ContainmentStrategy,
+		FlattenedCommit,
 		IncludedCommit,
+		LinkTraversal,
+		TagKind,
 	},
-	util::sortable_jira_ticket,
-	writing::{write_to_bin, write_to_markdown},
+	util::{
+		abbreviate,
+		is_absolute_path_str,
+		is_verbose,
+		is_windows_absolute_path_str,
+		jira_ticket_variant_key,
+		json_string,
+		read_stdin_lines,
+		relative_path_under,
+		sanitize_for_display,
+		set_git_retries,
+		set_strict_parsing,
+		set_verbose,
+		sortable_jira_ticket,
+		take_recorded_commands,
+		truncate_with_ellipsis,
+	},
+	writing::{write_svn_fanin_report, write_to_bin, write_to_markdown, MarkdownStyle},
 };
 
 // Constants
-const NO_JIRA_TICKET_STR: &str = "*No Jira Ticket*";
-const MERGE_COMMIT_MARKER_STR: &str = " (M)";
+const INDENTATION_STR: &str = "\t";
+const COMPACT_INDENTATION_STR: &str = "  ";
+/// Terminals narrower than this get the compact output automatically.
+const COMPACT_TERMINAL_WIDTH_THRESHOLD: u16 = 100;
+/// Commit subjects longer than this are cut short in the compact output.
+const COMPACT_SUBJECT_LENGTH: usize = 60;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+/// How many tickets to list in the mention impact report.
+const MENTION_IMPACT_TOP_TICKET_COUNT: usize = 10;
+/// The environment variable with the repo to use when `--repo` isn't provided.
+const REPO_ENV_VAR: &str = "CLOG_REPO";
+
+// Types and Structures
+/// How `list` orders the Jira tickets.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TicketSortOrder {
+	/// By project, then issue number.
+	Ticket,
+	/// By when the earliest commit for each ticket was committed.
+	Date,
+}
+
+/// How `search` orders the branches in each set of the results.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum BranchSortOrder {
+	/// By name.
+	Name,
+	/// By the committer date of each branch's tip, newest first.
+	Date,
+}
+
+/// How `compare` detects cherry-picks between the two objects.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CherryPickEquivalence {
+	/// Commits that reference each other in their messages, with clog's own
+	/// heuristic.
+	Clog,
+	/// Commits that make the same change, according to Git's
+	/// `--cherry-pick` option.
+	GitCherry,
+}
+
+/// The options that control how individual commits are displayed.
+#[derive(Clone, Copy, Debug)]
+struct CommitDisplayOptions<'a> {
+	hash_length:       usize,
+	show_svn_path:     bool,
+	show_svn_revision: bool,
+	/// The layout used to work out the SVN branch or tag shown alongside the
+	/// revision.
+	svn_layout:        &'a SvnLayout,
+	indentation:       &'a str,
+	merge_marker:      &'a str,
+}
+
+/// How Jira tickets are presented in the output.
+///
+/// Every output goes through this, so the prefix and link are applied the same
+/// way everywhere, and structured outputs can carry the raw ticket alongside.
+#[derive(Clone, Copy, Debug)]
+struct TicketDisplay<'a> {
+	prefix:        &'a str,
+	/// Leave the project out, so only the issue number is shown.
+	strip_project: bool,
+	/// Where each ticket can be viewed, with `{ticket}` standing in for it.
+	url_template:  Option<&'a str>,
+}
+
+impl<'a> TicketDisplay<'a> {
+	/// The ticket as it's shown in the readable output, with the prefix
+	/// applied.
+	fn display(&self, jira_ticket: Option<&str>) -> String {
+		jira_ticket.map_or_else(
+			|| NO_JIRA_TICKET_STR.to_owned(),
+			|jira_ticket| {
+				if self.strip_project {
+					format!("{}{}", self.prefix, sortable_jira_ticket(jira_ticket).1)
+				} else {
+					format!("{}{jira_ticket}", self.prefix)
+				}
+			},
+		)
+	}
+
+	/// The ticket as it's written to the output, linked to where it can be
+	/// viewed if a URL template was provided.
+	fn render(&self, jira_ticket: Option<&str>, target: RenderTarget) -> String {
+		let display = self.display(jira_ticket);
+		match jira_ticket.and_then(|jira_ticket| self.url(jira_ticket)) {
+			// Other tools expect just the ticket, without a link
+			Some(url) if target == RenderTarget::Markdown => Fragment::Link {
+				text: display.as_str(),
+				url:  url.as_str(),
+			}
+			.render(target),
+			_ => Fragment::Text(display.as_str()).render(target),
+		}
+	}
+
+	/// Where the ticket can be viewed, if a URL template was provided.
+	fn url(&self, jira_ticket: &str) -> Option<String> {
+		self.url_template
+			.map(|url_template| url_template.replace(TICKET_URL_PLACEHOLDER, jira_ticket))
+	}
+}
+
+/// Where each section of the `compare` output is in the captured output, so
+/// that they can be copied to the clipboard on their own.
+#[derive(Clone, Debug)]
+struct CompareSections {
+	only_on_object_a: Range<usize>,
+	only_on_object_b: Range<usize>,
+	on_both_objects:  Range<usize>,
+}
+
+impl CompareSections {
+	/// The part of the captured output to copy for a `--clipboard-section`,
+	/// where `None` means all of it.
+	fn clipboard_range(&self, clipboard_section: &str) -> Option<Range<usize>> {
+		match clipboard_section {
+			"a" => Some(self.only_on_object_a.clone()),
+			"b" => Some(self.only_on_object_b.clone()),
+			"both" => Some(self.on_both_objects.clone()),
+			"all" => None,
+			_ => unreachable!("Clap ensures only the possible values are provided"),
+		}
+	}
+}
+
+/// Everything read from the repo that the index is built on.
+///
+/// The index borrows from this, so it's kept separately for the index to
+/// outlive the reading.
+struct RepoHistory {
+	commits:        Vec<Commit>,
+	annotated_tags: Vec<AnnotatedTag>,
+}
+
+impl RepoHistory {
+	/// Collects all commits and annotated tags in the repo.
+	fn read(
+		matches: &ArgMatches,
+		repo_dir: &str,
+		ticket_parsing_options: &TicketParsingOptions,
+	) -> Result<Self> {
+		let commits = get_commit_list(matches, repo_dir, ticket_parsing_options)?;
+
+		// Collect the annotated tags, so references to them can be resolved
+		let annotated_tags = get_annotated_tags(repo_dir)
+			.with_context(|| "unable to collect the annotated tags from the repo")?;
+
+		Ok(Self {
+			commits,
+			annotated_tags,
+		})
+	}
+
+	/// Builds the index over the commits and annotated tags, linking through
+	/// `link_kinds`.
+	fn build_index(&self, link_kinds: &[LinkKind]) -> Result<Index> {
+		Index::new(
+			self.commits.as_slice(),
+			self.annotated_tags.as_slice(),
+			link_kinds,
+		)
+	}
+}
 
 // Entry Point
 fn main() -> Result<()> {
 	let cli_definition = build_cli();
 	let subcommand_matches = cli_definition.get_matches();
 
+	// Run the subcommand, logging a record of the run afterwards (even if it
+	// failed) if requested
+	let started_at = SystemTime::now();
+	let started_instant = Instant::now();
+	let mut run_details = RunDetails::default();
+	let result = run(&subcommand_matches, None, &mut run_details);
+	if let Some(log_file) = subcommand_matches.get_one::<String>("log-file") {
+		let arguments = env::args_os()
+			.skip(1)
+			.map(|argument| argument.to_string_lossy().into_owned())
+			.collect::<Vec<_>>();
+		let repo_dir = subcommand_matches
+			.subcommand()
+			.and_then(|(_, matches)| get_repo_dir(matches));
+		let run_record = RunRecord {
+			started_at,
+			duration: started_instant.elapsed(),
+			subcommand: subcommand_matches.subcommand_name(),
+			arguments: arguments.as_slice(),
+			repo_dir: repo_dir.as_deref(),
+			details: &run_details,
+			result: &result,
+		};
+		// A failure to log shouldn't hide the outcome of the run itself
+		if let Err(error) = append_run_record(log_file, &run_record) {
+			eprintln!("[WARNING] Unable to append to the run log: {error:#}");
+		}
+	}
+
+	result
+}
+
+/// Runs the subcommand that was requested.
+///
+/// The output goes to stdout (or the pager), unless `output` is provided, in
+/// which case it's written there instead. The details that are only known
+/// partway through, like the resolved revspec, are recorded in `run_details`
+/// for the run log.
+fn run(
+	subcommand_matches: &ArgMatches,
+	output: Option<&mut dyn Write>,
+	run_details: &mut RunDetails,
+) -> Result<()> {
+	// Apply the global options
+	set_verbose(
+		*subcommand_matches
+			.get_one::<bool>("verbose")
+			.unwrap_or(&false),
+	);
+	set_git_retries(
+		*subcommand_matches
+			.get_one::<u32>("git-retries")
+			.expect("Clap provides a default value"),
+	);
+	set_strict_parsing(
+		*subcommand_matches
+			.get_one::<bool>("strict-parse")
+			.unwrap_or(&false),
+	);
+	let svn_layout = subcommand_matches
+		.get_one::<SvnLayout>("svn-layout")
+		.expect("Clap provides a default value")
+		.clone();
+
+	// Print the build information on its own, since it shouldn't need a working Git
+	// The same goes for the checks, which report a missing Git themselves
+	match subcommand_matches.subcommand() {
+		Some(("version", _)) => {
+			return match output {
+				Some(output) => print_build_information(output),
+				None => print_build_information(&mut stdout().lock()),
+			};
+		}
+		Some(("doctor", matches)) => {
+			let repo_dir = get_repo_dir(matches).expect("Clap provides a default value");
+			return match output {
+				Some(output) => print_doctor_report(output, repo_dir.as_str()),
+				None => print_doctor_report(&mut stdout().lock(), repo_dir.as_str()),
+			};
+		}
+		_ => {}
+	}
+
+	// Start a fresh record of the Git commands, so the structured outputs only
+	// report the ones from this run
+	take_recorded_commands();
+
+	// Detect the Git version up-front, so missing features are reported before any
+	// work is done
+	let git_version = get_git_version()?;
+	if is_verbose() {
+		eprintln!("[VERBOSE] Git version: {git_version}");
+	}
+
 	// Set up the multi-writer
+	// The output is only captured when it's going to be copied to the clipboard, so
+	// that large outputs aren't held in memory for nothing
+	let capture_output = subcommand_matches
+		.subcommand()
+		.and_then(|(_, matches)| {
+			matches
+				.try_get_one::<bool>("copy-to-clipboard")
+				.ok()
+				.flatten()
+		})
+		.map_or(false, |copy_to_clipboard| *copy_to_clipboard);
+	// Long output is paged, except when copying to the clipboard means waiting for
+	// Enter afterwards, since the pager would be reading the terminal at the same
+	// time
+	// The pager only replaces stdout, so the captured output is unaffected
+	let no_pager = *subcommand_matches
+		.get_one::<bool>("no-pager")
+		.unwrap_or(&false);
+	let mut pager = if output.is_some() || no_pager || (capture_output && clipboard_reads_stdin()) {
+		None
+	} else {
+		Pager::start_if_appropriate()?
+	};
+	// Only output that's going straight to the terminal adapts to its width
+	let output_is_interactive =
+		output.is_none() && pager.is_none() && !capture_output && stdout().is_terminal();
 	let mut stdout_writer = stdout();
-	let mut string_output_raw = Vec::new();
-	let mut multi_writer = MultiWriter::new(vec![&mut stdout_writer, &mut string_output_raw]);
+	let mut captured_output = capture_output.then(Vec::new);
+	let mut output_writers: Vec<&mut dyn Write> = vec![if let Some(output) = output {
+		output
+	} else if let Some(pager) = &mut pager {
+		pager
+	} else {
+		&mut stdout_writer
+	}];
+	if let Some(captured_output) = &mut captured_output {
+		output_writers.push(captured_output);
+	}
+	let mut multi_writer = MultiWriter::new(output_writers);
 
 	match subcommand_matches.subcommand() {
 		Some(("list", matches)) => {
 			// Collect the CLI arguments that were provided
-			let repo_dir = matches
-				.get_one::<String>("repo")
-				.expect("Clap ensures the argument is provided");
-			let revspec = matches
-				.get_one::<String>("revspec")
-				.expect("Clap ensures the argument is provided");
+			let repo_dir = &get_repo_dir(matches).expect("Clap provides a default value");
+			let stdin_revspec = *matches.get_one::<bool>("stdin-revspec").unwrap_or(&false);
+			let mut provided_revspec = matches.get_one::<String>("revspec").cloned();
+			let between_tags = matches
+				.get_many::<String>("between-tags")
+				.map(|tags| tags.map(String::as_str).collect::<Vec<_>>());
+			let since_tag = *matches.get_one::<bool>("since-tag").unwrap_or(&false);
 			let affected_filepath_sets = matches.get_many::<String>("filepath");
+			let excluded_jira_ticket_sets = matches.get_many::<String>("exclude-ticket");
+			let no_ticket_as = matches
+				.get_one::<String>("no-ticket-as")
+				.map(String::as_str);
+			let merge_ticket_variants = *matches
+				.get_one::<bool>("merge-ticket-variants")
+				.unwrap_or(&false);
 			let include_merge_commits = *matches
 				.get_one::<bool>("include-merge-commits")
 				.unwrap_or(&false);
-			let include_mentioned_jira_tickets = *matches
-				.get_one::<bool>("include-mentioned")
+			let resolve_merge_tickets = *matches
+				.get_one::<bool>("resolve-merge-tickets")
 				.unwrap_or(&false);
+			let ticket_exclusions = get_ticket_exclusions(matches);
+			let mention_impact = *matches.get_one::<bool>("mention-impact").unwrap_or(&false);
+			let link_kinds = get_link_kinds(matches);
 			let show_commits = *matches.get_one::<bool>("show-commits").unwrap_or(&false);
-			let hash_length = *matches
-				.get_one::<u32>("hash-length")
+			let commit_limit_per_ticket = matches
+				.get_one::<u32>("commit-limit-per-ticket")
+				.map(|commit_limit| *commit_limit as usize);
+			let group_by_file = *matches.get_one::<bool>("group-by-file").unwrap_or(&false);
+			let collapse_duplicates = *matches
+				.get_one::<bool>("collapse-duplicates")
+				.unwrap_or(&false);
+			let count_only = *matches.get_one::<bool>("count-only").unwrap_or(&false);
+			let ticket_sort_order = match matches
+				.get_one::<String>("sort-by")
+				.expect("Clap provides a default value")
+				.as_str()
+			{
+				"ticket" => TicketSortOrder::Ticket,
+				"date" => TicketSortOrder::Date,
+				_ => unreachable!("Clap ensures only the possible values are provided"),
+			};
+			let json_lines = match matches
+				.get_one::<String>("format")
+				.expect("Clap provides a default value")
+				.as_str()
+			{
+				"text" => false,
+				"jsonl" | "json-lines" => true,
+				_ => unreachable!("Clap ensures only the possible values are provided"),
+			};
+			let plain = *matches.get_one::<bool>("plain").unwrap_or(&false);
+			let max_tickets = matches
+				.get_one::<u32>("max-tickets")
+				.map(|max_tickets| *max_tickets as usize);
+			let page = *matches
+				.get_one::<u32>("page")
 				.expect("Clap provides a default value") as usize;
-			let ticket_prefix = matches
-				.get_one::<String>("ticket-prefix")
+			let link_traversal = match matches
+				.get_one::<String>("link-traversal")
+				.expect("Clap provides a default value")
+				.as_str()
+			{
+				"forward" => LinkTraversal::Forward,
+				"backward" => LinkTraversal::Backward,
+				"none" => LinkTraversal::None,
+				_ => unreachable!("Clap ensures only the possible values are provided"),
+			};
+			let link_only_merges = *matches
+				.get_one::<bool>("link-only-merges")
+				.unwrap_or(&false);
+			let hide_unreferenced = *matches
+				.get_one::<bool>("hide-unreferenced")
+				.unwrap_or(&false);
+			let hash_length = get_hash_length(matches);
+			let show_svn_path = *matches.get_one::<bool>("show-svn-path").unwrap_or(&false);
+			let show_svn_revision = *matches
+				.get_one::<bool>("show-svn-revision")
+				.unwrap_or(&false);
+			let compact = *matches.get_one::<bool>("compact").unwrap_or(&false);
+			let indent = matches.get_one::<String>("indent").map(String::as_str);
+			let compact_form = use_compact_form(compact, indent, output_is_interactive);
+			let merge_marker = matches
+				.get_one::<String>("merge-marker")
 				.expect("Clap provides a default value");
+			let ticket_display = get_ticket_display(matches);
 			let copy_to_clipboard = *matches
 				.get_one::<bool>("copy-to-clipboard")
 				.unwrap_or(&false);
 
+			// JSON Lines output is meant to be read by other tools, so it doesn't mix with
+			// the options for people
+			if json_lines && count_only {
+				return Err(anyhow!(
+					"`--count-only` can't be used with JSON Lines output, since the totals are in \
+					 its summary line"
+				));
+			}
+			if json_lines && copy_to_clipboard {
+				return Err(anyhow!(
+					"JSON Lines output can't be copied to the clipboard; pipe it where it's \
+					 needed instead"
+				));
+			}
+			let print_headers = !count_only && !json_lines && !plain;
+
+			// Read the revspec from stdin, if requested
+			if stdin_revspec {
+				ensure_stdin_is_free_for_arguments(copy_to_clipboard)?;
+				provided_revspec = Some(read_stdin_lines(&["revspec"])?.remove(0));
+			}
+
+			let commit_display_options = CommitDisplayOptions {
+				hash_length,
+				show_svn_path,
+				show_svn_revision,
+				svn_layout: &svn_layout,
+				indentation: get_indentation_str(compact_form, indent),
+				merge_marker,
+			};
+
+			// Make sure the installed Git supports the requested features
+			if hide_unreferenced {
+				require_git_version("`--hide-unreferenced`", FOR_EACH_REF_CONTAINS_VERSION)?;
+			}
+			if collapse_duplicates {
+				require_git_version("`--collapse-duplicates`", PATCH_ID_STABLE_VERSION)?;
+			}
+			if between_tags.is_some() {
+				require_git_version("`--between-tags`", MERGE_BASE_IS_ANCESTOR_VERSION)?;
+			}
+
+			// Default to everything on the current branch that isn't on its upstream yet
+			let revspec = if let Some(revspec) = &provided_revspec {
+				revspec.clone()
+			} else if let Some(between_tags) = &between_tags {
+				get_between_tags_revspec(repo_dir.as_str(), between_tags[0], between_tags[1])?
+			} else if since_tag {
+				if let Some(latest_tag) = get_latest_tag(repo_dir.as_str())? {
+					if print_headers {
+						writeln!(
+							&mut multi_writer,
+							"Most recent tag reachable from HEAD: `{latest_tag}`"
+						)?;
+					}
+					format!("{latest_tag}..HEAD")
+				} else {
+					eprintln!(
+						"[WARNING] There's no tag reachable from HEAD, so listing its full \
+						 history."
+					);
+					"HEAD".to_owned()
+				}
+			} else {
+				get_default_list_revspec(repo_dir.as_str())?
+			};
+			run_details.record_target("revspec", revspec.as_str());
+
 			// Print the revspec used
-			writeln!(
-				&mut multi_writer,
-				"Using the following revspec: `{revspec}`"
-			)?;
+			if print_headers {
+				if provided_revspec.is_some() || between_tags.is_some() || since_tag {
+					writeln!(
+						&mut multi_writer,
+						"Using the following revspec: `{revspec}`"
+					)?;
+				} else {
+					writeln!(
+						&mut multi_writer,
+						"No revspec was provided, so defaulting to the following: `{revspec}`"
+					)?;
+				}
+			}
 
 			// Since the filepaths can be provided all in one argument, or separately with
 			// multiple arguments, they need to be collected into a single list
 			let mut affected_filepaths = Vec::new();
 			if let Some(filepath_sets) = affected_filepath_sets {
-				affected_filepaths = flatten_string_sets_on_shell_words(filepath_sets)
-					.with_context(|| "unable to parse filepath sets")?;
+				affected_filepaths = resolve_affected_filepaths(
+					repo_dir.as_str(),
+					flatten_filepath_sets(filepath_sets)
+						.with_context(|| "unable to parse filepath sets")?,
+				)?;
 			}
+			let mut excluded_jira_tickets = HashSet::new();
+			if let Some(jira_ticket_sets) = excluded_jira_ticket_sets {
+				excluded_jira_tickets.extend(
+					flatten_string_sets_on_shell_words(jira_ticket_sets)
+						.with_context(|| "unable to parse excluded Jira ticket sets")?,
+				);
+			}
+			let excluded_author_patterns = get_excluded_author_patterns(matches);
 
 			// Display the filepaths being considered
-			if !affected_filepaths.is_empty() {
+			if print_headers && !affected_filepaths.is_empty() {
 				writeln!(
 					&mut multi_writer,
 					"Only considering commits that affected the following filepaths:"
@@ -142,106 +690,583 @@ fn main() -> Result<()> {
 				}
 			}
 
-			// Collect all commits in the repo
-			let commits =
-				get_complete_commit_list(repo_dir.as_str(), include_mentioned_jira_tickets)
-					.with_context(|| "unable to build the complete commit list from the repo")?;
+			// Collect all commits and annotated tags in the repo
+			let ticket_parsing_options =
+				get_ticket_parsing_options(matches, ticket_exclusions.as_slice(), mention_impact);
+			let repo_history =
+				RepoHistory::read(matches, repo_dir.as_str(), &ticket_parsing_options)?;
 
 			// Build the index
-			let index = Index::new(commits.as_slice())?;
+			let index = repo_history.build_index(link_kinds.as_slice())?;
 
 			// Perform the search
-			let search_results = get_search_results(
+			if is_verbose() {
+				eprintln!(
+					"[VERBOSE] Link traversal: {link_traversal:?}, only linking merges: \
+					 {link_only_merges}"
+				);
+			}
+			let mut search_results = get_search_results(
 				&index,
 				repo_dir.as_str(),
 				revspec.as_str(),
 				include_merge_commits,
 				affected_filepaths.as_slice(),
+				link_traversal,
+				link_only_merges,
 			)
 			.with_context(|| "unable to perform the search")?;
 
-			// Display the results
-			writeln!(&mut multi_writer)?;
+			// Hide the commits that aren't on any ref, if requested
+			// They're still in the index, so they've already served their purpose for
+			// resolving references
+			if hide_unreferenced {
+				search_results =
+					remove_unreferenced_commits(repo_dir, search_results, &mut HashMap::new())
+						.with_context(|| "unable to filter out unreferenced commits")?;
+			}
+
+			// Leave out the commits by excluded authors
+			let excluded_author_commit_count =
+				remove_excluded_author_commits(&mut search_results, &excluded_author_patterns);
+			if print_headers && excluded_author_commit_count > 0 {
+				writeln!(
+					&mut multi_writer,
+					"Excluded {excluded_author_commit_count} commit(s) by excluded authors."
+				)?;
+			}
+
+			// Group the commits by Jira ticket, crediting merges with the tickets they
+			// bring in if requested
+			let merged_jira_tickets = if resolve_merge_tickets {
+				get_merged_jira_tickets(&index, search_results.as_slice())
+			} else {
+				HashMap::new()
+			};
+			let mut jira_ticket_groups = group_by_jira_tickets(
+				search_results.as_slice(),
+				no_ticket_as,
+				&merged_jira_tickets,
+			);
+
+			// Remove the excluded Jira tickets
+			remove_excluded_jira_tickets(&mut jira_ticket_groups, &excluded_jira_tickets);
+
+			// Report how much of the result set comes from mentions, if requested
+			if mention_impact {
+				report_mention_impact(
+					search_results.as_slice(),
+					ticket_parsing_options.include_mentioned,
+					&excluded_jira_tickets,
+				);
+			}
+
+			// Look for tickets that are probably the same, and fold them together if
+			// requested
+			for jira_ticket_variants in find_jira_ticket_variants(&jira_ticket_groups) {
+				let variant_list = jira_ticket_variants
+					.iter()
+					.map(|jira_ticket| {
+						format!(
+							"`{jira_ticket}` ({})",
+							jira_ticket_groups[&Some(*jira_ticket)].len()
+						)
+					})
+					.collect::<Vec<_>>()
+					.join(", ");
+				if merge_ticket_variants {
+					let canonical_jira_ticket = merge_jira_ticket_variants(
+						&index,
+						&mut jira_ticket_groups,
+						jira_ticket_variants.as_slice(),
+					);
+					eprintln!(
+						"[WARNING] Merging Jira ticket variants into `{canonical_jira_ticket}`: \
+						 {variant_list}"
+					);
+				} else {
+					eprintln!(
+						"[WARNING] These Jira tickets look like variants of the same ticket (use \
+						 `--merge-ticket-variants` to merge them): {variant_list}"
+					);
+				}
+			}
+
+			// Collapse commits that make the same change, if requested
+			if collapse_duplicates {
+				let patch_ids = get_patch_ids(
+					repo_dir.as_str(),
+					search_results
+						.iter()
+						.map(|included_commit| included_commit.commit.git_revision.as_str())
+						.collect::<Vec<_>>()
+						.as_slice(),
+				)
+				.with_context(|| "unable to collect patch IDs for collapsing duplicates")?;
+				for ticket_commits in jira_ticket_groups.values_mut() {
+					*ticket_commits =
+						collapse_duplicate_commits(ticket_commits.as_slice(), &patch_ids);
+				}
+			}
 
-			// Group the commits by Jira ticket
-			let jira_ticket_groups = group_by_jira_tickets(search_results.as_slice());
 			let jira_ticket_total = if jira_ticket_groups.contains_key(&None) {
 				jira_ticket_groups.len() - 1
 			} else {
 				jira_ticket_groups.len()
 			};
+			let commit_total = search_results
+				.iter()
+				.filter(|included_commit| {
+					!is_fully_excluded(included_commit.commit, &excluded_jira_tickets)
+				})
+				.count();
+			run_details.record_count("jira_tickets", jira_ticket_total);
+			run_details.record_count("commits", commit_total);
 
-			// Sort the Jira tickets
-			let mut jira_ticket_groups_sorted = jira_ticket_groups.iter().collect::<Vec<_>>();
-			jira_ticket_groups_sorted
-				.sort_unstable_by_key(|entry| entry.0.map(sortable_jira_ticket));
+			// Display just the totals if that's all that was asked for
+			if count_only {
+				writeln!(&mut multi_writer, "Jira tickets: {jira_ticket_total}")?;
+				writeln!(&mut multi_writer, "Commits: {commit_total}")?;
+			} else {
+				// Sort the Jira tickets
+				let mut jira_ticket_groups_sorted = jira_ticket_groups.iter().collect::<Vec<_>>();
+				match ticket_sort_order {
+					TicketSortOrder::Ticket => jira_ticket_groups_sorted
+						.sort_unstable_by_key(|entry| entry.0.map(sortable_jira_ticket)),
+					// Ties are broken by the ticket, so the order is stable between runs
+					TicketSortOrder::Date => {
+						jira_ticket_groups_sorted.sort_unstable_by_key(|entry| {
+							(
+								entry
+									.1
+									.iter()
+									.map(|included_commit| {
+										included_commit.commit.committer.timestamp
+									})
+									.min()
+									.unwrap_or(i64::MAX),
+								entry.0.map(sortable_jira_ticket),
+							)
+						});
+					}
+				}
 
-			// Display the results
-			writeln!(
-				&mut multi_writer,
-				"Jira tickets: ({jira_ticket_total} total)"
-			)?;
-			display_jira_ticket_commit_list(
-				&mut multi_writer,
-				jira_ticket_groups_sorted.as_slice(),
-				show_commits,
-				hash_length,
-				ticket_prefix,
-			)?;
+				// Only display the requested page, if the results are being paginated
+				// This has to happen after sorting and filtering so that the pages are
+				// stable
+				let displayed_jira_ticket_groups = if let Some(max_tickets) = max_tickets {
+					let entry_total = jira_ticket_groups_sorted.len();
+					let page_total = entry_total.div_ceil(max_tickets).max(1);
+					if page > page_total {
+						return Err(anyhow!(
+							"page {page} is out of range, since there are only {page_total} pages \
+							 of results"
+						));
+					}
+					let page_start = (page - 1) * max_tickets;
+					let page_end = (page_start + max_tickets).min(entry_total);
+					if print_headers {
+						writeln!(
+							&mut multi_writer,
+							"Displaying page {page}/{page_total} (tickets {}\u{2013}{page_end} of \
+							 {entry_total})",
+							(page_start + 1).min(page_end)
+						)?;
+					}
+					&jira_ticket_groups_sorted[page_start..page_end]
+				} else {
+					jira_ticket_groups_sorted.as_slice()
+				};
 
-			// Copy the output to the clipboard if specified
-			if copy_to_clipboard {
-				copy_str_to_clipboard(from_utf8(string_output_raw.as_slice()).expect(
-					"only string values were written to the buffer, so it's guaranteed to be \
-					 valid UTF-8",
-				))?;
+				// Display the results
+				if json_lines {
+					let git_version_json = json_string(git_version.to_string().as_str());
+					writeln!(
+						&mut multi_writer,
+						"{{\"type\":\"meta\",\"clog_version\":{},\"clog_commit\":{},\"revspec\":\
+						 {},\"filepaths\":[{}],\"jira_ticket_total\":{jira_ticket_total},\"page\":\
+						 {},\"max_tickets\":{},\"git_version\":{git_version_json},\"commands\":\
+						 [{}]}}",
+						json_string(APPLICATION_VERSION),
+						json_string(BUILD_GIT_COMMIT),
+						json_string(revspec.as_str()),
+						affected_filepaths
+							.iter()
+							.map(|affected_filepath| json_string(affected_filepath))
+							.collect::<Vec<_>>()
+							.join(","),
+						if max_tickets.is_some() {
+							page.to_string()
+						} else {
+							"null".to_owned()
+						},
+						max_tickets.map_or_else(
+							|| "null".to_owned(),
+							|max_tickets| max_tickets.to_string()
+						),
+						take_recorded_commands()
+							.iter()
+							.map(|argv| format!(
+								"[{}]",
+								argv.iter()
+									.map(|word| json_string(word))
+									.collect::<Vec<_>>()
+									.join(",")
+							))
+							.collect::<Vec<_>>()
+							.join(","),
+					)?;
+					display_jira_ticket_json_lines(
+						&mut multi_writer,
+						displayed_jira_ticket_groups,
+						jira_ticket_total,
+						&ticket_display,
+					)?;
+				} else if plain {
+					display_jira_ticket_plain(
+						&mut multi_writer,
+						displayed_jira_ticket_groups,
+						show_commits,
+						hash_length,
+						compact_form,
+						&ticket_display,
+					)?;
+				} else {
+					// Work out where each displayed commit belongs, if they're being grouped
+					// that way
+					let primary_directories = (show_commits && group_by_file)
+						.then(|| {
+							let displayed_git_revisions = displayed_jira_ticket_groups
+								.iter()
+								.flat_map(|(_, commits)| commits.iter())
+								.map(|included_commit| included_commit.commit.git_revision.as_str())
+								.collect::<Vec<_>>();
+							get_primary_directories(
+								repo_dir.as_str(),
+								displayed_git_revisions.as_slice(),
+							)
+							.with_context(|| "unable to get the directories the commits touched")
+						})
+						.transpose()?;
+
+					writeln!(&mut multi_writer)?;
+					writeln!(
+						&mut multi_writer,
+						"Jira tickets: ({jira_ticket_total} total)"
+					)?;
+					display_jira_ticket_commit_list(
+						&mut multi_writer,
+						displayed_jira_ticket_groups,
+						show_commits,
+						commit_limit_per_ticket,
+						primary_directories.as_ref(),
+						&commit_display_options,
+						&ticket_display,
+					)?;
+				}
 			}
+
+			// Copy the output to the clipboard if specified
+			copy_captured_output_to_clipboard(captured_output.as_deref(), None)?;
 		}
 		Some(("compare", matches)) => {
 			// Collect the CLI arguments that were provided
-			let repo_dir = matches
-				.get_one::<String>("repo")
-				.expect("Clap ensures the argument is provided");
-			let object_a = matches
-				.get_one::<String>("object-a")
-				.expect("Clap ensures the argument is provided");
-			let object_b = matches
-				.get_one::<String>("object-b")
-				.expect("Clap ensures the argument is provided");
+			let repo_dir = &get_repo_dir(matches).expect("Clap provides a default value");
+			let stdin_objects = *matches.get_one::<bool>("stdin-objects").unwrap_or(&false);
+			let releases_pattern = matches.get_one::<String>("releases");
+			let release_count = *matches
+				.get_one::<u32>("release-count")
+				.expect("Clap provides a default value") as usize;
 			let affected_filepath_sets = matches.get_many::<String>("filepath");
+			let excluded_jira_ticket_sets = matches.get_many::<String>("exclude-ticket");
+			let no_ticket_as = matches
+				.get_one::<String>("no-ticket-as")
+				.map(String::as_str);
 			let include_merge_commits = *matches
 				.get_one::<bool>("include-merge-commits")
 				.unwrap_or(&false);
+			let resolve_merge_tickets = *matches
+				.get_one::<bool>("resolve-merge-tickets")
+				.unwrap_or(&false);
 			let include_cherry_picks = *matches
 				.get_one::<bool>("include-cherry-picks")
 				.unwrap_or(&false);
-			let include_mentioned_jira_tickets = *matches
-				.get_one::<bool>("include-mentioned")
+			let date_based_cherry_pick_detection = *matches
+				.get_one::<bool>("date-based-cherry-pick-detection")
 				.unwrap_or(&false);
+			let backport_window_days = *matches
+				.get_one::<u32>("backport-window")
+				.expect("Clap provides a default value");
+			let ignore_trivial = *matches.get_one::<bool>("ignore-trivial").unwrap_or(&false);
+			let generated_paths = matches
+				.get_many::<String>("generated-path")
+				.map_or_else(Vec::new, |generated_paths| {
+					generated_paths.cloned().collect()
+				});
+			let cherry_pick_equivalence = match matches
+				.get_one::<String>("equivalence")
+				.expect("Clap provides a default value")
+				.as_str()
+			{
+				"clog" => CherryPickEquivalence::Clog,
+				"git-cherry" => CherryPickEquivalence::GitCherry,
+				_ => unreachable!("Clap ensures only the possible values are provided"),
+			};
+			let ticket_exclusions = get_ticket_exclusions(matches);
 			let show_commits = *matches.get_one::<bool>("show-commits").unwrap_or(&false);
-			let hash_length = *matches
-				.get_one::<u32>("hash-length")
-				.expect("Clap provides a default value") as usize;
-			let ticket_prefix = matches
-				.get_one::<String>("ticket-prefix")
+			let commit_limit_per_ticket = matches
+				.get_one::<u32>("commit-limit-per-ticket")
+				.map(|commit_limit| *commit_limit as usize);
+			let hash_length = get_hash_length(matches);
+			let show_svn_path = *matches.get_one::<bool>("show-svn-path").unwrap_or(&false);
+			let show_svn_revision = *matches
+				.get_one::<bool>("show-svn-revision")
+				.unwrap_or(&false);
+			let compact = *matches.get_one::<bool>("compact").unwrap_or(&false);
+			let indent = matches.get_one::<String>("indent").map(String::as_str);
+			let compact_form = use_compact_form(compact, indent, output_is_interactive);
+			let merge_marker = matches
+				.get_one::<String>("merge-marker")
 				.expect("Clap provides a default value");
+			let ticket_display = get_ticket_display(matches);
 			let copy_to_clipboard = *matches
 				.get_one::<bool>("copy-to-clipboard")
 				.unwrap_or(&false);
+			let clipboard_section = matches
+				.get_one::<String>("clipboard-section")
+				.expect("Clap provides a default value");
+			let summary_only = *matches.get_one::<bool>("summary-only").unwrap_or(&false);
+			let expected_commits_path = matches.get_one::<String>("expected-commits");
+			let strict = *matches.get_one::<bool>("strict").unwrap_or(&false);
+			let fail_if_empty = *matches.get_one::<bool>("fail-if-empty").unwrap_or(&false);
+			let both_only = *matches.get_one::<bool>("both-only").unwrap_or(&false);
+			let sections = if both_only {
+				vec!["both"]
+			} else {
+				matches
+					.get_many::<String>("sections")
+					.expect("Clap provides a default value")
+					.map(String::as_str)
+					.collect::<Vec<_>>()
+			};
+
+			// Read the objects from stdin or pick them from the tags, if requested
+			let (object_a, object_b) = if let Some(releases_pattern) = releases_pattern {
+				let mut release_tags = get_tags_by_version(repo_dir, releases_pattern.as_str())
+					.with_context(|| "unable to get the release tags")?;
+				if release_tags.len() < release_count {
+					return Err(anyhow!(
+						"`--releases` needs at least {release_count} tags matching \
+						 `{releases_pattern}`, but only {} were found",
+						release_tags.len()
+					));
+				}
+				release_tags.truncate(release_count);
+				let object_b = release_tags.remove(0);
+				let object_a = release_tags
+					.pop()
+					.expect("there are always at least two tags");
+				writeln!(
+					&mut multi_writer,
+					"Using the release tags `{object_a}` and `{object_b}`, the oldest and newest \
+					 of the {release_count} most recent tags matching `{releases_pattern}`"
+				)?;
+				(object_a, object_b)
+			} else if stdin_objects {
+				ensure_stdin_is_free_for_arguments(copy_to_clipboard)?;
+				let mut objects = read_stdin_lines(&["first object", "second object"])?;
+				let object_b = objects.pop().expect("both lines are always read");
+				let object_a = objects.pop().expect("both lines are always read");
+				(object_a, object_b)
+			} else {
+				(
+					matches
+						.get_one::<String>("object-a")
+						.expect("Clap ensures the argument is provided")
+						.clone(),
+					matches
+						.get_one::<String>("object-b")
+						.expect("Clap ensures the argument is provided")
+						.clone(),
+				)
+			};
+
+			if date_based_cherry_pick_detection && include_cherry_picks {
+				return Err(anyhow!(
+					"`--date-based-cherry-pick-detection` can't be used with \
+					 `--include-cherry-picks`, since it only changes how cherry-picks are \
+					 filtered out"
+				));
+			}
+			if cherry_pick_equivalence == CherryPickEquivalence::GitCherry {
+				if include_cherry_picks {
+					return Err(anyhow!(
+						"`--equivalence git-cherry` can't be used with `--include-cherry-picks`, \
+						 since it only changes how cherry-picks are filtered out"
+					));
+				}
+				if date_based_cherry_pick_detection {
+					return Err(anyhow!(
+						"`--date-based-cherry-pick-detection` can't be used with `--equivalence \
+						 git-cherry`, since it's part of the `clog` engine"
+					));
+				}
+			}
+
+			let commit_display_options = CommitDisplayOptions {
+				hash_length,
+				show_svn_path,
+				show_svn_revision,
+				svn_layout: &svn_layout,
+				indentation: get_indentation_str(compact_form, indent),
+				merge_marker,
+			};
+
+			// Resolve the objects up-front, so that tags can be shown with the commits
+			// they point to, and so that bad references get a specific error
+			let (object_a_description, object_a_revision) =
+				describe_compare_object(repo_dir.as_str(), object_a.as_str(), hash_length)?;
+			let (object_b_description, object_b_revision) =
+				describe_compare_object(repo_dir.as_str(), object_b.as_str(), hash_length)?;
 
 			// Print the objects being compared
+			run_details.record_target("object_a", object_a.as_str());
+			run_details.record_target("object_b", object_b.as_str());
 			writeln!(
 				&mut multi_writer,
-				"Comparing the following two references: `{object_a}` against `{object_b}`"
+				"Comparing the following two references: {object_a_description} against \
+				 {object_b_description}"
+			)?;
+
+			// There's nothing to search for if both objects are the same commit
+			if object_a_revision == object_b_revision {
+				writeln!(
+					&mut multi_writer,
+					"Both objects resolve to `{}`, so there's nothing to compare",
+					abbreviate(object_a_revision.as_str(), hash_length)?
+				)?;
+				copy_captured_output_to_clipboard(captured_output.as_deref(), None)?;
+				if fail_if_empty {
+					return Err(anyhow!(
+						"the comparison is empty, since both objects resolve to the same commit"
+					));
+				}
+				return Ok(());
+			}
+
+			// Point out when one object contains the other, since one of the sections is
+			// always empty then
+			if get_git_version()? >= MERGE_BASE_IS_ANCESTOR_VERSION {
+				if is_ancestor(
+					repo_dir.as_str(),
+					object_a_revision.as_str(),
+					object_b_revision.as_str(),
+				)? {
+					writeln!(
+						&mut multi_writer,
+						"`{object_a}` is fully contained in `{object_b}`, so the section only on \
+						 `{object_a}` will be empty"
+					)?;
+				} else if is_ancestor(
+					repo_dir.as_str(),
+					object_b_revision.as_str(),
+					object_a_revision.as_str(),
+				)? {
+					writeln!(
+						&mut multi_writer,
+						"`{object_b}` is fully contained in `{object_a}`, so the section only on \
+						 `{object_b}` will be empty"
+					)?;
+				}
+			}
+
+			// Show how far apart the objects are, for context before the ticket lists
+			let (merge_base, object_a_timestamp, object_b_timestamp) = get_newest_merge_base(
+				repo_dir.as_str(),
+				object_a_revision.as_str(),
+				object_b_revision.as_str(),
 			)?;
+			if let Some(merge_base) = merge_base {
+				let (only_on_object_a_count, only_on_object_b_count) = count_divergent_commits(
+					repo_dir.as_str(),
+					object_a_revision.as_str(),
+					object_b_revision.as_str(),
+				)?;
+				let merge_base_description = format!(
+					"(last common commit `{}` on {})",
+					abbreviate(merge_base.git_revision.as_str(), hash_length)?,
+					merge_base.date
+				);
+				for (behind_object, ahead_object, ahead_count, ahead_timestamp) in [
+					(
+						object_a.as_str(),
+						object_b.as_str(),
+						only_on_object_b_count,
+						object_b_timestamp,
+					),
+					(
+						object_b.as_str(),
+						object_a.as_str(),
+						only_on_object_a_count,
+						object_a_timestamp,
+					),
+				] {
+					if ahead_count > 0 {
+						let ahead_days =
+							((ahead_timestamp - merge_base.timestamp) / SECONDS_PER_DAY).max(0);
+						writeln!(
+							&mut multi_writer,
+							"`{behind_object}` is {ahead_count} commit{} / {ahead_days} day{} \
+							 behind `{ahead_object}` {merge_base_description}",
+							if ahead_count == 1 { "" } else { "s" },
+							if ahead_days == 1 { "" } else { "s" },
+						)?;
+					}
+				}
+			} else {
+				writeln!(
+					&mut multi_writer,
+					"`{object_a}` and `{object_b}` have unrelated histories, so they don't have \
+					 any commits in common"
+				)?;
+			}
+			if !include_cherry_picks {
+				writeln!(
+					&mut multi_writer,
+					"Cherry-picks are detected with: {}",
+					match cherry_pick_equivalence {
+						CherryPickEquivalence::Clog => "commit message references (`clog`)",
+						CherryPickEquivalence::GitCherry =>
+							"identical patches, using `git log --cherry-pick` (`git-cherry`)",
+					}
+				)?;
+			}
+
+			// Warn about local branches that have diverged from their upstreams (usually
+			// because of a force-push), since the results depend entirely on which one was
+			// named
+			for object in [&object_a, &object_b] {
+				warn_if_diverged_from_upstream(repo_dir.as_str(), object.as_str())?;
+			}
 
 			// Since the filepaths can be provided all in one argument, or separately with
 			// multiple arguments, they need to be collected into a single list
 			let mut affected_filepaths = Vec::new();
 			if let Some(filepath_sets) = affected_filepath_sets {
-				affected_filepaths = flatten_string_sets_on_shell_words(filepath_sets)
-					.with_context(|| "unable to parse filepath sets")?;
+				affected_filepaths = resolve_affected_filepaths(
+					repo_dir.as_str(),
+					flatten_filepath_sets(filepath_sets)
+						.with_context(|| "unable to parse filepath sets")?,
+				)?;
 			}
+			let mut excluded_jira_tickets = HashSet::new();
+			if let Some(jira_ticket_sets) = excluded_jira_ticket_sets {
+				excluded_jira_tickets.extend(
+					flatten_string_sets_on_shell_words(jira_ticket_sets)
+						.with_context(|| "unable to parse excluded Jira ticket sets")?,
+				);
+			}
+			let excluded_author_patterns = get_excluded_author_patterns(matches);
 
 			// Display the filepaths being considered
 			if !affected_filepaths.is_empty() {
@@ -254,49 +1279,131 @@ fn main() -> Result<()> {
 				}
 			}
 
-			// Collect all commits in the repo
-			let commits =
-				get_complete_commit_list(repo_dir.as_str(), include_mentioned_jira_tickets)
-					.with_context(|| "unable to build the complete commit list from the repo")?;
+			// Collect all commits and annotated tags in the repo
+			let ticket_parsing_options =
+				get_ticket_parsing_options(matches, ticket_exclusions.as_slice(), false);
+			let repo_history =
+				RepoHistory::read(matches, repo_dir.as_str(), &ticket_parsing_options)?;
 
 			// Build the index
-			let index = Index::new(commits.as_slice())?;
+			let index = repo_history.build_index(ALL_LINK_KINDS)?;
+
+			// Work out which searches are needed for the requested sections
+			// The section on both objects and clog's cherry-pick filtering compare the two
+			// results, so they need both searches regardless
+			let filter_cherry_picks_by_references =
+				!include_cherry_picks && cherry_pick_equivalence == CherryPickEquivalence::Clog;
+			let show_section_only_on_object_a = sections.contains(&"only-a");
+			let show_section_only_on_object_b = sections.contains(&"only-b");
+			let show_section_on_both_objects = sections.contains(&"both");
+			let search_both_objects =
+				show_section_on_both_objects || filter_cherry_picks_by_references;
+			let search_object_a = show_section_only_on_object_a || search_both_objects;
+			let search_object_b = show_section_only_on_object_b || search_both_objects;
+			if is_verbose()
+				&& (search_object_a && !show_section_only_on_object_a
+					|| search_object_b && !show_section_only_on_object_b)
+			{
+				eprintln!(
+					"[VERBOSE] Both searches are still being performed, since they're needed for \
+					 {}, but only the requested sections will be displayed",
+					if show_section_on_both_objects {
+						"the section on both objects"
+					} else {
+						"filtering out cherry-picks"
+					}
+				);
+			}
 
 			// Perform the searches
 			// The `A ^B` syntax basically searches for all commits accessible from
 			// object A, that aren't accessible from object B
-			let search_revspec_only_on_object_a = format!("\"{object_a}\" ^\"{object_b}\"");
-			let mut search_results_only_on_object_a = get_search_results(
-				&index,
-				repo_dir.as_str(),
-				search_revspec_only_on_object_a.as_str(),
-				include_merge_commits,
-				affected_filepaths.as_slice(),
-			)
-			.with_context(|| {
-				format!(
-					"unable to perform the search for items that are on `{object_a}` but not \
-					 `{object_b}`"
-				)
-			})?;
+			// With Git's equivalence, the symmetric `A...B` range is used instead, and Git
+			// leaves out the commits with an identical patch on the other side
+			// The two searches are independent Git processes, so they're run concurrently
+			// to cut down on the wall time spent waiting on Git
+			let (search_revspec_only_on_object_a, search_revspec_only_on_object_b) =
+				match cherry_pick_equivalence {
+					CherryPickEquivalence::Clog => (
+						format!("\"{object_a}\" ^\"{object_b}\""),
+						format!("\"{object_b}\" ^\"{object_a}\""),
+					),
+					CherryPickEquivalence::GitCherry => (
+						format!("--cherry-pick --left-only \"{object_a}\"...\"{object_b}\""),
+						format!("--cherry-pick --right-only \"{object_a}\"...\"{object_b}\""),
+					),
+				};
+			let (search_results_only_on_object_a, search_results_only_on_object_b) =
+				thread::scope(|scope| {
+					let search_thread_only_on_object_a = scope.spawn(|| {
+						if !search_object_a {
+							return Ok(Vec::new());
+						}
+						get_search_results(
+							&index,
+							repo_dir.as_str(),
+							search_revspec_only_on_object_a.as_str(),
+							include_merge_commits,
+							affected_filepaths.as_slice(),
+							LinkTraversal::Forward,
+							false,
+						)
+						.with_context(|| {
+							format!(
+								"unable to perform the search for items that are on `{object_a}` \
+								 but not `{object_b}`"
+							)
+						})
+					});
 
-			let search_revspec_only_on_object_b = format!("\"{object_b}\" ^\"{object_a}\"");
-			let mut search_results_only_on_object_b = get_search_results(
-				&index,
-				repo_dir.as_str(),
-				search_revspec_only_on_object_b.as_str(),
-				include_merge_commits,
-				affected_filepaths.as_slice(),
-			)
-			.with_context(|| {
-				format!(
-					"unable to perform the search for items that are on `{object_b}` but not \
-					 `{object_a}`"
-				)
-			})?;
+					let search_results_only_on_object_b = if search_object_b {
+						get_search_results(
+							&index,
+							repo_dir.as_str(),
+							search_revspec_only_on_object_b.as_str(),
+							include_merge_commits,
+							affected_filepaths.as_slice(),
+							LinkTraversal::Forward,
+							false,
+						)
+						.with_context(|| {
+							format!(
+								"unable to perform the search for items that are on `{object_b}` \
+								 but not `{object_a}`"
+							)
+						})
+					} else {
+						Ok(Vec::new())
+					};
+
+					(
+						search_thread_only_on_object_a
+							.join()
+							.expect("the search thread should never panic"),
+						search_results_only_on_object_b,
+					)
+				});
+			let mut search_results_only_on_object_a = search_results_only_on_object_a?;
+			let mut search_results_only_on_object_b = search_results_only_on_object_b?;
+
+			// Leave out the commits by excluded authors, on both sides
+			let excluded_author_commit_count = remove_excluded_author_commits(
+				&mut search_results_only_on_object_a,
+				&excluded_author_patterns,
+			) + remove_excluded_author_commits(
+				&mut search_results_only_on_object_b,
+				&excluded_author_patterns,
+			);
+			if excluded_author_commit_count > 0 {
+				writeln!(
+					&mut multi_writer,
+					"Excluded {excluded_author_commit_count} commit(s) by excluded authors."
+				)?;
+			}
 
 			// Filter out cherry-picks and SVN merges between the two objects
-			if !include_cherry_picks {
+			// Git has already done this with its own equivalence
+			if filter_cherry_picks_by_references {
 				// Build hash sets from the results to make searching faster
 				let search_results_only_on_object_a_hash_set = search_results_only_on_object_a
 					.iter()
@@ -351,16 +1458,84 @@ fn main() -> Result<()> {
 					}
 					true
 				});
-			}
 
-			// Group the Jira tickets
-			let jira_tickets_on_object_a =
-				group_by_jira_tickets(search_results_only_on_object_a.as_slice());
-			let jira_tickets_on_object_b =
-				group_by_jira_tickets(search_results_only_on_object_b.as_slice());
+				// Filter out the cherry-picks that don't reference anything, if requested
+				if date_based_cherry_pick_detection {
+					let probable_backport_count = remove_probable_backports(
+						&mut search_results_only_on_object_a,
+						&mut search_results_only_on_object_b,
+						i64::from(backport_window_days) * SECONDS_PER_DAY,
+					);
+					if is_verbose() {
+						eprintln!(
+							"[VERBOSE] Filtered out {probable_backport_count} pair(s) of commits \
+							 that look like back-ports of each other based on their dates"
+						);
+					}
+				}
+			}
 
-			// Find the intersection and symmetric differences between the sets
-			let mut jira_tickets_only_on_object_a = Vec::new();
+			// Leave out the commits that don't change anything meaningful, if requested
+			// Merges are kept, since Git doesn't show what they change
+			if ignore_trivial {
+				let trivial_commits = get_trivial_commits(
+					repo_dir.as_str(),
+					search_results_only_on_object_a
+						.iter()
+						.chain(&search_results_only_on_object_b)
+						.filter(|included_commit| {
+							included_commit.commit.parent_revisions.len() <= 1
+						})
+						.map(|included_commit| included_commit.commit.git_revision.as_str())
+						.collect::<Vec<_>>()
+						.as_slice(),
+					generated_paths.as_slice(),
+				)
+				.with_context(|| "unable to find the trivial commits")?;
+				for search_results in [
+					&mut search_results_only_on_object_a,
+					&mut search_results_only_on_object_b,
+				] {
+					search_results.retain(|included_commit| {
+						!trivial_commits.contains(included_commit.commit.git_revision.as_str())
+					});
+				}
+				if is_verbose() {
+					eprintln!(
+						"[VERBOSE] Left out {} trivial commit(s)",
+						trivial_commits.len()
+					);
+				}
+			}
+
+			// Group the Jira tickets, leaving out the excluded ones
+			// Merges are credited with the tickets they bring in, if requested
+			let mut merged_jira_tickets = HashMap::new();
+			if resolve_merge_tickets {
+				merged_jira_tickets.extend(get_merged_jira_tickets(
+					&index,
+					search_results_only_on_object_a.as_slice(),
+				));
+				merged_jira_tickets.extend(get_merged_jira_tickets(
+					&index,
+					search_results_only_on_object_b.as_slice(),
+				));
+			}
+			let mut jira_tickets_on_object_a = group_by_jira_tickets(
+				search_results_only_on_object_a.as_slice(),
+				no_ticket_as,
+				&merged_jira_tickets,
+			);
+			let mut jira_tickets_on_object_b = group_by_jira_tickets(
+				search_results_only_on_object_b.as_slice(),
+				no_ticket_as,
+				&merged_jira_tickets,
+			);
+			remove_excluded_jira_tickets(&mut jira_tickets_on_object_a, &excluded_jira_tickets);
+			remove_excluded_jira_tickets(&mut jira_tickets_on_object_b, &excluded_jira_tickets);
+
+			// Find the intersection and symmetric differences between the sets
+			let mut jira_tickets_only_on_object_a = Vec::new();
 			let mut jira_tickets_only_on_object_b = Vec::new();
 			let mut jira_tickets_on_both_objects = HashMap::new();
 			for (jira_ticket, commits) in &jira_tickets_on_object_a {
@@ -416,152 +1591,434 @@ fn main() -> Result<()> {
 			} else {
 				jira_tickets_only_on_object_b.len()
 			};
+			run_details.record_count(
+				"jira_tickets_only_on_a",
+				jira_tickets_only_on_object_a_total,
+			);
+			run_details.record_count(
+				"jira_tickets_only_on_b",
+				jira_tickets_only_on_object_b_total,
+			);
+			run_details.record_count("jira_tickets_on_both", jira_tickets_on_both_objects_total);
 
 			// Display the results
-			writeln!(&mut multi_writer)?;
-			writeln!(
-				&mut multi_writer,
-				"Jira tickets only on `{object_a}`: ({jira_tickets_only_on_object_a_total} total)"
-			)?;
-			display_jira_ticket_commit_list(
-				&mut multi_writer,
-				jira_tickets_only_on_object_a.as_slice(),
-				show_commits,
-				hash_length,
-				ticket_prefix,
-			)?;
+			// The boundaries of each section are recorded as they're written, so that
+			// individual sections can be copied to the clipboard
+			// Sections that weren't requested are left out entirely, and their boundaries
+			// are left empty
+			// With `--summary-only`, only the totals lines of each section are displayed
+			if summary_only {
+				writeln!(&mut multi_writer)?;
+			}
+			let section_only_on_object_a_start = multi_writer.bytes_written();
+			if show_section_only_on_object_a {
+				if !summary_only {
+					writeln!(&mut multi_writer)?;
+				}
+				writeln!(
+					&mut multi_writer,
+					"Jira tickets only on `{object_a}`: ({jira_tickets_only_on_object_a_total} \
+					 total)"
+				)?;
+				if !summary_only {
+					display_jira_ticket_commit_list(
+						&mut multi_writer,
+						jira_tickets_only_on_object_a.as_slice(),
+						show_commits,
+						commit_limit_per_ticket,
+						None,
+						&commit_display_options,
+						&ticket_display,
+					)?;
+				}
+			}
+			let section_only_on_object_a_end = multi_writer.bytes_written();
 
-			writeln!(&mut multi_writer)?;
+			let section_only_on_object_b_start = multi_writer.bytes_written();
+			if show_section_only_on_object_b {
+				if !summary_only {
+					writeln!(&mut multi_writer)?;
+				}
+				writeln!(
+					&mut multi_writer,
+					"Jira tickets only on `{object_b}`: ({jira_tickets_only_on_object_b_total} \
+					 total)"
+				)?;
+				if !summary_only {
+					display_jira_ticket_commit_list(
+						&mut multi_writer,
+						jira_tickets_only_on_object_b.as_slice(),
+						show_commits,
+						commit_limit_per_ticket,
+						None,
+						&commit_display_options,
+						&ticket_display,
+					)?;
+				}
+			}
+			let section_only_on_object_b_end = multi_writer.bytes_written();
 
-			writeln!(
-				&mut multi_writer,
-				"Jira tickets only on `{object_b}`: ({jira_tickets_only_on_object_b_total} total)"
-			)?;
-			display_jira_ticket_commit_list(
-				&mut multi_writer,
-				jira_tickets_only_on_object_b.as_slice(),
-				show_commits,
-				hash_length,
-				ticket_prefix,
-			)?;
+			let section_on_both_objects_start = multi_writer.bytes_written();
+			if show_section_on_both_objects {
+				if !summary_only {
+					writeln!(&mut multi_writer)?;
+				}
+				writeln!(
+					&mut multi_writer,
+					"Jira tickets on both `{object_a}` and `{object_b}`: \
+					 ({jira_tickets_on_both_objects_total} total)"
+				)?;
+				if !summary_only {
+					display_jira_ticket_commit_list_intersection(
+						&mut multi_writer,
+						jira_tickets_on_both_objects_sorted.as_slice(),
+						object_a.as_str(),
+						object_b.as_str(),
+						show_commits,
+						&commit_display_options,
+						&ticket_display,
+					)?;
+				}
+			}
+			let section_on_both_objects_end = multi_writer.bytes_written();
 
-			writeln!(&mut multi_writer)?;
+			// Check which of the expected commits made it onto object B, if requested
+			let mut failed_expected_commit_count = 0;
+			if let Some(expected_commits_path) = expected_commits_path {
+				let expected_revisions = read_to_string(expected_commits_path)
+					.with_context(|| "unable to read the expected commits file")?
+					.lines()
+					.filter_map(|line| line.split_whitespace().next())
+					.filter(|revision| !revision.starts_with('#'))
+					.map(ToOwned::to_owned)
+					.collect::<Vec<_>>();
+				writeln!(&mut multi_writer)?;
+				failed_expected_commit_count = display_expected_commits_audit(
+					&mut multi_writer,
+					repo_dir.as_str(),
+					&index,
+					expected_revisions.as_slice(),
+					object_b.as_str(),
+					&commit_display_options,
+					&ticket_display,
+				)?;
+			}
 
-			writeln!(
-				&mut multi_writer,
-				"Jira tickets on both `{object_a}` and `{object_b}`: \
-				 ({jira_tickets_on_both_objects_total} total)"
-			)?;
-			display_jira_ticket_commit_list_intersection(
-				&mut multi_writer,
-				jira_tickets_on_both_objects_sorted.as_slice(),
-				object_a.as_str(),
-				object_b.as_str(),
-				show_commits,
-				hash_length,
-				ticket_prefix,
+			// Copy the output to the clipboard if specified
+			let compare_sections = CompareSections {
+				only_on_object_a: section_only_on_object_a_start..section_only_on_object_a_end,
+				only_on_object_b: section_only_on_object_b_start..section_only_on_object_b_end,
+				on_both_objects:  section_on_both_objects_start..section_on_both_objects_end,
+			};
+			copy_captured_output_to_clipboard(
+				captured_output.as_deref(),
+				compare_sections.clipboard_range(clipboard_section.as_str()),
 			)?;
 
-			// Copy the output to the clipboard if specified
-			if copy_to_clipboard {
-				copy_str_to_clipboard(from_utf8(string_output_raw.as_slice()).expect(
-					"only string values were written to the buffer, so it's guaranteed to be \
-					 valid UTF-8",
-				))?;
+			// Fail the run if it's being used as a gate
+			if strict && failed_expected_commit_count > 0 {
+				return Err(anyhow!(
+					"{failed_expected_commit_count} of the expected commits are absent from \
+					 `{object_b}` or couldn't be resolved"
+				));
+			}
+			if fail_if_empty
+				&& jira_tickets_only_on_object_a_total == 0
+				&& jira_tickets_only_on_object_b_total == 0
+				&& jira_tickets_on_both_objects_total == 0
+			{
+				return Err(anyhow!(
+					"the comparison is empty, since no Jira tickets were found on either object"
+				));
 			}
 		}
 		Some(("search", matches)) => {
 			// Collect the CLI arguments that were provided
-			let repo_dir = matches
-				.get_one::<String>("repo")
-				.expect("Clap ensures the argument is provided");
+			let repo_dir = &get_repo_dir(matches).expect("Clap provides a default value");
 			let jira_tickets = matches
 				.get_many::<String>("jira-ticket")
 				.expect("Clap ensures at least one argument is provided")
 				.collect::<Vec<_>>();
 			let search_tags = *matches.get_one::<bool>("search-tags").unwrap_or(&false);
 			let local_branches = *matches.get_one::<bool>("local-branches").unwrap_or(&false);
-			let include_mentioned_jira_tickets = *matches
-				.get_one::<bool>("include-mentioned")
+			let containment_strategy = match matches
+				.get_one::<String>("containment-strategy")
+				.expect("Clap provides a default value")
+				.as_str()
+			{
+				"git" => ContainmentStrategy::Git,
+				"graph" => ContainmentStrategy::Graph,
+				_ => unreachable!("Clap ensures only the possible values are provided"),
+			};
+			let all_of = *matches.get_one::<bool>("all-of").unwrap_or(&false);
+			let include_merge_commits = *matches
+				.get_one::<bool>("include-merge-commits")
 				.unwrap_or(&false);
-			let hash_length = *matches
-				.get_one::<u32>("hash-length")
-				.expect("Clap provides a default value") as usize;
-			let ticket_prefix = matches
-				.get_one::<String>("ticket-prefix")
-				.expect("Clap provides a default value");
-			let copy_to_clipboard = *matches
-				.get_one::<bool>("copy-to-clipboard")
+			let strip_remote_prefix = *matches
+				.get_one::<bool>("strip-remote-prefix")
+				.unwrap_or(&false);
+			let collapse_identical_branches = *matches
+				.get_one::<bool>("collapse-identical-branches")
+				.unwrap_or(&false);
+			let no_back_tree = *matches.get_one::<bool>("no-back-tree").unwrap_or(&false);
+			let show_provenance = *matches.get_one::<bool>("show-provenance").unwrap_or(&false);
+			let branch_sort_order = match matches
+				.get_one::<String>("sort-branches-by")
+				.expect("Clap provides a default value")
+				.as_str()
+			{
+				"name" => BranchSortOrder::Name,
+				"date" => BranchSortOrder::Date,
+				_ => unreachable!("Clap ensures only the possible values are provided"),
+			};
+			let max_branches = matches
+				.get_one::<u32>("max-branches")
+				.map(|max_branches| *max_branches as usize);
+			let ticket_exclusions = get_ticket_exclusions(matches);
+			let link_kinds = get_link_kinds(matches);
+			let hide_unreferenced = *matches
+				.get_one::<bool>("hide-unreferenced")
+				.unwrap_or(&false);
+			let hash_length = get_hash_length(matches);
+			let show_svn_path = *matches.get_one::<bool>("show-svn-path").unwrap_or(&false);
+			let show_svn_revision = *matches
+				.get_one::<bool>("show-svn-revision")
 				.unwrap_or(&false);
+			let compact = *matches.get_one::<bool>("compact").unwrap_or(&false);
+			let indent = matches.get_one::<String>("indent").map(String::as_str);
+			let compact_form = use_compact_form(compact, indent, output_is_interactive);
+			let merge_marker = matches
+				.get_one::<String>("merge-marker")
+				.expect("Clap provides a default value");
+			let ticket_display = get_ticket_display(matches);
+
+			let commit_display_options = CommitDisplayOptions {
+				hash_length,
+				show_svn_path,
+				show_svn_revision,
+				svn_layout: &svn_layout,
+				indentation: get_indentation_str(compact_form, indent),
+				merge_marker,
+			};
+
+			// Make sure the installed Git supports the requested features
+			if hide_unreferenced {
+				require_git_version("`--hide-unreferenced`", FOR_EACH_REF_CONTAINS_VERSION)?;
+			}
+			if containment_strategy == ContainmentStrategy::Graph {
+				require_git_version("`--containment-strategy graph`", REFNAME_LSTRIP_VERSION)?;
+			}
 
 			// Print the search criteria
-			writeln!(
-				&mut multi_writer,
-				"Searching for all locations where any commits were merged for the following:"
-			)?;
+			if all_of {
+				writeln!(
+					&mut multi_writer,
+					"Searching for all locations where commits from every one of the following \
+					 were merged:"
+				)?;
+			} else {
+				writeln!(
+					&mut multi_writer,
+					"Searching for all locations where any commits were merged for the following:"
+				)?;
+			}
 			for jira_ticket in &jira_tickets {
-				writeln!(&mut multi_writer, "- {ticket_prefix}{jira_ticket}")?;
+				writeln!(
+					&mut multi_writer,
+					"- {}",
+					ticket_display.render(Some(jira_ticket.as_str()), RenderTarget::Markdown)
+				)?;
 			}
+			run_details.record_target(
+				"jira_tickets",
+				jira_tickets
+					.iter()
+					.map(|jira_ticket| jira_ticket.as_str())
+					.collect::<Vec<_>>()
+					.join(",")
+					.as_str(),
+			);
 
 			writeln!(&mut multi_writer)?;
 
-			// Collect all commits in the repo
-			let commits =
-				get_complete_commit_list(repo_dir.as_str(), include_mentioned_jira_tickets)
-					.with_context(|| "unable to build the complete commit list from the repo")?;
+			// Collect all commits and annotated tags in the repo
+			let ticket_parsing_options =
+				get_ticket_parsing_options(matches, ticket_exclusions.as_slice(), false);
+			let repo_history =
+				RepoHistory::read(matches, repo_dir.as_str(), &ticket_parsing_options)?;
 
 			// Build the index
-			let index = Index::new(commits.as_slice())?;
+			let index = repo_history.build_index(link_kinds.as_slice())?;
 
-			// Find commits that belong to the ticket directly
-			// This is an expensive operation, but building it into the index would likely
-			// be even worse since we only search once
-			let direct_commits = commits
-				.iter()
-				.filter(|commit| {
-					jira_tickets
-						.iter()
-						.any(|jira_ticket| commit.jira_tickets.contains(jira_ticket))
-				})
-				.collect::<Vec<_>>();
+			// Find commits that belong to the tickets directly
+			let mut direct_commits = index.lookup_jira_tickets(jira_tickets.as_slice());
+
+			// Leave the commits by excluded authors out of the commits being searched
+			let excluded_author_patterns = get_excluded_author_patterns(matches);
+			if !excluded_author_patterns.is_empty() {
+				let direct_commit_count = direct_commits.len();
+				direct_commits
+					.retain(|commit| !is_by_excluded_author(commit, &excluded_author_patterns));
+				let excluded_author_commit_count = direct_commit_count - direct_commits.len();
+				if excluded_author_commit_count > 0 {
+					writeln!(
+						&mut multi_writer,
+						"Excluded {excluded_author_commit_count} commit(s) by excluded authors \
+						 from the commits being searched."
+					)?;
+					writeln!(&mut multi_writer)?;
+				}
+			}
+
+			// Leave merge commits out of the commits being searched unless requested, since
+			// they drag in everything they merge
+			// They're still followed when looking for the commits that merge the others
+			if !include_merge_commits {
+				let direct_commit_count = direct_commits.len();
+				direct_commits.retain(|commit| commit.parent_revisions.len() <= 1);
+				let excluded_merge_commit_count = direct_commit_count - direct_commits.len();
+				if excluded_merge_commit_count > 0 {
+					writeln!(
+						&mut multi_writer,
+						"Excluded {excluded_merge_commit_count} merge commit(s) from the commits \
+						 being searched (use `--include-merge-commits` to include them)."
+					)?;
+					writeln!(&mut multi_writer)?;
+				}
+			}
 
 			// Find all merges of those commits
-			let back_reference_inclusion_tree =
-				build_commit_inclusion_tree(&index, direct_commits.as_slice(), false, true)
-					.with_context(|| "unable to process direct commit results")?;
+			let back_reference_inclusion_tree = build_commit_inclusion_tree(
+				&index,
+				direct_commits.as_slice(),
+				LinkTraversal::Backward,
+				true,
+			)
+			.with_context(|| "unable to process direct commit results")?;
 
-			// Display the back-reference inclusion tree
-			writeln!(
-				&mut multi_writer,
-				"Commit list being searched, with commits that merge them elsewhere as \
-				 sub-entries:"
-			)?;
-			display_commit_reference_tree(
-				&mut multi_writer,
-				back_reference_inclusion_tree.as_slice(),
-				0,
-				hash_length,
-			)?;
+			// Display the back-reference inclusion tree, unless it's been suppressed
+			// Commits that aren't on any ref are only hidden from this display - they
+			// can't contribute any locations to the results anyway
+			if !no_back_tree {
+				let filtered_back_reference_inclusion_tree;
+				let displayed_back_reference_inclusion_tree = if hide_unreferenced {
+					filtered_back_reference_inclusion_tree = remove_unreferenced_commits(
+						repo_dir,
+						back_reference_inclusion_tree.clone(),
+						&mut HashMap::new(),
+					)
+					.with_context(|| "unable to filter out unreferenced commits")?;
+					filtered_back_reference_inclusion_tree.as_slice()
+				} else {
+					back_reference_inclusion_tree.as_slice()
+				};
+				writeln!(
+					&mut multi_writer,
+					"Commit list being searched, with commits that merge them elsewhere as \
+					 sub-entries:"
+				)?;
+				display_commit_reference_tree(
+					&mut multi_writer,
+					displayed_back_reference_inclusion_tree,
+					0,
+					&commit_display_options,
+				)?;
 
-			writeln!(&mut multi_writer)?;
+				writeln!(&mut multi_writer)?;
+			}
 
 			// Find all branches (and tags, if requested) that contain any of those commits
 			// This yields a list of locations per commit, which then need to be transposed
 			// into a list of commits per location
 			// A `Vec` is used here instead of a `HashSet` to preserve the order of the
 			// commits
+			// Each commit is only in it once, even if it merges several of the commits
+			// being searched, so it's only looked up once
 			let flattened_inclusion_tree =
 				flatten_inclusion_tree(back_reference_inclusion_tree.as_slice());
+			let flattened_commits = flattened_inclusion_tree
+				.iter()
+				.map(|flattened_commit| flattened_commit.commit)
+				.collect::<Vec<_>>();
+			let provenance = show_provenance.then(|| {
+				flattened_inclusion_tree
+					.iter()
+					.map(|flattened_commit| {
+						(
+							flattened_commit.commit.git_revision.as_str(),
+							*flattened_commit,
+						)
+					})
+					.collect::<HashMap<_, _>>()
+			});
+
+			// Find the branches that are identical to others, so that only one of each
+			// group is searched
+			let branch_tips = if collapse_identical_branches {
+				get_branch_tips(repo_dir, local_branches)
+					.with_context(|| "unable to get the tips of the branches")?
+			} else {
+				HashMap::new()
+			};
+			let identical_branches = group_identical_branches(&branch_tips);
+			let branch_tip_dates = get_branch_tip_dates(repo_dir, local_branches)
+				.with_context(|| "unable to get the dates of the branch tips")?;
+			let collapsed_branches = identical_branches
+				.values()
+				.flatten()
+				.copied()
+				.collect::<HashSet<_>>();
+
+			// With the graph strategy, the containment for every commit is worked out up
+			// front in a single pass
+			let mut branches_per_commit = HashMap::new();
+			let mut tags_per_commit = HashMap::new();
+			if containment_strategy == ContainmentStrategy::Graph {
+				let branch_tips = get_branch_tips_for_containment(repo_dir, local_branches)
+					.with_context(|| "unable to get the tips of the branches")?;
+				branches_per_commit = get_locations_containing(
+					&index,
+					branch_tips.as_slice(),
+					flattened_commits.as_slice(),
+				);
+				if search_tags {
+					let tag_tips = get_tag_tips(repo_dir)
+						.with_context(|| "unable to get the tips of the tags")?;
+					tags_per_commit = get_locations_containing(
+						&index,
+						tag_tips.as_slice(),
+						flattened_commits.as_slice(),
+					);
+				}
+			}
+
+			// The commits are visited in the flattened tree's order, so each location's
+			// commits always end up in that order too
 			let mut commits_per_branch: HashMap<String, Vec<&Commit>> = HashMap::new();
 			let mut commits_per_tag: HashMap<String, Vec<&Commit>> = HashMap::new();
-			for commit in flattened_inclusion_tree {
+			for commit in flattened_commits {
 				// Process all branches containing the commit
-				let branches_containing_commit =
-					get_branches_containing(repo_dir, commit.git_revision.as_str(), local_branches)
-						.with_context(|| {
-							"unable to get the list of branches containing a commit"
-						})?;
+				let branches_containing_commit = match containment_strategy {
+					ContainmentStrategy::Git => get_branches_containing(
+						repo_dir,
+						commit.git_revision.as_str(),
+						local_branches,
+					)
+					.with_context(|| "unable to get the list of branches containing a commit")?,
+					ContainmentStrategy::Graph => {
+						branches_per_commit.get(commit).cloned().unwrap_or_default()
+					}
+				};
 				for branch in branches_containing_commit {
+					// Symbolic branches like `origin/HEAD` are always aliases, so they're
+					// skipped along with the branches collapsed into others
+					if collapse_identical_branches
+						&& (branch.contains(" -> ")
+							|| collapsed_branches
+								.contains(branch.trim_start_matches(['*', '+', ' '])))
+					{
+						continue;
+					}
 					commits_per_branch
 						.entry(branch)
 						.and_modify(|commit_set| commit_set.push(commit))
@@ -570,10 +2027,17 @@ fn main() -> Result<()> {
 
 				// Process all tags containing the commit
 				if search_tags {
-					let tags_containing_commit =
-						get_tags_containing(repo_dir, commit.git_revision.as_str()).with_context(
-							|| "unable to get the list of tags containing a commit",
-						)?;
+					let tags_containing_commit = match containment_strategy {
+						ContainmentStrategy::Git => {
+							get_tags_containing(repo_dir, commit.git_revision.as_str())
+								.with_context(|| {
+									"unable to get the list of tags containing a commit"
+								})?
+						}
+						ContainmentStrategy::Graph => {
+							tags_per_commit.get(commit).cloned().unwrap_or_default()
+						}
+					};
 					for tag in tags_containing_commit {
 						commits_per_tag
 							.entry(tag)
@@ -583,107 +2047,272 @@ fn main() -> Result<()> {
 				}
 			}
 
-			// Group those locations by the commits they contain
-			let mut locations_per_commit_set: HashMap<Vec<&Commit>, (Vec<String>, Vec<String>)> =
-				HashMap::new();
-			for (branch, commit_set) in commits_per_branch {
-				locations_per_commit_set
-					.entry(commit_set)
-					.and_modify(|(branch_list, _)| branch_list.push(branch.clone()))
-					.or_insert_with(|| (vec![branch], Vec::new()));
-			}
-			for (tag, commit_set) in commits_per_tag {
-				locations_per_commit_set
-					.entry(commit_set)
-					.and_modify(|(_, tag_list)| tag_list.push(tag.clone()))
-					.or_insert_with(|| (Vec::new(), vec![tag]));
+			// Only keep the locations that contain commits from every ticket, if requested
+			// Each commit carries the tickets of the direct commits it merges
+			if all_of {
+				let searched_jira_ticket_count = jira_tickets.iter().collect::<HashSet<_>>().len();
+				let mut carried_jira_tickets = HashMap::new();
+				collect_carried_jira_tickets(
+					back_reference_inclusion_tree.as_slice(),
+					&HashSet::new(),
+					jira_tickets.as_slice(),
+					&mut carried_jira_tickets,
+				);
+				let covers_all_jira_tickets = |commit_set: &Vec<&Commit>| {
+					let covered_jira_tickets = commit_set
+						.iter()
+						.filter_map(|commit| carried_jira_tickets.get(commit.git_revision.as_str()))
+						.flatten()
+						.collect::<HashSet<_>>();
+					covered_jira_tickets.len() == searched_jira_ticket_count
+				};
+				commits_per_branch.retain(|_, commit_set| covers_all_jira_tickets(commit_set));
+				commits_per_tag.retain(|_, commit_set| covers_all_jira_tickets(commit_set));
 			}
 
-			// Sort the location lists
-			// The locations are collected into the sets without a particular order, and
-			// sorting them makes the final output more readable
+			// Group those locations by the commits they contain, in a stable order
+			// Each branch is paired with the branches identical to it, if they were
+			// collapsed
 			let mut locations_per_commit_set_ordered =
-				locations_per_commit_set.drain().collect::<Vec<_>>();
-			for (_, (branch_list, tag_list)) in &mut locations_per_commit_set_ordered {
-				branch_list.sort();
-				tag_list.sort();
-			}
+				group_locations_by_commit_set(commits_per_branch, commits_per_tag)
+					.into_iter()
+					.map(|(commit_set, (mut branch_list, tag_list))| {
+						// The sort is stable, so branches with the same date stay in name order
+						if branch_sort_order == BranchSortOrder::Date {
+							branch_list.sort_by_cached_key(|branch| {
+								Reverse(get_branch_tip_date(&branch_tip_dates, branch))
+							});
+						}
+						let branch_tip_date_list = branch_list
+							.iter()
+							.map(|branch| get_branch_tip_date(&branch_tip_dates, branch))
+							.collect::<Vec<_>>();
+						let identical_branch_lists = branch_list
+							.iter()
+							.map(|branch| {
+								identical_branches
+									.get(branch.trim_start_matches(['*', '+', ' ']))
+									.map_or_else(Vec::new, |identical_branch_list| {
+										identical_branch_list
+											.iter()
+											.map(|&identical_branch| identical_branch.to_owned())
+											.collect()
+									})
+							})
+							.collect::<Vec<_>>();
+						(
+							commit_set,
+							(
+								branch_list,
+								identical_branch_lists,
+								branch_tip_date_list,
+								tag_list,
+							),
+						)
+					})
+					.collect::<Vec<_>>();
 
-			// Sort the list of branch sets to put the sets with the most branches near the
-			// top
-			locations_per_commit_set_ordered
-				.sort_by_key(|(_, (branch_list, tag_list))| branch_list.len() + tag_list.len());
-			locations_per_commit_set_ordered.reverse();
+			// Shorten the remote branch names if requested
+			// This is done per set, since ambiguity only matters within a single set
+			if strip_remote_prefix {
+				let remotes =
+					get_remotes(repo_dir).with_context(|| "unable to get the list of remotes")?;
+				// The identical branches are left qualified, since they're only
+				// distinguishable from the branch they're listed next to by their remote
+				for (_, (branch_list, ..)) in &mut locations_per_commit_set_ordered {
+					*branch_list =
+						strip_remote_prefixes(branch_list.as_slice(), remotes.as_slice());
+				}
+			}
 
 			// Display the branches where each specific set of commits is
+			run_details.record_count("result_sets", locations_per_commit_set_ordered.len());
+			let indentation = commit_display_options.indentation;
 			writeln!(&mut multi_writer, "Results:")?;
-			for (index, (commit_set, (branch_list, tag_list))) in
-				locations_per_commit_set_ordered.iter().enumerate()
+			for (
+				index,
+				(commit_set, (branch_list, identical_branch_lists, branch_tip_date_list, tag_list)),
+			) in locations_per_commit_set_ordered.iter().enumerate()
 			{
 				writeln!(&mut multi_writer, "- Set {index}:")?;
-				writeln!(&mut multi_writer, "\t- Commits:")?;
-				display_commit_set(&mut multi_writer, commit_set.as_slice(), 2, hash_length)?;
+				writeln!(&mut multi_writer, "{indentation}- Commits:")?;
+				display_commit_set(
+					&mut multi_writer,
+					commit_set.as_slice(),
+					2,
+					provenance.as_ref(),
+					&commit_display_options,
+				)?;
 				if !branch_list.is_empty() {
-					writeln!(&mut multi_writer, "\t- Branches:")?;
-					for branch in branch_list {
-						writeln!(&mut multi_writer, "\t\t- `{branch}`")?;
+					writeln!(&mut multi_writer, "{indentation}- Branches:")?;
+					let displayed_branch_count = max_branches
+						.map_or(branch_list.len(), |max_branches| {
+							max_branches.min(branch_list.len())
+						});
+					for ((branch, identical_branch_list), branch_tip_date) in branch_list
+						.iter()
+						.zip(identical_branch_lists)
+						.zip(branch_tip_date_list)
+						.take(displayed_branch_count)
+					{
+						write!(
+							&mut multi_writer,
+							"{indentation}{indentation}- {}",
+							Fragment::Code(branch).render(RenderTarget::Markdown)
+						)?;
+						if let Some(branch_tip_date) = branch_tip_date {
+							write!(&mut multi_writer, " (updated {branch_tip_date})")?;
+						}
+						if !identical_branch_list.is_empty() {
+							write!(
+								&mut multi_writer,
+								" (+{} identical: {})",
+								identical_branch_list.len(),
+								identical_branch_list
+									.iter()
+									.map(|identical_branch| Fragment::Code(identical_branch)
+										.render(RenderTarget::Markdown))
+									.collect::<Vec<_>>()
+									.join(", ")
+							)?;
+						}
+						writeln!(&mut multi_writer)?;
+					}
+					if displayed_branch_count < branch_list.len() {
+						writeln!(
+							&mut multi_writer,
+							"{indentation}{indentation}- ...and {} more branches",
+							branch_list.len() - displayed_branch_count
+						)?;
 					}
 				}
 				if search_tags && !tag_list.is_empty() {
-					writeln!(&mut multi_writer, "\t- Tags:")?;
+					writeln!(&mut multi_writer, "{indentation}- Tags:")?;
 					for tag in tag_list {
-						writeln!(&mut multi_writer, "\t\t- `{tag}`")?;
+						writeln!(
+							&mut multi_writer,
+							"{indentation}{indentation}- {}",
+							Fragment::Code(tag).render(RenderTarget::Markdown)
+						)?;
 					}
 				}
 			}
 
 			// Copy the output to the clipboard if specified
-			if copy_to_clipboard {
-				copy_str_to_clipboard(from_utf8(string_output_raw.as_slice()).expect(
-					"only string values were written to the buffer, so it's guaranteed to be \
-					 valid UTF-8",
-				))?;
-			}
+			copy_captured_output_to_clipboard(captured_output.as_deref(), None)?;
 		}
 		Some(("revmap", matches)) => {
 			// Collect the CLI arguments that were provided
-			let repo_dir = matches
-				.get_one::<String>("repo")
-				.expect("Clap ensures the argument is provided");
-			let hash_length = *matches
-				.get_one::<u32>("hash-length")
-				.expect("Clap provides a default value") as usize;
+			let repo_dir = &get_repo_dir(matches).expect("Clap provides a default value");
+			let hash_length = get_hash_length(matches);
+			let markdown_style = match matches
+				.get_one::<String>("markdown-style")
+				.expect("Clap provides a default value")
+				.as_str()
+			{
+				"list" => MarkdownStyle::List,
+				"table" => MarkdownStyle::Table,
+				"split" => MarkdownStyle::Split {
+					chunk_size: *matches
+						.get_one::<u32>("markdown-chunk-size")
+						.expect("Clap provides a default value"),
+				},
+				_ => unreachable!("Clap ensures only the possible values are provided"),
+			};
 
 			// Collect all commits in the repo
-			let commits = get_complete_commit_list(repo_dir.as_str(), false)
-				.with_context(|| "unable to build the complete commit list from the repo")?;
+			let commits =
+				get_commit_list(matches, repo_dir.as_str(), &TicketParsingOptions::ignored())?;
 
-			// Build a revision map and discard any commits that don't have SVN info
-			let mut revision_map = commits
+			// Build a revision map, which leaves out any commits that don't have SVN info
+			let revision_map = build_revision_map(commits.as_slice(), &svn_layout);
+			run_details.record_count("revisions", revision_map.len());
+
+			// Only one of the outputs can go to stdout, since they'd be mixed together
+			// otherwise
+			if ["binary", "markdown", "markdown-table", "svn-fanin"]
 				.iter()
-				.filter_map(|commit| {
-					commit.svn_info.as_ref().map(|svn_info| {
-						(
-							svn_info.svn_revision,
-							svn_info.svn_url.as_str(),
-							commit.git_revision.as_str(),
-						)
-					})
+				.filter(|output| {
+					matches
+						.get_one::<String>(output)
+						.map_or(false, |path| path == STDOUT_PATH)
 				})
-				.collect::<Vec<_>>();
+				.count() > 1
+			{
+				return Err(anyhow!(
+					"only one output can be written to stdout (`{STDOUT_PATH}`) at a time"
+				));
+			}
 
-			// Sort the revision map to ensure that it's in order
-			revision_map.sort_by_key(|entry| entry.0); // Stable sort to preserve order in case of ties
+			// Each format can go to its own path, to the output directory, or both
+			let output_dir = matches.get_one::<String>("output-dir").map(PathBuf::from);
+			if let Some(output_dir) = &output_dir {
+				create_dir_all(output_dir).with_context(|| {
+					format!("unable to create the directory `{}`", output_dir.display())
+				})?;
+			}
+			let output_dir_formats = matches
+				.get_many::<String>("formats")
+				.expect("Clap provides a default value")
+				.map(String::as_str)
+				.collect::<HashSet<_>>();
+			let output_paths = |path_arg: &str, format: &str, file_name: &str| {
+				matches
+					.get_one::<String>(path_arg)
+					.map(PathBuf::from)
+					.into_iter()
+					.chain(
+						output_dir
+							.as_ref()
+							.filter(|_| output_dir_formats.contains(format))
+							.map(|output_dir| output_dir.join(file_name)),
+					)
+					.collect::<Vec<_>>()
+			};
 
 			// Write it to disk in the specified formats
-			if let Some(path) = matches.get_one::<String>("binary") {
+			for path in output_paths("binary", "bin", "revmap.bin") {
 				write_to_bin(path, revision_map.as_slice())
 					.with_context(|| "unable to write the revision map to binary")?;
 			}
-			if let Some(path) = matches.get_one::<String>("markdown") {
-				write_to_markdown(path, revision_map.as_slice(), hash_length)
+			for path in output_paths("markdown", "md", "revmap.md") {
+				write_to_markdown(path, revision_map.as_slice(), hash_length, markdown_style)
 					.with_context(|| "unable to write the revision map to markdown")?;
-			};
+			}
+			for path in output_paths("markdown-table", "md-table", "revmap-table.md") {
+				write_to_markdown(
+					path,
+					revision_map.as_slice(),
+					hash_length,
+					MarkdownStyle::Table,
+				)
+				.with_context(|| "unable to write the revision map to a markdown table")?;
+			}
+			let svn_fanin_paths = output_paths("svn-fanin", "fanin", "revmap-fanin.md");
+			if !svn_fanin_paths.is_empty() {
+				// Each commit is associated with its own SVN revision and any it references
+				let mut svn_fanin = commits
+					.iter()
+					.filter_map(|commit| {
+						let mut svn_revisions = commit
+							.svn_info
+							.iter()
+							.map(|svn_info| svn_info.svn_revision)
+							.chain(commit.referenced_commits.svn_commits.iter().copied())
+							.collect::<Vec<_>>();
+						svn_revisions.sort_unstable();
+						svn_revisions.dedup();
+						(svn_revisions.len() > 1)
+							.then_some((commit.git_revision.as_str(), svn_revisions))
+					})
+					.collect::<Vec<_>>();
+				// Put the biggest fan-ins first
+				svn_fanin.sort_by_key(|(_, svn_revisions)| Reverse(svn_revisions.len()));
+				for path in svn_fanin_paths {
+					write_svn_fanin_report(path, svn_fanin.as_slice(), hash_length)
+						.with_context(|| "unable to write the SVN fan-in report")?;
+				}
+			}
 		}
 		_ => unreachable!("Clap ensures that a subcommand is provided"),
 	}
@@ -691,89 +2320,1189 @@ fn main() -> Result<()> {
 	Ok(())
 }
 
-/// Flattens string sets based on shell "words".
-///
-/// For example: `"abc def", "ghi"` -> `"abc", "def", "ghi"`
-fn flatten_string_sets_on_shell_words(string_sets: ValuesRef<String>) -> Result<Vec<String>> {
-	let mut flattened_set = Vec::with_capacity(string_sets.len());
-	for string_set in string_sets {
-		flattened_set.extend(
-			split_shell_words(string_set.as_str())
-				.with_context(|| format!("unable to split set: {string_set}"))?,
-		);
+/// Prints the version of this build, along with everything else that's useful
+/// to know about it for a bug report.
+fn print_build_information(output: &mut dyn Write) -> Result<()> {
+	writeln!(output, "{APPLICATION_PROPER_NAME} {APPLICATION_VERSION}")?;
+	writeln!(output, "Commit: {BUILD_GIT_COMMIT}")?;
+	writeln!(output, "Target: {BUILD_TARGET}")?;
+	writeln!(output, "Compiler: {BUILD_RUSTC_VERSION}")?;
+	match get_git_version() {
+		Ok(git_version) => writeln!(output, "Git: {git_version}")?,
+		Err(error) => writeln!(output, "Git: unavailable ({error:#})")?,
 	}
-	flattened_set.sort_unstable();
 
-	Ok(flattened_set)
+	Ok(())
 }
 
-/// Group a set of included commits by Jira ticket.
-fn group_by_jira_tickets<'a>(
-	included_commits: &'a [IncludedCommit<'a>],
-) -> HashMap<Option<&'a str>, Vec<IncludedCommit<'a>>> {
-	let mut jira_ticket_groups = HashMap::new();
+/// Runs the environment and repo checks and prints a report, returning an
+/// error if any of them failed.
+fn print_doctor_report(output: &mut dyn Write, repo_dir: &str) -> Result<()> {
+	let check_results = run_checks(repo_dir)?;
+	for check_result in &check_results {
+		writeln!(
+			output,
+			"[{}] {}: {}",
+			check_result.status, check_result.name, check_result.detail
+		)?;
+	}
 
-	for included_commit in included_commits {
-		// The `clone` calls here are a little ugly, but the `IncludedCommit` struct
-		// basically just holds references anyway, so cloning it is cheap
-		if included_commit.commit.jira_tickets.is_empty() {
-			jira_ticket_groups
-				.entry(None)
-				.and_modify(|ticket_commits: &mut Vec<IncludedCommit>| {
-					ticket_commits.push(included_commit.clone());
-				})
-				.or_insert_with(|| vec![included_commit.clone()]);
-		} else {
-			for jira_ticket in &included_commit.commit.jira_tickets {
-				jira_ticket_groups
-					.entry(Some(jira_ticket.as_str()))
-					.and_modify(|ticket_commits: &mut Vec<IncludedCommit>| {
-						ticket_commits.push(included_commit.clone());
-					})
-					.or_insert_with(|| vec![included_commit.clone()]);
-			}
-		}
+	let count_status = |status| {
+		check_results
+			.iter()
+			.filter(|check_result| check_result.status == status)
+			.count()
+	};
+	let failure_count = count_status(CheckStatus::Fail);
+	writeln!(output)?;
+	writeln!(
+		output,
+		"{} passed, {} warning(s), {failure_count} failure(s)",
+		count_status(CheckStatus::Pass),
+		count_status(CheckStatus::Warn)
+	)?;
+
+	if failure_count > 0 {
+		return Err(anyhow!("{failure_count} check(s) failed"));
 	}
 
-	jira_ticket_groups
+	Ok(())
 }
 
-/// Displays the simple list of Jira tickets, optionally with commit
-/// information.
-#[allow(clippy::ref_option_ref)]
-fn display_jira_ticket_commit_list(
-	multi_writer: &mut MultiWriter,
-	jira_tickets: &[(&Option<&str>, &Vec<IncludedCommit>)],
-	show_commits: bool,
-	hash_length: usize,
-	ticket_prefix: &str,
-) -> Result<()> {
-	for (jira_ticket_option, commits) in jira_tickets {
-		let jira_ticket = if let Some(ticket) = jira_ticket_option {
-			format!("{ticket_prefix}{ticket}")
-		} else {
-			NO_JIRA_TICKET_STR.to_owned()
-		};
-		if show_commits {
-			writeln!(multi_writer, "- {jira_ticket}:")?;
-			display_commit_reference_tree(multi_writer, commits.as_slice(), 1, hash_length)?;
-		} else {
-			writeln!(multi_writer, "- {jira_ticket} ({})", commits.len())?;
-		}
+/// Gets the number of characters to abbreviate Git revision hashes to, taking
+/// `--full-hash` into account.
+fn get_hash_length(matches: &ArgMatches) -> usize {
+	if *matches.get_one::<bool>("full-hash").unwrap_or(&false) {
+		SHA1_HASH_ASCII_LENGTH
+	} else {
+		*matches
+			.get_one::<u32>("hash-length")
+			.expect("Clap provides a default value") as usize
 	}
+}
 
-	Ok(())
+/// Gets how Jira tickets are presented, from `--ticket-prefix`,
+/// `--strip-project`, and `--ticket-url`.
+fn get_ticket_display(matches: &ArgMatches) -> TicketDisplay {
+	TicketDisplay {
+		prefix:        matches
+			.get_one::<String>("ticket-prefix")
+			.expect("Clap provides a default value")
+			.as_str(),
+		strip_project: *matches.get_one::<bool>("strip-project").unwrap_or(&false),
+		url_template:  matches.get_one::<String>("ticket-url").map(String::as_str),
+	}
 }
 
-/// Displays the list of Jira tickets for the intersection between two objects'
-/// lists.
-///
-/// The counterpart of `display_jira_ticket_commit_list`.
-///
-/// The reason this is in its own function despite only being used once, is so
-/// that it can be updated in tandem with its counterpart.
-#[allow(clippy::ref_option_ref, clippy::type_complexity)]
-fn display_jira_ticket_commit_list_intersection(
+/// Gets the patterns for text that's never a ticket, from
+/// `--ticket-pattern-exclude`.
+fn get_ticket_exclusions(matches: &ArgMatches) -> Vec<Regex> {
+	matches
+		.get_many::<Regex>("ticket-pattern-exclude")
+		.map_or_else(Vec::new, |ticket_exclusions| {
+			ticket_exclusions.cloned().collect()
+		})
+}
+
+/// Gets how Jira tickets are parsed from commit messages, from the ticket
+/// parsing arguments and the exclusions from [`get_ticket_exclusions`].
+fn get_ticket_parsing_options<'a>(
+	matches: &ArgMatches,
+	exclusions: &'a [Regex],
+	track_mentions: bool,
+) -> TicketParsingOptions<'a> {
+	TicketParsingOptions {
+		include_mentioned: *matches
+			.get_one::<bool>("include-mentioned")
+			.unwrap_or(&false),
+		filter_mentioned: !*matches
+			.get_one::<bool>("no-mention-filtering")
+			.unwrap_or(&false),
+		exclusions,
+		min_project_length: *matches
+			.get_one::<u32>("min-project-length")
+			.expect("Clap provides a default value") as usize,
+		track_mentions,
+		include_notes: *matches.get_one::<bool>("include-notes").unwrap_or(&false),
+		svn_hash_references: *matches.get_one::<bool>("svn-hash-refs").unwrap_or(&false),
+	}
+}
+
+/// Collects all commits in the repo, from the refs in `--ref-scope` and taking
+/// `--lenient-parsing` into account.
+fn get_commit_list(
+	matches: &ArgMatches,
+	repo_dir: &str,
+	ticket_parsing_options: &TicketParsingOptions,
+) -> Result<Vec<Commit>> {
+	let ref_scopes = matches
+		.get_many::<String>("ref-scope")
+		.map_or_else(Vec::new, |ref_scopes| {
+			ref_scopes.cloned().collect::<Vec<_>>()
+		});
+	let lenient_parsing = *matches.get_one::<bool>("lenient-parsing").unwrap_or(&false);
+
+	get_complete_commit_list(
+		repo_dir,
+		ticket_parsing_options,
+		ref_scopes.as_slice(),
+		lenient_parsing,
+	)
+	.with_context(|| "unable to build the complete commit list from the repo")
+}
+
+/// Gets the author patterns to leave out, from `--exclude-author` and
+/// `--exclude-bots`.
+fn get_excluded_author_patterns(matches: &ArgMatches) -> Vec<String> {
+	let mut excluded_author_patterns = matches
+		.get_many::<String>("exclude-author")
+		.map(|patterns| patterns.cloned().collect::<Vec<_>>())
+		.unwrap_or_default();
+	if *matches.get_one::<bool>("exclude-bots").unwrap_or(&false) {
+		excluded_author_patterns.extend(
+			BOT_AUTHOR_PATTERNS
+				.iter()
+				.map(|&pattern| pattern.to_owned()),
+		);
+	}
+	excluded_author_patterns
+}
+
+/// Looks up the date of a branch's tip, given the branch the way `git branch`
+/// displays it (with markers like `* ` for the current branch, and the targets
+/// of symbolic branches).
+fn get_branch_tip_date<'a>(
+	branch_tip_dates: &'a HashMap<String, String>,
+	branch: &str,
+) -> Option<&'a str> {
+	let branch = branch.trim_start_matches(['*', '+', ' ']);
+	let branch = branch
+		.split_once(" -> ")
+		.map_or(branch, |(symbolic_branch, _)| symbolic_branch);
+	branch_tip_dates.get(branch).map(String::as_str)
+}
+
+/// Gets the repo to read from, which is `--repo` if it was provided, then
+/// `$CLOG_REPO`, then the current directory.
+///
+/// The environment variable is checked after parsing, since Clap's default
+/// would always win over it otherwise.
+fn get_repo_dir(matches: &ArgMatches) -> Option<String> {
+	let repo_dir = matches.try_get_one::<String>("repo").ok().flatten()?;
+	if matches.value_source("repo") == Some(ValueSource::DefaultValue) {
+		if let Some(env_repo_dir) = env::var(REPO_ENV_VAR)
+			.ok()
+			.filter(|env_repo_dir| !env_repo_dir.is_empty())
+		{
+			return Some(env_repo_dir);
+		}
+	}
+
+	Some(repo_dir.clone())
+}
+
+/// Gets the kinds of references to link commits through, from `--link-kinds`.
+fn get_link_kinds(matches: &ArgMatches) -> Vec<LinkKind> {
+	matches
+		.get_many::<String>("link-kinds")
+		.expect("Clap provides a default value")
+		.map(|link_kind| match link_kind.as_str() {
+			"git" => LinkKind::Git,
+			"svn" => LinkKind::Svn,
+			_ => unreachable!("Clap ensures only the possible values are provided"),
+		})
+		.collect()
+}
+
+/// Copies the captured output to the clipboard, or just the part in `range` if
+/// one is provided.
+///
+/// The output is only captured when copying to the clipboard was requested, so
+/// nothing is copied if there isn't any.
+fn copy_captured_output_to_clipboard(
+	captured_output: Option<&[u8]>,
+	range: Option<Range<usize>>,
+) -> Result<()> {
+	let Some(captured_output) = captured_output else {
+		return Ok(());
+	};
+
+	copy_str_to_clipboard(select_captured_output(captured_output, range))
+}
+
+/// Gets the part of the captured output in `range`, or all of it if there's no
+/// range.
+fn select_captured_output(captured_output: &[u8], range: Option<Range<usize>>) -> &str {
+	let range = range.unwrap_or(0..captured_output.len());
+	from_utf8(&captured_output[range]).expect(
+		"only string values were written to the buffer, and the section boundaries are always \
+		 between lines, so it's guaranteed to be valid UTF-8",
+	)
+}
+
+/// Makes sure that stdin isn't needed for anything else before arguments are
+/// read from it.
+///
+/// On some OSes, copying to the clipboard waits for Enter on stdin, which
+/// would never come if stdin is piped in.
+fn ensure_stdin_is_free_for_arguments(copy_to_clipboard: bool) -> Result<()> {
+	if copy_to_clipboard && clipboard_reads_stdin() {
+		return Err(anyhow!(
+			"arguments can't be read from stdin while copying to the clipboard, since on this OS \
+			 the clipboard waits for Enter on stdin before exiting"
+		));
+	}
+
+	Ok(())
+}
+
+/// Builds the revspec for the changes between two tags, making sure that both
+/// tags exist and that they're in the right order.
+fn get_between_tags_revspec(repo_dir: &str, from_tag: &str, to_tag: &str) -> Result<String> {
+	for tag in [from_tag, to_tag] {
+		if get_tag_kind(repo_dir, tag)?.is_none() {
+			return Err(anyhow!("`{tag}` isn't a tag in the repo"));
+		}
+		peel_to_commit(repo_dir, tag)
+			.map_err(|_| anyhow!("the tag `{tag}` doesn't point to a commit"))?;
+	}
+
+	// If the newer tag comes first, the range would be empty, which is almost
+	// certainly not what was intended
+	if !is_ancestor(repo_dir, to_tag, from_tag)? || is_ancestor(repo_dir, from_tag, to_tag)? {
+		Ok(format!("{from_tag}..{to_tag}"))
+	} else {
+		Err(anyhow!(
+			"`{from_tag}` comes after `{to_tag}` in the history, so the tags seem to be in the \
+			 wrong order; try `--between-tags {to_tag} {from_tag}`"
+		))
+	}
+}
+
+/// Gets the revspec `list` uses when none is provided: everything on the
+/// current branch that isn't on its upstream yet.
+fn get_default_list_revspec(repo_dir: &str) -> Result<String> {
+	let head_state = get_head_state(repo_dir)
+		.with_context(|| "unable to determine the state of HEAD for the default revspec")?;
+	let branch = head_state
+		.require_branch()
+		.with_context(|| "no revspec was provided, and there's no current branch to default to")?;
+
+	let upstream = upstream_ref_if_possible(repo_dir, branch)?.ok_or_else(|| {
+		anyhow!(
+			"no revspec was provided, and the current branch `{branch}` doesn't have an upstream \
+			 to default to; pass an explicit revspec (like `origin/main..HEAD`), or set one with \
+			 `git branch --set-upstream-to`"
+		)
+	})?;
+
+	Ok(format!("{upstream}..HEAD"))
+}
+
+/// Describes an object being compared for the output header, including the
+/// commit it points to if it's a tag.
+fn describe_compare_object(
+	repo_dir: &str,
+	object: &str,
+	hash_length: usize,
+) -> Result<(String, String)> {
+	let tag_kind = get_tag_kind(repo_dir, object)?;
+	let git_revision = peel_to_commit(repo_dir, object).map_err(|_| match tag_kind {
+		Some(TagKind::Annotated) => {
+			anyhow!("the annotated tag `{object}` doesn't point to a commit")
+		}
+		Some(TagKind::Lightweight) => {
+			anyhow!("the lightweight tag `{object}` doesn't point to a commit")
+		}
+		None => anyhow!("`{object}` doesn't name a branch, tag, or commit in the repo"),
+	})?;
+
+	let abbreviated_revision = abbreviate(git_revision.as_str(), hash_length)?;
+	let description = match tag_kind {
+		Some(TagKind::Annotated) => {
+			format!("`{object}` (annotated tag of `{abbreviated_revision}`)")
+		}
+		Some(TagKind::Lightweight) => {
+			format!("`{object}` (lightweight tag of `{abbreviated_revision}`)")
+		}
+		None => format!("`{object}`"),
+	};
+	Ok((description, git_revision))
+}
+
+/// Prints a warning if a reference is a local branch that has diverged from
+/// its upstream.
+fn warn_if_diverged_from_upstream(repo_dir: &str, reference: &str) -> Result<()> {
+	if let Some(warning) = get_upstream_divergence_warning(repo_dir, reference)? {
+		eprintln!("[WARNING] {warning}");
+	}
+
+	Ok(())
+}
+
+/// Gets the warning for `warn_if_diverged_from_upstream`, if the reference is a
+/// local branch with commits that aren't on its upstream and the other way
+/// around.
+fn get_upstream_divergence_warning(repo_dir: &str, reference: &str) -> Result<Option<String>> {
+	let Some(upstream) = upstream_ref_if_possible(repo_dir, reference)? else {
+		return Ok(None);
+	};
+
+	let (local_only_total, remote_only_total) =
+		count_divergent_commits(repo_dir, reference, upstream.as_str())?;
+	Ok((local_only_total > 0 && remote_only_total > 0).then(|| {
+		format!(
+			"Local `{reference}` and `{upstream}` have diverged: {local_only_total} local-only, \
+			 {remote_only_total} remote-only commits. If the remote branch was force-pushed, you \
+			 probably want to use `{upstream}` instead."
+		)
+	}))
+}
+
+/// Flattens string sets based on shell "words".
+///
+/// For example: `"abc def", "ghi"` -> `"abc", "def", "ghi"`
+fn flatten_string_sets_on_shell_words(string_sets: ValuesRef<String>) -> Result<Vec<String>> {
+	let mut flattened_set = Vec::with_capacity(string_sets.len());
+	for string_set in string_sets {
+		flattened_set.extend(
+			split_shell_words(string_set.as_str())
+				.with_context(|| format!("unable to split set: {string_set}"))?,
+		);
+	}
+	flattened_set.sort_unstable();
+
+	Ok(flattened_set)
+}
+
+/// Collects the searched Jira tickets that each commit in an inclusion tree
+/// carries, keyed by Git revision.
+///
+/// A commit carries the searched tickets it mentions itself, along with those
+/// of every commit it's nested under in the tree, since a linked commit brings
+/// the work of the commit it's linked from along with it. This is what
+/// `--all-of` checks each location against.
+fn collect_carried_jira_tickets<'a>(
+	inclusion_tree: &[IncludedCommit<'a>],
+	inherited_jira_tickets: &HashSet<&'a str>,
+	searched_jira_tickets: &[&'a String],
+	carried_jira_tickets: &mut HashMap<&'a str, HashSet<&'a str>>,
+) {
+	for included_commit in inclusion_tree {
+		let mut commit_jira_tickets = inherited_jira_tickets.clone();
+		commit_jira_tickets.extend(
+			searched_jira_tickets
+				.iter()
+				.filter(|jira_ticket| included_commit.commit.jira_tickets.contains(jira_ticket))
+				.map(|jira_ticket| jira_ticket.as_str()),
+		);
+		collect_carried_jira_tickets(
+			included_commit.linked_commits.as_slice(),
+			&commit_jira_tickets,
+			searched_jira_tickets,
+			carried_jira_tickets,
+		);
+		carried_jira_tickets
+			.entry(included_commit.commit.git_revision.as_str())
+			.or_default()
+			.extend(commit_jira_tickets);
+	}
+}
+
+/// Flattens filepath sets based on shell "words", like
+/// `flatten_string_sets_on_shell_words`.
+///
+/// Absolute Windows paths are taken as-is instead, since splitting them would
+/// treat their backslashes as escapes.
+fn flatten_filepath_sets(filepath_sets: ValuesRef<String>) -> Result<Vec<String>> {
+	let mut flattened_set = Vec::with_capacity(filepath_sets.len());
+	for filepath_set in filepath_sets {
+		if is_windows_absolute_path_str(filepath_set.trim()) {
+			flattened_set.push(filepath_set.trim().to_owned());
+		} else {
+			flattened_set.extend(
+				split_shell_words(filepath_set.as_str())
+					.with_context(|| format!("unable to split set: {filepath_set}"))?,
+			);
+		}
+	}
+	flattened_set.sort_unstable();
+
+	Ok(flattened_set)
+}
+
+/// Rewrites absolute filepaths to be relative to the repo root, since Git
+/// won't match them otherwise.
+///
+/// It's an error for an absolute filepath to be outside of the repo.
+fn resolve_affected_filepaths(repo_dir: &str, filepaths: Vec<String>) -> Result<Vec<String>> {
+	if !filepaths
+		.iter()
+		.any(|filepath| is_absolute_path_str(filepath))
+	{
+		return Ok(filepaths);
+	}
+
+	// The root might be reached through a symlink, so the canonical form is tried
+	// too
+	let repo_root = get_repo_root(repo_dir)?;
+	let canonical_repo_root = canonicalize(repo_root.as_str())
+		.ok()
+		.map(|path| path.to_string_lossy().into_owned());
+
+	filepaths
+		.into_iter()
+		.map(|filepath| {
+			if !is_absolute_path_str(filepath.as_str()) {
+				return Ok(filepath);
+			}
+
+			let relative_filepath = relative_path_under(filepath.as_str(), repo_root.as_str())
+				.or_else(|| {
+					canonical_repo_root
+						.as_ref()
+						.and_then(|canonical_repo_root| {
+							relative_path_under(filepath.as_str(), canonical_repo_root.as_str())
+						})
+				})
+				.ok_or_else(|| {
+					anyhow!(
+						"the filepath `{filepath}` is outside of the repo at `{repo_root}`; \
+						 filepaths should be relative to the repo root"
+					)
+				})?;
+			eprintln!(
+				"[WARNING] The absolute filepath `{filepath}` was rewritten relative to the repo \
+				 root: `{relative_filepath}`"
+			);
+
+			Ok(relative_filepath)
+		})
+		.collect()
+}
+
+/// Gets the Jira tickets that each merge in a set of results brings in, keyed
+/// by the merge's Git revision, so the merges can be credited with them.
+fn get_merged_jira_tickets<'a>(
+	index: &Index<'a>,
+	included_commits: &[IncludedCommit<'a>],
+) -> HashMap<&'a str, Vec<&'a str>> {
+	included_commits
+		.iter()
+		.map(|included_commit| included_commit.commit)
+		.filter(|commit| commit.is_likely_a_merge)
+		.map(|commit| {
+			(
+				commit.git_revision.as_str(),
+				get_merged_jira_tickets_of(index, commit),
+			)
+		})
+		.collect()
+}
+
+/// Gets the Jira tickets of the commits a merge references, following the
+/// references of any merges it brings in as well.
+///
+/// The merge's own tickets are left out, and the rest are sorted.
+fn get_merged_jira_tickets_of<'a>(index: &Index<'a>, merge_commit: &'a Commit) -> Vec<&'a str> {
+	let mut merged_jira_tickets = BTreeSet::new();
+	let mut visited_commits = HashSet::from([merge_commit.git_revision.as_str()]);
+	let mut commits_to_visit = index.get_commit_forward_references(merge_commit);
+	while let Some(commit) = commits_to_visit.pop() {
+		if !visited_commits.insert(commit.git_revision.as_str()) {
+			continue;
+		}
+		merged_jira_tickets.extend(commit.jira_tickets.iter().map(String::as_str));
+		if commit.is_likely_a_merge {
+			commits_to_visit.extend(index.get_commit_forward_references(commit));
+		}
+	}
+	for jira_ticket in &merge_commit.jira_tickets {
+		merged_jira_tickets.remove(jira_ticket.as_str());
+	}
+
+	merged_jira_tickets.into_iter().collect()
+}
+
+/// Group a set of included commits by Jira ticket.
+///
+/// Merges are also grouped under the tickets in `merged_jira_tickets` for
+/// them, if there are any. Commits without a Jira ticket are grouped under
+/// `None`, unless a synthetic ticket is provided for them.
+fn group_by_jira_tickets<'a>(
+	included_commits: &'a [IncludedCommit<'a>],
+	no_ticket_as: Option<&'a str>,
+	merged_jira_tickets: &HashMap<&str, Vec<&'a str>>,
+) -> HashMap<Option<&'a str>, Vec<IncludedCommit<'a>>> {
+	let mut jira_ticket_groups = HashMap::new();
+
+	for included_commit in included_commits {
+		let jira_tickets = included_commit
+			.commit
+			.jira_tickets
+			.iter()
+			.map(String::as_str)
+			.chain(
+				merged_jira_tickets
+					.get(included_commit.commit.git_revision.as_str())
+					.into_iter()
+					.flatten()
+					.copied(),
+			)
+			.collect::<Vec<_>>();
+
+		// The `clone` calls here are a little ugly, but the `IncludedCommit` struct
+		// basically just holds references anyway, so cloning it is cheap
+		if jira_tickets.is_empty() {
+			jira_ticket_groups
+				.entry(no_ticket_as)
+				.and_modify(|ticket_commits: &mut Vec<IncludedCommit>| {
+					ticket_commits.push(included_commit.clone());
+				})
+				.or_insert_with(|| vec![included_commit.clone()]);
+		} else {
+			for jira_ticket in jira_tickets {
+				jira_ticket_groups
+					.entry(Some(jira_ticket))
+					.and_modify(|ticket_commits: &mut Vec<IncludedCommit>| {
+						ticket_commits.push(included_commit.clone());
+					})
+					.or_insert_with(|| vec![included_commit.clone()]);
+			}
+		}
+	}
+
+	jira_ticket_groups
+}
+
+/// Finds the Jira tickets in a set of groups that look like variants of the
+/// same ticket, like `MY_PROJ-12` and `MYPROJ-12`.
+///
+/// Each list of variants is sorted, and the lists are sorted by their first
+/// entry.
+fn find_jira_ticket_variants<'a>(
+	jira_ticket_groups: &HashMap<Option<&'a str>, Vec<IncludedCommit<'a>>>,
+) -> Vec<Vec<&'a str>> {
+	let mut jira_tickets_per_key: HashMap<(String, u64), Vec<&str>> = HashMap::new();
+	for jira_ticket in jira_ticket_groups.keys().flatten() {
+		if let Some(variant_key) = jira_ticket_variant_key(jira_ticket) {
+			jira_tickets_per_key
+				.entry(variant_key)
+				.or_default()
+				.push(jira_ticket);
+		}
+	}
+
+	let mut jira_ticket_variants = jira_tickets_per_key
+		.into_values()
+		.filter(|jira_tickets| jira_tickets.len() > 1)
+		.map(|mut jira_tickets| {
+			jira_tickets.sort_unstable();
+			jira_tickets
+		})
+		.collect::<Vec<_>>();
+	jira_ticket_variants.sort_unstable();
+
+	jira_ticket_variants
+}
+
+/// Folds the variants of a Jira ticket into the one with the most commits,
+/// returning the one they were folded into.
+///
+/// Commits that belong to more than one of the variants are only kept once,
+/// and the merged group is put back in log order.
+fn merge_jira_ticket_variants<'a>(
+	index: &Index<'a>,
+	jira_ticket_groups: &mut HashMap<Option<&'a str>, Vec<IncludedCommit<'a>>>,
+	jira_ticket_variants: &[&'a str],
+) -> &'a str {
+	// Ties go to the variant that sorts first, so the result is stable
+	let canonical_jira_ticket = *jira_ticket_variants
+		.iter()
+		.min_by_key(|jira_ticket| {
+			(
+				Reverse(jira_ticket_groups[&Some(**jira_ticket)].len()),
+				**jira_ticket,
+			)
+		})
+		.expect("there are always multiple variants");
+
+	let mut merged_commits = jira_ticket_variants
+		.iter()
+		.filter_map(|jira_ticket| jira_ticket_groups.remove(&Some(*jira_ticket)))
+		.flatten()
+		.collect::<Vec<_>>();
+	merged_commits.sort_by_key(|included_commit| index.log_position(included_commit.commit));
+	merged_commits.dedup_by(|included_commit, other| included_commit.commit == other.commit);
+	jira_ticket_groups.insert(Some(canonical_jira_ticket), merged_commits);
+
+	canonical_jira_ticket
+}
+
+/// Whether a commit's author matches any of the excluded author patterns.
+fn is_by_excluded_author(commit: &Commit, excluded_author_patterns: &[String]) -> bool {
+	excluded_author_patterns
+		.iter()
+		.any(|pattern| commit.author.matches_pattern(pattern.as_str()))
+}
+
+/// Removes the commits by excluded authors from search results, returning how
+/// many were removed.
+fn remove_excluded_author_commits(
+	included_commits: &mut Vec<IncludedCommit>,
+	excluded_author_patterns: &[String],
+) -> usize {
+	let included_commit_count = included_commits.len();
+	included_commits.retain(|included_commit| {
+		!is_by_excluded_author(included_commit.commit, excluded_author_patterns)
+	});
+	included_commit_count - included_commits.len()
+}
+
+/// Removes the excluded Jira tickets from a set of groups.
+///
+/// Commits that also belong to other tickets are left alone in those groups.
+fn remove_excluded_jira_tickets(
+	jira_ticket_groups: &mut HashMap<Option<&str>, Vec<IncludedCommit>>,
+	excluded_jira_tickets: &HashSet<String>,
+) {
+	if excluded_jira_tickets.is_empty() {
+		return;
+	}
+
+	jira_ticket_groups.retain(|jira_ticket, _| {
+		jira_ticket.map_or(true, |jira_ticket| {
+			!excluded_jira_tickets.contains(jira_ticket)
+		})
+	});
+}
+
+/// Reports (to stderr) how many Jira tickets and commit attributions only come
+/// from tickets mentioned in commit bodies, and which tickets gain the most
+/// commits that way.
+///
+/// This works the same whether or not the mentions are actually included, so
+/// the impact can be checked before turning them on.
+fn report_mention_impact(
+	included_commits: &[IncludedCommit],
+	include_mentioned_jira_tickets: bool,
+	excluded_jira_tickets: &HashSet<String>,
+) {
+	let mut subject_jira_tickets = HashSet::new();
+	let mut mention_only_commits_per_ticket: HashMap<&str, usize> = HashMap::new();
+	let mut attribution_total = 0;
+	let mut mention_only_attribution_total = 0;
+	for included_commit in included_commits {
+		let commit = included_commit.commit;
+		// When mentions are included, the ticket list has both kinds mixed together
+		for jira_ticket in commit
+			.jira_tickets
+			.iter()
+			.filter(|jira_ticket| {
+				!include_mentioned_jira_tickets
+					|| !commit.mentioned_jira_tickets.contains(jira_ticket)
+			})
+			.filter(|jira_ticket| !excluded_jira_tickets.contains(*jira_ticket))
+		{
+			subject_jira_tickets.insert(jira_ticket.as_str());
+			attribution_total += 1;
+		}
+		for jira_ticket in commit
+			.mentioned_jira_tickets
+			.iter()
+			.filter(|jira_ticket| !excluded_jira_tickets.contains(*jira_ticket))
+		{
+			*mention_only_commits_per_ticket
+				.entry(jira_ticket.as_str())
+				.or_default() += 1;
+			attribution_total += 1;
+			mention_only_attribution_total += 1;
+		}
+	}
+	let jira_ticket_total = subject_jira_tickets
+		.union(&mention_only_commits_per_ticket.keys().copied().collect())
+		.count();
+	let mention_only_jira_ticket_total = mention_only_commits_per_ticket
+		.keys()
+		.filter(|jira_ticket| !subject_jira_tickets.contains(*jira_ticket))
+		.count();
+
+	// Ties go to the ticket that sorts first, so the report is stable
+	let mut top_jira_tickets = mention_only_commits_per_ticket
+		.into_iter()
+		.collect::<Vec<_>>();
+	top_jira_tickets.sort_unstable_by_key(|(jira_ticket, commit_count)| {
+		(Reverse(*commit_count), sortable_jira_ticket(jira_ticket))
+	});
+	top_jira_tickets.truncate(MENTION_IMPACT_TOP_TICKET_COUNT);
+
+	eprintln!("Mention impact:");
+	eprintln!(
+		"- Commit attributions only from mentions: {mention_only_attribution_total} of \
+		 {attribution_total}"
+	);
+	eprintln!(
+		"- Jira tickets only from mentions: {mention_only_jira_ticket_total} of \
+		 {jira_ticket_total}"
+	);
+	if !top_jira_tickets.is_empty() {
+		eprintln!(
+			"- Most commits from mentions: {}",
+			top_jira_tickets
+				.iter()
+				.map(|(jira_ticket, commit_count)| format!("`{jira_ticket}` ({commit_count})"))
+				.collect::<Vec<_>>()
+				.join(", ")
+		);
+	}
+	if !include_mentioned_jira_tickets && mention_only_attribution_total > 0 {
+		eprintln!(
+			"[WARNING] `--include-mentioned` would add {mention_only_attribution_total} commit \
+			 attributions to the results"
+		);
+	}
+}
+
+/// Whether all of a commit's Jira tickets are excluded, meaning it won't be
+/// listed anywhere.
+fn is_fully_excluded(commit: &Commit, excluded_jira_tickets: &HashSet<String>) -> bool {
+	!commit.jira_tickets.is_empty()
+		&& commit
+			.jira_tickets
+			.iter()
+			.all(|jira_ticket| excluded_jira_tickets.contains(jira_ticket))
+}
+
+/// Displays the simple list of Jira tickets, optionally with commit
+/// information.
+///
+/// If the primary directories of the commits are provided, each ticket's
+/// commits are grouped by them.
+#[allow(clippy::ref_option_ref)]
+fn display_jira_ticket_commit_list(
+	multi_writer: &mut MultiWriter,
+	jira_tickets: &[(&Option<&str>, &Vec<IncludedCommit>)],
+	show_commits: bool,
+	commit_limit: Option<usize>,
+	primary_directories: Option<&HashMap<String, String>>,
+	commit_display_options: &CommitDisplayOptions,
+	ticket_display: &TicketDisplay,
+) -> Result<()> {
+	for (jira_ticket_option, commits) in jira_tickets {
+		let jira_ticket = ticket_display.render(**jira_ticket_option, RenderTarget::Markdown);
+		if show_commits {
+			writeln!(multi_writer, "- {jira_ticket}:")?;
+			// Only the top-level commits count towards the limit, so each one that's
+			// shown still has its full tree
+			let displayed_commit_count = commit_limit.map_or(commits.len(), |commit_limit| {
+				commit_limit.min(commits.len())
+			});
+			if let Some(primary_directories) = primary_directories {
+				display_commits_by_directory(
+					multi_writer,
+					&commits[..displayed_commit_count],
+					primary_directories,
+					commit_display_options,
+				)?;
+			} else {
+				display_commit_reference_tree(
+					multi_writer,
+					&commits[..displayed_commit_count],
+					1,
+					commit_display_options,
+				)?;
+			}
+			if displayed_commit_count < commits.len() {
+				writeln!(
+					multi_writer,
+					"{}- ...and {} more",
+					commit_display_options.indentation,
+					commits.len() - displayed_commit_count
+				)?;
+			}
+		} else {
+			writeln!(multi_writer, "- {jira_ticket} ({})", commits.len())?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Displays a ticket's commits grouped by the top-level directory each one
+/// touched the most, in alphabetical order. The commits that didn't change any
+/// files come last.
+fn display_commits_by_directory(
+	multi_writer: &mut MultiWriter,
+	included_commits: &[IncludedCommit],
+	primary_directories: &HashMap<String, String>,
+	commit_display_options: &CommitDisplayOptions,
+) -> Result<()> {
+	let mut commits_per_directory: HashMap<Option<&str>, Vec<IncludedCommit>> = HashMap::new();
+	for included_commit in included_commits {
+		commits_per_directory
+			.entry(
+				primary_directories
+					.get(included_commit.commit.git_revision.as_str())
+					.map(String::as_str),
+			)
+			.or_default()
+			.push(included_commit.clone());
+	}
+
+	// `None` would sort first on its own, so it's moved to the end
+	let mut directory_groups = commits_per_directory.into_iter().collect::<Vec<_>>();
+	directory_groups.sort_by_key(|(directory, _)| (directory.is_none(), *directory));
+	for (directory, commits) in directory_groups {
+		write!(multi_writer, "{}- ", commit_display_options.indentation)?;
+		match directory {
+			Some(directory) => writeln!(
+				multi_writer,
+				"{}:",
+				Fragment::Code(directory).render(RenderTarget::Markdown)
+			)?,
+			None => writeln!(multi_writer, "No changed files:")?,
+		}
+		display_commit_reference_tree(multi_writer, commits.as_slice(), 2, commit_display_options)?;
+	}
+
+	Ok(())
+}
+
+/// Removes pairs of commits (one from each object) that look like cherry-picks
+/// of each other based on their dates alone, returning the number of pairs
+/// removed.
+///
+/// Cherry-picks keep the original author and author date, so two commits that
+/// share those are the same change. To avoid matching unrelated commits that
+/// happen to share them (like ones from the same rebase), at least one of the
+/// two has to be committed more than `window_seconds` after it was authored.
+fn remove_probable_backports(
+	search_results_only_on_object_a: &mut Vec<IncludedCommit>,
+	search_results_only_on_object_b: &mut Vec<IncludedCommit>,
+	window_seconds: i64,
+) -> usize {
+	// Index the commits on object B by their authorship, leaving out merges
+	// since they're never cherry-picked
+	let mut commits_on_object_b_by_author: HashMap<(&str, &str, i64), Vec<&Commit>> =
+		HashMap::new();
+	for included_commit in search_results_only_on_object_b.iter() {
+		let commit = included_commit.commit;
+		if commit.parent_revisions.len() > 1 {
+			continue;
+		}
+		commits_on_object_b_by_author
+			.entry((
+				commit.author.name.as_str(),
+				commit.author.email.as_str(),
+				commit.author.timestamp,
+			))
+			.or_default()
+			.push(commit);
+	}
+
+	// Pair them up with the commits on object A
+	let mut object_a_removal_set = HashSet::new();
+	let mut object_b_removal_set = HashSet::new();
+	for included_commit in search_results_only_on_object_a.iter() {
+		let commit = included_commit.commit;
+		if commit.parent_revisions.len() > 1 {
+			continue;
+		}
+		let Some(candidate_commits) = commits_on_object_b_by_author.get(&(
+			commit.author.name.as_str(),
+			commit.author.email.as_str(),
+			commit.author.timestamp,
+		)) else {
+			continue;
+		};
+		if let Some(matching_commit) = candidate_commits.iter().find(|candidate_commit| {
+			!object_b_removal_set.contains(candidate_commit.git_revision.as_str())
+				&& (commit.is_probably_backported(window_seconds)
+					|| candidate_commit.is_probably_backported(window_seconds))
+		}) {
+			object_a_removal_set.insert(commit.git_revision.clone());
+			object_b_removal_set.insert(matching_commit.git_revision.as_str());
+		}
+	}
+
+	search_results_only_on_object_a.retain(|included_commit| {
+		!object_a_removal_set.contains(&included_commit.commit.git_revision)
+	});
+	search_results_only_on_object_b.retain(|included_commit| {
+		!object_b_removal_set.contains(included_commit.commit.git_revision.as_str())
+	});
+
+	object_a_removal_set.len()
+}
+
+/// Displays which of the expected commits are on an object, either directly or
+/// through a commit that cherry-picks or merges them, grouped by Jira ticket.
+///
+/// Returns the number of expected commits that are absent or couldn't be
+/// resolved.
+fn display_expected_commits_audit(
+	multi_writer: &mut MultiWriter,
+	repo_dir: &str,
+	index: &Index,
+	expected_revisions: &[String],
+	object: &str,
+	commit_display_options: &CommitDisplayOptions,
+	ticket_display: &TicketDisplay,
+) -> Result<usize> {
+	let mut present_commits = Vec::new();
+	let mut linked_commits = Vec::new();
+	let mut absent_commits = Vec::new();
+	let mut unresolved_revisions = Vec::new();
+	for expected_revision in expected_revisions {
+		let expected_commit = match expected_revision
+			.parse::<CommitId>()
+			.and_then(|commit_id| index.lookup_git_revision(&commit_id))
+		{
+			Ok(expected_commit) => expected_commit,
+			Err(error) => {
+				unresolved_revisions.push((expected_revision, error));
+				continue;
+			}
+		};
+
+		if is_ancestor(repo_dir, expected_commit.git_revision.as_str(), object)? {
+			present_commits.push(expected_commit);
+			continue;
+		}
+
+		// Look for a commit on the object that references the expected one
+		let referencing_commits = flatten_inclusion_tree(
+			build_commit_inclusion_tree(index, &[expected_commit], LinkTraversal::Backward, false)?
+				.as_slice(),
+		);
+		let mut linked_commit = None;
+		for referencing_commit in referencing_commits.into_iter().skip(1) {
+			if is_ancestor(
+				repo_dir,
+				referencing_commit.commit.git_revision.as_str(),
+				object,
+			)? {
+				linked_commit = Some(referencing_commit.commit);
+				break;
+			}
+		}
+		match linked_commit {
+			Some(linked_commit) => linked_commits.push((expected_commit, Some(linked_commit))),
+			None => absent_commits.push(expected_commit),
+		}
+	}
+
+	let failed_commit_count = absent_commits.len() + unresolved_revisions.len();
+
+	let indentation = commit_display_options.indentation;
+	writeln!(
+		multi_writer,
+		"Expected commits on `{object}`: ({} total)",
+		expected_revisions.len()
+	)?;
+	let present_commits = present_commits
+		.into_iter()
+		.map(|commit| (commit, None))
+		.collect::<Vec<_>>();
+	let absent_commits = absent_commits
+		.into_iter()
+		.map(|commit| (commit, None))
+		.collect::<Vec<_>>();
+	for (heading, commits) in [
+		("Present", present_commits),
+		("Present through a cherry-pick or merge", linked_commits),
+		("Absent", absent_commits),
+	] {
+		writeln!(multi_writer, "- {heading}: ({})", commits.len())?;
+
+		// Group the commits by their first Jira ticket
+		let mut commits_per_jira_ticket: HashMap<Option<&str>, Vec<_>> = HashMap::new();
+		for (commit, linked_commit) in commits {
+			commits_per_jira_ticket
+				.entry(commit.jira_tickets.first().map(String::as_str))
+				.or_default()
+				.push((commit, linked_commit));
+		}
+		let mut commits_per_jira_ticket = commits_per_jira_ticket.into_iter().collect::<Vec<_>>();
+		commits_per_jira_ticket
+			.sort_unstable_by_key(|(jira_ticket, _)| jira_ticket.map(sortable_jira_ticket));
+
+		for (jira_ticket, commits) in commits_per_jira_ticket {
+			writeln!(
+				multi_writer,
+				"{indentation}- {}:",
+				ticket_display.render(jira_ticket, RenderTarget::Markdown)
+			)?;
+			for (commit, linked_commit) in commits {
+				write!(
+					multi_writer,
+					"{indentation}{indentation}- {}",
+					format_commit(commit, commit_display_options)?
+				)?;
+				if let Some(linked_commit) = linked_commit {
+					write!(
+						multi_writer,
+						" (through {})",
+						format_commit(linked_commit, commit_display_options)?
+					)?;
+				}
+				writeln!(multi_writer)?;
+			}
+		}
+	}
+	if !unresolved_revisions.is_empty() {
+		writeln!(
+			multi_writer,
+			"- Unknown or ambiguous: ({})",
+			unresolved_revisions.len()
+		)?;
+		for (revision, error) in &unresolved_revisions {
+			writeln!(
+				multi_writer,
+				"{indentation}- {}: {error}",
+				Fragment::Code(revision).render(RenderTarget::Markdown)
+			)?;
+		}
+	}
+
+	Ok(failed_commit_count)
+}
+
+/// Displays the list of Jira tickets as JSON Lines, with one line per ticket
+/// followed by a summary line.
+///
+/// Each line is flushed as soon as it's written, so that consumers can process
+/// the results as they come in. The summary has the total number of tickets in
+/// the results, along with the counts for just the tickets displayed, which
+/// differ when the results are paged.
+#[allow(clippy::ref_option_ref)]
+fn display_jira_ticket_json_lines(
+	multi_writer: &mut MultiWriter,
+	jira_tickets: &[(&Option<&str>, &Vec<IncludedCommit>)],
+	jira_ticket_total: usize,
+	ticket_display: &TicketDisplay,
+) -> Result<()> {
+	let mut displayed_commit_count = 0;
+	for (jira_ticket_option, commits) in jira_tickets {
+		writeln!(
+			multi_writer,
+			"{{\"type\":\"ticket\",\"key\":{},\"display\":{},\"url\":{},\"commit_count\":{},\"\
+			 commits\":[{}]}}",
+			jira_ticket_option.map_or_else(|| "null".to_owned(), json_string),
+			jira_ticket_option.map_or_else(
+				|| "null".to_owned(),
+				|jira_ticket| json_string(ticket_display.display(Some(jira_ticket)).as_str())
+			),
+			jira_ticket_option
+				.and_then(|jira_ticket| ticket_display.url(jira_ticket))
+				.map_or_else(|| "null".to_owned(), |url| json_string(url.as_str())),
+			commits.len(),
+			commits
+				.iter()
+				.map(format_commit_json)
+				.collect::<Vec<_>>()
+				.join(","),
+		)?;
+		multi_writer.flush()?;
+		displayed_commit_count += commits.len();
+	}
+
+	// The commits without a ticket aren't a ticket themselves
+	let displayed_jira_ticket_count = jira_tickets
+		.iter()
+		.filter(|(jira_ticket_option, _)| jira_ticket_option.is_some())
+		.count();
+	writeln!(
+		multi_writer,
+		"{{\"type\":\"summary\",\"jira_ticket_total\":{jira_ticket_total},\"\
+		 displayed_jira_ticket_count\":{displayed_jira_ticket_count},\"displayed_commit_count\":\
+		 {displayed_commit_count}}}",
+	)?;
+	multi_writer.flush()?;
+
+	Ok(())
+}
+
+/// Displays the list of Jira tickets as tab-separated values, with either the
+/// commit count for each ticket or every commit's hash and subject.
+///
+/// Only the commits directly in the results are listed, not the ones linked to
+/// them.
+#[allow(clippy::ref_option_ref)]
+fn display_jira_ticket_plain(
+	multi_writer: &mut MultiWriter,
+	jira_tickets: &[(&Option<&str>, &Vec<IncludedCommit>)],
+	show_commits: bool,
+	hash_length: usize,
+	compact_form: bool,
+	ticket_display: &TicketDisplay,
+) -> Result<()> {
+	for (jira_ticket_option, commits) in jira_tickets {
+		if show_commits {
+			for included_commit in *commits {
+				let commit = included_commit.commit;
+				let subject = sanitize_for_display(commit.subject.as_str()).replace('\t', " ");
+				writeln!(
+					multi_writer,
+					"{}\t{}",
+					abbreviate(commit.git_revision.as_str(), hash_length)?,
+					if compact_form {
+						truncate_with_ellipsis(subject.as_str(), COMPACT_SUBJECT_LENGTH)
+					} else {
+						Cow::Borrowed(subject.as_str())
+					}
+				)?;
+			}
+		} else {
+			let jira_ticket = ticket_display.render(**jira_ticket_option, RenderTarget::Plain);
+			writeln!(multi_writer, "{jira_ticket}\t{}", commits.len())?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Formats a commit and the commits linked to it as a JSON object.
+fn format_commit_json(included_commit: &IncludedCommit) -> String {
+	let commit = included_commit.commit;
+	format!(
+		"{{\"hash\":{},\"subject\":{},\"is_merge\":{},\"svn_revision\":{},\"svn_url\":{},\"\
+		 duplicates\":[{}],\"linked_commits\":[{}]}}",
+		json_string(commit.git_revision.as_str()),
+		json_string(commit.subject.as_str()),
+		commit.is_likely_a_merge,
+		commit.svn_info.as_ref().map_or_else(
+			|| "null".to_owned(),
+			|svn_info| svn_info.svn_revision.to_string()
+		),
+		commit.svn_info.as_ref().map_or_else(
+			|| "null".to_owned(),
+			|svn_info| json_string(svn_info.svn_url.as_str())
+		),
+		included_commit
+			.duplicate_commits
+			.iter()
+			.map(|duplicate_commit| json_string(duplicate_commit.git_revision.as_str()))
+			.collect::<Vec<_>>()
+			.join(","),
+		included_commit
+			.linked_commits
+			.iter()
+			.map(format_commit_json)
+			.collect::<Vec<_>>()
+			.join(","),
+	)
+}
+
+/// Displays the list of Jira tickets for the intersection between two objects'
+/// lists.
+///
+/// The counterpart of `display_jira_ticket_commit_list`.
+///
+/// The reason this is in its own function despite only being used once, is so
+/// that it can be updated in tandem with its counterpart.
+#[allow(clippy::ref_option_ref, clippy::type_complexity)]
+fn display_jira_ticket_commit_list_intersection(
 	multi_writer: &mut MultiWriter,
 	jira_ticket_intersection: &[(
 		&&Option<&str>,
@@ -782,34 +3511,31 @@ fn display_jira_ticket_commit_list_intersection(
 	object_a: &str,
 	object_b: &str,
 	show_commits: bool,
-	hash_length: usize,
-	ticket_prefix: &str,
+	commit_display_options: &CommitDisplayOptions,
+	ticket_display: &TicketDisplay,
 ) -> Result<()> {
 	for (jira_ticket_option, (commits_object_a, commits_object_b)) in jira_ticket_intersection {
-		let jira_ticket = if let Some(ticket) = jira_ticket_option {
-			format!("{ticket_prefix}{ticket}")
-		} else {
-			NO_JIRA_TICKET_STR.to_owned()
-		};
+		let jira_ticket = ticket_display.render(***jira_ticket_option, RenderTarget::Markdown);
 		let commits_object_a = commits_object_a
 			.expect("the Option types are just present for the population stage of the process");
 		let commits_object_b = commits_object_b
 			.expect("the Option types are just present for the population stage of the process");
 		if show_commits {
+			let indentation = commit_display_options.indentation;
 			writeln!(multi_writer, "- {jira_ticket}:")?;
-			writeln!(multi_writer, "\t- On `{object_a}`:")?;
+			writeln!(multi_writer, "{indentation}- On `{object_a}`:")?;
 			display_commit_reference_tree(
 				multi_writer,
 				commits_object_a.as_slice(),
 				2,
-				hash_length,
+				commit_display_options,
 			)?;
-			writeln!(multi_writer, "\t- On `{object_b}`:")?;
+			writeln!(multi_writer, "{indentation}- On `{object_b}`:")?;
 			display_commit_reference_tree(
 				multi_writer,
 				commits_object_b.as_slice(),
 				2,
-				hash_length,
+				commit_display_options,
 			)?;
 		} else {
 			writeln!(
@@ -829,63 +3555,1272 @@ fn display_commit_reference_tree(
 	multi_writer: &mut MultiWriter,
 	included_commits: &[IncludedCommit],
 	indentation: u32,
-	hash_length: usize,
+	commit_display_options: &CommitDisplayOptions,
 ) -> Result<()> {
 	for included_commit in included_commits {
 		// Print the indentation
 		for _ in 0..indentation {
-			write!(multi_writer, "\t")?;
+			write!(multi_writer, "{}", commit_display_options.indentation)?;
 		}
 
-		// Print the commit revision
-		writeln!(
+		// Print the commit revision, along with any duplicates collapsed into it
+		write!(
 			multi_writer,
-			"- `{}`{}",
-			&included_commit.commit.git_revision[0..hash_length],
-			if included_commit.commit.is_likely_a_merge {
-				MERGE_COMMIT_MARKER_STR
-			} else {
-				""
-			}
+			"- {}",
+			format_commit(included_commit.commit, commit_display_options)?
 		)?;
+		if !included_commit.duplicate_commits.is_empty() {
+			write!(
+				multi_writer,
+				" (\u{d7}{}: {})",
+				included_commit.duplicate_commits.len() + 1,
+				iter::once(included_commit.commit)
+					.chain(included_commit.duplicate_commits.iter().copied())
+					.map(|commit| abbreviate(
+						commit.git_revision.as_str(),
+						commit_display_options.hash_length
+					)
+					.map(|abbreviated_revision| format!("`{abbreviated_revision}`")))
+					.collect::<Result<Vec<_>>>()?
+					.join(", ")
+			)?;
+		}
+		writeln!(multi_writer)?;
 
 		// Recurse over the referenced commits
 		display_commit_reference_tree(
 			multi_writer,
 			included_commit.linked_commits.as_slice(),
 			indentation + 1,
-			hash_length,
+			commit_display_options,
 		)?;
 	}
 
 	Ok(())
 }
 
-/// Displays a set of commits.
+/// Displays a set of commits, noting which of the commits being searched each
+/// one merges if the provenance is provided.
 fn display_commit_set(
 	multi_writer: &mut MultiWriter,
 	commits: &[&Commit],
 	indentation: u32,
-	hash_length: usize,
+	provenance: Option<&HashMap<&str, FlattenedCommit>>,
+	commit_display_options: &CommitDisplayOptions,
 ) -> Result<()> {
 	for commit in commits {
 		// Print the indentation
 		for _ in 0..indentation {
-			write!(multi_writer, "\t")?;
+			write!(multi_writer, "{}", commit_display_options.indentation)?;
 		}
 
 		// Print the commit revision
-		writeln!(
+		write!(
 			multi_writer,
-			"- `{}`{}",
-			&commit.git_revision[0..hash_length],
-			if commit.is_likely_a_merge {
-				MERGE_COMMIT_MARKER_STR
-			} else {
-				""
-			}
+			"- {}",
+			format_commit(commit, commit_display_options)?
 		)?;
+
+		// Print where it came from, if it isn't one of the commits being searched
+		if let Some(flattened_commit) = provenance
+			.and_then(|provenance| provenance.get(commit.git_revision.as_str()))
+			.filter(|flattened_commit| flattened_commit.depth > 0)
+		{
+			write!(
+				multi_writer,
+				" (merges {}, {} link(s) away)",
+				Fragment::Code(abbreviate(
+					flattened_commit.seed.git_revision.as_str(),
+					commit_display_options.hash_length
+				)?)
+				.render(RenderTarget::Markdown),
+				flattened_commit.depth
+			)?;
+		}
+		writeln!(multi_writer)?;
 	}
 
 	Ok(())
 }
+
+/// Whether to use the compact form of the output, which is when it's requested,
+/// or when the output is going straight to a narrow terminal.
+///
+/// Output that's paged, captured for the clipboard, or not going to a terminal
+/// at all gets the full form unless requested otherwise. A custom indentation
+/// replaces the compact form entirely.
+fn use_compact_form(compact: bool, indent: Option<&str>, output_is_interactive: bool) -> bool {
+	if indent.is_some() {
+		return false;
+	}
+
+	compact
+		|| (output_is_interactive
+			&& terminal_size().map_or(false, |(Width(width), _)| {
+				width < COMPACT_TERMINAL_WIDTH_THRESHOLD
+			}))
+}
+
+/// Gets the string to indent nested output with, which is the one provided if
+/// there is one, or the one for the compact or full form otherwise.
+fn get_indentation_str(compact_form: bool, indent: Option<&str>) -> &str {
+	match indent {
+		Some(indent) => indent,
+		None if compact_form => COMPACT_INDENTATION_STR,
+		None => INDENTATION_STR,
+	}
+}
+
+/// Formats a single commit for display, without any indentation or bullet.
+fn format_commit(commit: &Commit, commit_display_options: &CommitDisplayOptions) -> Result<String> {
+	let mut fragments = vec![Fragment::Code(abbreviate(
+		commit.git_revision.as_str(),
+		commit_display_options.hash_length,
+	)?)];
+	let svn_revision_str;
+	let svn_path_str;
+	if commit_display_options.show_svn_revision {
+		if let Some(svn_info) = &commit.svn_info {
+			svn_revision_str = format!(" (r{})", svn_info.svn_revision);
+			fragments.push(Fragment::Text(svn_revision_str.as_str()));
+		}
+	}
+	if commit_display_options.show_svn_path {
+		if let Some(svn_info) = &commit.svn_info {
+			svn_path_str = format!(
+				" [{}]",
+				svn_info.location(commit_display_options.svn_layout)
+			);
+			fragments.push(Fragment::Text(svn_path_str.as_str()));
+		}
+	}
+	if commit.is_likely_a_merge {
+		fragments.push(Fragment::Text(commit_display_options.merge_marker));
+	}
+
+	Ok(render_fragments(
+		fragments.as_slice(),
+		RenderTarget::Markdown,
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		collection::SvnInfo,
+		constants::{APPLICATION_BIN_NAME, MERGE_COMMIT_MARKER_STR},
+		index::ALL_LINK_KINDS,
+		test_util::{commit, full_hash, included, FixtureRepo},
+	};
+
+	/// The tickets each commit carries, keyed by Git revision.
+	type CarriedJiraTickets<'a> = &'a [(&'a str, &'a [&'a str])];
+
+	/// Runs clog with the arguments (not including the binary name), returning
+	/// everything it wrote to stdout.
+	fn run_clog(args: &[&str]) -> Result<String> {
+		let subcommand_matches = build_cli()
+			.try_get_matches_from([APPLICATION_BIN_NAME].iter().chain(args))
+			.expect("the arguments are valid");
+		let mut output = Vec::new();
+		run(
+			&subcommand_matches,
+			Some(&mut output),
+			&mut RunDetails::default(),
+		)?;
+
+		Ok(String::from_utf8(output).expect("the output is valid UTF-8"))
+	}
+
+	/// Builds a repo with two commits on `main`, cloned so that `main` has an
+	/// upstream, then detaches `HEAD` at the first commit.
+	///
+	/// Returns both repos (since the clone needs its origin), along with the
+	/// first commit's hash.
+	fn detached_fixture_repo(name: &str) -> (FixtureRepo, FixtureRepo, String) {
+		let origin_repo = FixtureRepo::new(format!("{name}-origin").as_str());
+		let first_revision = origin_repo.commit("PROJ-1: Initial commit");
+		origin_repo.commit("PROJ-2: Add a feature");
+		let fixture_repo = FixtureRepo::clone_of(&origin_repo, name);
+		fixture_repo.git(&["checkout", "--quiet", "--detach", first_revision.as_str()]);
+
+		(origin_repo, fixture_repo, first_revision)
+	}
+
+	fn commit_with_jira_tickets(git_revision: &str, jira_tickets: &[&str]) -> Commit {
+		let mut commit = commit(git_revision, &[]);
+		commit.jira_tickets = jira_tickets
+			.iter()
+			.map(|jira_ticket| (*jira_ticket).to_owned())
+			.collect();
+		commit
+	}
+
+	#[test]
+	fn carried_jira_tickets_include_the_tree_above() {
+		// `a` links to `b`, which links to `c`, and `d` links to `c` too
+		let a = commit_with_jira_tickets("a", &["PROJ-1"]);
+		let b = commit_with_jira_tickets("b", &["PROJ-2", "PROJ-9"]);
+		let c = commit_with_jira_tickets("c", &[]);
+		let d = commit_with_jira_tickets("d", &["PROJ-3"]);
+		let inclusion_tree = vec![
+			included(&a, vec![included(&b, vec![included(&c, Vec::new())])]),
+			included(&d, vec![included(&c, Vec::new())]),
+		];
+
+		// The searched tickets and what each commit should carry
+		let cases: [(&[&str], CarriedJiraTickets); 3] = [
+			(
+				&["PROJ-1", "PROJ-2", "PROJ-3"],
+				&[
+					("a", &["PROJ-1"]),
+					("b", &["PROJ-1", "PROJ-2"]),
+					("c", &["PROJ-1", "PROJ-2", "PROJ-3"]),
+					("d", &["PROJ-3"]),
+				],
+			),
+			(
+				&["PROJ-2"],
+				&[
+					("a", &[]),
+					("b", &["PROJ-2"]),
+					("c", &["PROJ-2"]),
+					("d", &[]),
+				],
+			),
+			(
+				&["PROJ-4"],
+				&[("a", &[]), ("b", &[]), ("c", &[]), ("d", &[])],
+			),
+		];
+
+		for (searched_jira_tickets, expected) in cases {
+			let searched_jira_tickets = searched_jira_tickets
+				.iter()
+				.map(|jira_ticket| (*jira_ticket).to_owned())
+				.collect::<Vec<_>>();
+			let mut carried_jira_tickets = HashMap::new();
+			collect_carried_jira_tickets(
+				inclusion_tree.as_slice(),
+				&HashSet::new(),
+				searched_jira_tickets.iter().collect::<Vec<_>>().as_slice(),
+				&mut carried_jira_tickets,
+			);
+
+			let carried_jira_tickets = carried_jira_tickets
+				.into_iter()
+				.map(|(git_revision, jira_tickets)| {
+					(
+						git_revision,
+						jira_tickets.into_iter().collect::<BTreeSet<_>>(),
+					)
+				})
+				.collect::<HashMap<_, _>>();
+			let expected = expected
+				.iter()
+				.map(|(git_revision, jira_tickets)| {
+					(
+						*git_revision,
+						jira_tickets.iter().copied().collect::<BTreeSet<_>>(),
+					)
+				})
+				.collect::<HashMap<_, _>>();
+			assert_eq!(carried_jira_tickets, expected, "{searched_jira_tickets:?}");
+		}
+	}
+
+	#[test]
+	fn merged_jira_ticket_variants_stay_in_log_order() {
+		let commits = ["a1", "b2", "c3", "d4"]
+			.iter()
+			.map(|prefix| commit(full_hash(prefix).as_str(), &[]))
+			.collect::<Vec<_>>();
+		let index = Index::new(commits.as_slice(), &[], ALL_LINK_KINDS).expect("the index builds");
+		// The groups are given out of log order, and share a commit
+		let mut jira_ticket_groups = HashMap::from([
+			(
+				Some("proj-1"),
+				vec![
+					included(&commits[3], Vec::new()),
+					included(&commits[1], Vec::new()),
+				],
+			),
+			(
+				Some("PROJ-1"),
+				vec![
+					included(&commits[2], Vec::new()),
+					included(&commits[1], Vec::new()),
+				],
+			),
+			(Some("PROJ-2"), vec![included(&commits[0], Vec::new())]),
+		]);
+
+		let canonical_jira_ticket =
+			merge_jira_ticket_variants(&index, &mut jira_ticket_groups, &["PROJ-1", "proj-1"]);
+
+		// It's a tie, so the variant that sorts first wins
+		assert_eq!(canonical_jira_ticket, "PROJ-1");
+		assert_eq!(jira_ticket_groups.len(), 2);
+		assert_eq!(
+			jira_ticket_groups[&Some("PROJ-1")]
+				.iter()
+				.map(|included_commit| included_commit.commit)
+				.collect::<Vec<_>>(),
+			[&commits[1], &commits[2], &commits[3]]
+		);
+	}
+
+	#[test]
+	fn json_lines_summary_leaves_out_the_commits_without_a_ticket() {
+		let commits = ["a1", "b2", "c3"]
+			.iter()
+			.map(|prefix| commit(full_hash(prefix).as_str(), &[]))
+			.collect::<Vec<_>>();
+		let no_jira_ticket_group = vec![included(&commits[0], Vec::new())];
+		let jira_ticket_group = vec![
+			included(&commits[1], Vec::new()),
+			included(&commits[2], Vec::new()),
+		];
+		let jira_tickets = [
+			(&None, &no_jira_ticket_group),
+			(&Some("PROJ-1"), &jira_ticket_group),
+		];
+		let ticket_display = TicketDisplay {
+			prefix:        "",
+			strip_project: false,
+			url_template:  None,
+		};
+
+		let mut output = Vec::new();
+		let mut multi_writer = MultiWriter::new(vec![&mut output]);
+		// There are more tickets than are displayed, like on one page of results
+		display_jira_ticket_json_lines(&mut multi_writer, &jira_tickets, 4, &ticket_display)
+			.expect("writing to memory doesn't fail");
+		drop(multi_writer);
+
+		let output = String::from_utf8(output).expect("the output is UTF-8");
+		let lines = output.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 3);
+		assert!(lines
+			.iter()
+			.all(|line| line.starts_with("{\"type\":") && line.ends_with('}')));
+		assert_eq!(
+			lines[2],
+			"{\"type\":\"summary\",\"jira_ticket_total\":4,\"displayed_jira_ticket_count\":1,\"\
+			 displayed_commit_count\":3}"
+		);
+	}
+
+	#[test]
+	fn compact_form_is_only_automatic_for_interactive_output() {
+		// Whether it's requested, the custom indentation, and the expected form
+		let cases = [
+			(false, None, false, INDENTATION_STR),
+			(true, None, true, COMPACT_INDENTATION_STR),
+			(true, Some(">"), false, ">"),
+		];
+		for (compact, indent, expected_compact_form, expected_indentation) in cases {
+			// Non-interactive output never looks at the terminal size
+			let compact_form = use_compact_form(compact, indent, false);
+			assert_eq!(compact_form, expected_compact_form, "{compact} {indent:?}");
+			assert_eq!(
+				get_indentation_str(compact_form, indent),
+				expected_indentation,
+				"{compact} {indent:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn clipboard_sections_select_their_own_output() {
+		let mut captured_output = Vec::new();
+		let mut stdout_output = Vec::new();
+		let mut multi_writer = MultiWriter::new(vec![&mut stdout_output, &mut captured_output]);
+
+		// Written the same way `compare` writes its sections
+		writeln!(multi_writer, "Comparing `a` to `b`").unwrap();
+		let mut write_section = |text: &str| {
+			let start = multi_writer.bytes_written();
+			write!(multi_writer, "{text}").unwrap();
+			start..multi_writer.bytes_written()
+		};
+		let compare_sections = CompareSections {
+			only_on_object_a: write_section("\nJira tickets only on `a`: (1 total)\n- PROJ-1\n"),
+			only_on_object_b: write_section(
+				"\nJira tickets only on `b`: (1 total)\n- PROJ-\u{e9}\n",
+			),
+			on_both_objects:  write_section(""),
+		};
+		writeln!(multi_writer, "Expected commits on `b`: (0 total)").unwrap();
+		drop(multi_writer);
+
+		// The clipboard selection and what it should contain
+		let cases = [
+			("a", "\nJira tickets only on `a`: (1 total)\n- PROJ-1\n"),
+			(
+				"b",
+				"\nJira tickets only on `b`: (1 total)\n- PROJ-\u{e9}\n",
+			),
+			("both", ""),
+			(
+				"all",
+				"Comparing `a` to `b`\n\nJira tickets only on `a`: (1 total)\n- PROJ-1\n\nJira \
+				 tickets only on `b`: (1 total)\n- PROJ-\u{e9}\nExpected commits on `b`: (0 \
+				 total)\n",
+			),
+		];
+		for (clipboard_section, expected) in cases {
+			assert_eq!(
+				select_captured_output(
+					captured_output.as_slice(),
+					compare_sections.clipboard_range(clipboard_section)
+				),
+				expected,
+				"{clipboard_section}"
+			);
+		}
+		// Everything is always displayed, no matter what's copied
+		assert_eq!(stdout_output, captured_output);
+	}
+
+	#[test]
+	fn svn_revisions_and_locations_are_shown_separately() {
+		let mut migrated_commit = commit(full_hash("a1b2c3d4").as_str(), &[]);
+		migrated_commit.svn_info = Some(SvnInfo {
+			svn_url:         "https://svn.example.com/repo/branches/release%201.2".to_owned(),
+			svn_revision:    1234,
+			repository_uuid: "0a1b2c3d-0000-0000-0000-000000000000".to_owned(),
+		});
+		let svn_layout = SvnLayout::default();
+
+		// Whether the revision and the location are shown, and the expected output
+		let cases = [
+			(false, false, "`a1b2c3d4`"),
+			(true, false, "`a1b2c3d4` (r1234)"),
+			(false, true, "`a1b2c3d4` [branch release 1.2]"),
+			(true, true, "`a1b2c3d4` (r1234) [branch release 1.2]"),
+		];
+		for (show_svn_revision, show_svn_path, expected) in cases {
+			let commit_display_options = CommitDisplayOptions {
+				hash_length: 8,
+				show_svn_path,
+				show_svn_revision,
+				svn_layout: &svn_layout,
+				indentation: INDENTATION_STR,
+				merge_marker: MERGE_COMMIT_MARKER_STR,
+			};
+			assert_eq!(
+				format_commit(&migrated_commit, &commit_display_options).unwrap(),
+				expected,
+				"{show_svn_revision} {show_svn_path}"
+			);
+		}
+	}
+
+	#[test]
+	fn list_needs_an_explicit_revspec_from_a_detached_head() {
+		let (_origin_repo, fixture_repo, first_revision) = detached_fixture_repo("list-detached");
+
+		// The branch it was on has an upstream, but HEAD isn't on it anymore
+		let error = run_clog(&["list", "--repo", fixture_repo.path_str()])
+			.expect_err("there's no branch to default to");
+		assert_eq!(
+			format!("{error:#}"),
+			format!(
+				"no revspec was provided, and there's no current branch to default to: HEAD is \
+				 detached at {}; pass an explicit ref",
+				&first_revision[..7]
+			)
+		);
+
+		assert_eq!(
+			run_clog(&["list", "--repo", fixture_repo.path_str(), "HEAD"])
+				.expect("an explicit revspec works"),
+			[
+				"Using the following revspec: `HEAD`",
+				"",
+				"Jira tickets: (1 total)",
+				"- PROJ-1 (1)",
+				"",
+			]
+			.join("\n")
+		);
+	}
+
+	#[test]
+	fn list_defaults_to_the_upstream_of_the_current_branch() {
+		let (_origin_repo, fixture_repo, _) = detached_fixture_repo("list-upstream");
+		fixture_repo.git(&["checkout", "--quiet", "main"]);
+		fixture_repo.commit("PROJ-3: Add a local feature");
+
+		assert_eq!(
+			run_clog(&["list", "--repo", fixture_repo.path_str()]).expect("the upstream is used"),
+			[
+				"No revspec was provided, so defaulting to the following: `origin/main..HEAD`",
+				"",
+				"Jira tickets: (1 total)",
+				"- PROJ-3 (1)",
+				"",
+			]
+			.join("\n")
+		);
+	}
+
+	#[test]
+	fn compare_works_from_a_detached_head() {
+		let (_origin_repo, fixture_repo, first_revision) =
+			detached_fixture_repo("compare-detached");
+
+		assert_eq!(
+			run_clog(&["compare", "--repo", fixture_repo.path_str(), "HEAD", "main"])
+				.expect("a detached HEAD can be compared"),
+			[
+				"Comparing the following two references: `HEAD` against `main`",
+				"`HEAD` is fully contained in `main`, so the section only on `HEAD` will be empty",
+				format!(
+					"`HEAD` is 1 commit / 0 days behind `main` (last common commit `{}` on \
+					 2024-03-10)",
+					&first_revision[..8]
+				)
+				.as_str(),
+				"Cherry-picks are detected with: commit message references (`clog`)",
+				"",
+				"Jira tickets only on `HEAD`: (0 total)",
+				"",
+				"Jira tickets only on `main`: (1 total)",
+				"- PROJ-2 (1)",
+				"",
+				"Jira tickets on both `HEAD` and `main`: (0 total)",
+				"",
+			]
+			.join("\n")
+		);
+	}
+
+	#[test]
+	fn compare_stops_early_for_the_same_commit() {
+		let fixture_repo = FixtureRepo::new("compare-same-commit");
+		fixture_repo.commit("PROJ-1: Initial commit");
+		let tip_revision = fixture_repo.commit("PROJ-2: Add a feature");
+		fixture_repo.git(&["tag", "--annotate", "--message=Release", "v1.0.0"]);
+
+		let args = [
+			"compare",
+			"--repo",
+			fixture_repo.path_str(),
+			"main",
+			"v1.0.0",
+		];
+		assert_eq!(
+			run_clog(&args).expect("the comparison succeeds"),
+			[
+				format!(
+					"Comparing the following two references: `main` against `v1.0.0` (annotated \
+					 tag of `{}`)",
+					&tip_revision[..8]
+				),
+				format!(
+					"Both objects resolve to `{}`, so there's nothing to compare",
+					&tip_revision[..8]
+				),
+				String::new(),
+			]
+			.join("\n")
+		);
+
+		let error = run_clog(&[&args[..], &["--fail-if-empty"]].concat())
+			.expect_err("the comparison is empty");
+		assert_eq!(
+			error.to_string(),
+			"the comparison is empty, since both objects resolve to the same commit"
+		);
+	}
+
+	#[test]
+	fn compare_points_out_when_one_object_contains_the_other() {
+		let fixture_repo = FixtureRepo::new("compare-ancestor");
+		fixture_repo.commit("PROJ-1: Initial commit");
+		fixture_repo.git(&["branch", "old"]);
+		fixture_repo.commit("PROJ-2: Add a feature");
+
+		// The objects in order, and the line about which one is contained in the other
+		let cases = [
+			(
+				["old", "main"],
+				"`old` is fully contained in `main`, so the section only on `old` will be empty",
+			),
+			(
+				["main", "old"],
+				"`old` is fully contained in `main`, so the section only on `old` will be empty",
+			),
+		];
+		for ([object_a, object_b], expected) in cases {
+			let output = run_clog(&[
+				"compare",
+				"--repo",
+				fixture_repo.path_str(),
+				object_a,
+				object_b,
+			])
+			.expect("the comparison succeeds");
+			assert_eq!(
+				output.lines().nth(1),
+				Some(expected),
+				"{object_a} {object_b}"
+			);
+		}
+	}
+
+	#[test]
+	fn compare_shows_how_far_behind_each_object_is() {
+		// `release` branches off on the 10th and gets a fix on the 12th, while `main`
+		// carries on until the 20th
+		let fixture_repo = FixtureRepo::new("compare-behind");
+		let base_revision =
+			fixture_repo.commit_at("PROJ-1: Initial commit", "2024-03-10T12:00:00Z");
+		fixture_repo.git(&["branch", "release"]);
+		fixture_repo.commit_at("PROJ-2: Add a feature", "2024-03-15T12:00:00Z");
+		fixture_repo.commit_at("PROJ-3: Add another feature", "2024-03-20T12:00:00Z");
+		fixture_repo.git(&["checkout", "--quiet", "release"]);
+		fixture_repo.commit_at("PROJ-4: Fix a bug", "2024-03-12T12:00:00Z");
+
+		let output = run_clog(&[
+			"compare",
+			"--repo",
+			fixture_repo.path_str(),
+			"main",
+			"release",
+		])
+		.expect("the comparison succeeds");
+		let merge_base_description = format!(
+			"(last common commit `{}` on 2024-03-10)",
+			&base_revision[..8]
+		);
+		assert_eq!(
+			output.lines().skip(1).take(2).collect::<Vec<_>>(),
+			[
+				format!("`main` is 1 commit / 2 days behind `release` {merge_base_description}"),
+				format!("`release` is 2 commits / 10 days behind `main` {merge_base_description}"),
+			]
+		);
+	}
+
+	#[test]
+	fn diverged_local_branches_are_pointed_out() {
+		let origin_repo = FixtureRepo::new("diverged-origin");
+		origin_repo.commit("PROJ-1: Initial commit");
+		let fixture_repo = FixtureRepo::clone_of(&origin_repo, "diverged");
+		let repo_dir = fixture_repo.path_str();
+
+		// Nothing is said until both sides have commits of their own
+		assert_eq!(
+			get_upstream_divergence_warning(repo_dir, "main").expect("the check succeeds"),
+			None
+		);
+		fixture_repo.commit("PROJ-2: Add a local feature");
+		assert_eq!(
+			get_upstream_divergence_warning(repo_dir, "main").expect("the check succeeds"),
+			None
+		);
+
+		// Like a force-push that rewrote the remote branch
+		origin_repo.commit("PROJ-3: Add a remote feature");
+		origin_repo.commit("PROJ-4: Add another remote feature");
+		fixture_repo.git(&["fetch", "--quiet"]);
+		assert_eq!(
+			get_upstream_divergence_warning(repo_dir, "main").expect("the check succeeds"),
+			Some(
+				"Local `main` and `origin/main` have diverged: 1 local-only, 2 remote-only \
+				 commits. If the remote branch was force-pushed, you probably want to use \
+				 `origin/main` instead."
+					.to_owned()
+			)
+		);
+		// Remote branches and commits don't have upstreams to diverge from
+		for reference in ["origin/main", "HEAD~1"] {
+			assert_eq!(
+				get_upstream_divergence_warning(repo_dir, reference).expect("the check succeeds"),
+				None,
+				"{reference}"
+			);
+		}
+	}
+
+	#[test]
+	fn compare_resolves_annotated_tags() {
+		let fixture_repo = FixtureRepo::new("compare-annotated-tags");
+		let first_revision = fixture_repo.commit("PROJ-1: Initial commit");
+		fixture_repo.git(&["tag", "--annotate", "--message=First release", "v1.0.0"]);
+		let second_revision = fixture_repo.commit("PROJ-2: Add a feature");
+		fixture_repo.git(&["tag", "--annotate", "--message=Second release", "v2.0.0"]);
+
+		assert_eq!(
+			run_clog(&[
+				"compare",
+				"--repo",
+				fixture_repo.path_str(),
+				"v1.0.0",
+				"v2.0.0"
+			])
+			.expect("the comparison succeeds"),
+			[
+				format!(
+					"Comparing the following two references: `v1.0.0` (annotated tag of `{}`) \
+					 against `v2.0.0` (annotated tag of `{}`)",
+					&first_revision[..8],
+					&second_revision[..8]
+				)
+				.as_str(),
+				"`v1.0.0` is fully contained in `v2.0.0`, so the section only on `v1.0.0` will be \
+				 empty",
+				format!(
+					"`v1.0.0` is 1 commit / 0 days behind `v2.0.0` (last common commit `{}` on \
+					 2024-03-10)",
+					&first_revision[..8]
+				)
+				.as_str(),
+				"Cherry-picks are detected with: commit message references (`clog`)",
+				"",
+				"Jira tickets only on `v1.0.0`: (0 total)",
+				"",
+				"Jira tickets only on `v2.0.0`: (1 total)",
+				"- PROJ-2 (1)",
+				"",
+				"Jira tickets on both `v1.0.0` and `v2.0.0`: (0 total)",
+				"",
+			]
+			.join("\n")
+		);
+	}
+
+	#[test]
+	fn compare_picks_the_most_recent_release_tags() {
+		let fixture_repo = FixtureRepo::new("compare-releases");
+		fixture_repo.commit("PROJ-1: Initial commit");
+		fixture_repo.git(&["tag", "v1.2.0"]);
+		fixture_repo.commit("PROJ-2: Add a feature");
+		fixture_repo.git_at(
+			&["tag", "--annotate", "--message=Release", "v1.10.0"],
+			"2024-03-01T12:00:00Z",
+		);
+		fixture_repo.git(&["tag", "--annotate", "--message=Release", "v1.9.0", "HEAD~1"]);
+
+		// The number of releases, and the tags that are picked for it
+		let cases = [("2", "v1.9.0", "v1.10.0"), ("3", "v1.2.0", "v1.10.0")];
+		for (release_count, object_a, object_b) in cases {
+			let output = run_clog(&[
+				"compare",
+				"--repo",
+				fixture_repo.path_str(),
+				"--releases",
+				"v*",
+				"--release-count",
+				release_count,
+			])
+			.expect("the comparison succeeds");
+			assert_eq!(
+				output.lines().next(),
+				Some(
+					format!(
+						"Using the release tags `{object_a}` and `{object_b}`, the oldest and \
+						 newest of the {release_count} most recent tags matching `v*`"
+					)
+					.as_str()
+				),
+				"{release_count}"
+			);
+		}
+
+		let error = run_clog(&[
+			"compare",
+			"--repo",
+			fixture_repo.path_str(),
+			"--releases",
+			"v*",
+			"--release-count",
+			"4",
+		])
+		.expect_err("there aren't enough tags");
+		assert_eq!(
+			error.to_string(),
+			"`--releases` needs at least 4 tags matching `v*`, but only 3 were found"
+		);
+	}
+
+	#[test]
+	fn only_late_cherry_picks_are_detected_by_date() {
+		const AUTHORED_AT: i64 = 1_700_000_000;
+		const WINDOW_SECONDS: i64 = 30 * SECONDS_PER_DAY;
+
+		let dated_commit = |git_revision: &str, committed_days_later: i64| {
+			let mut commit = commit(full_hash(git_revision).as_str(), &[]);
+			commit.author.timestamp = AUTHORED_AT;
+			commit.committer.timestamp = AUTHORED_AT + committed_days_later * SECONDS_PER_DAY;
+			commit
+		};
+		// Whether the commit on object B was back-ported or just rebased, by how many
+		// days after it was authored it was committed
+		let cases = [(0, false), (2, false), (30, false), (31, true), (120, true)];
+		for (committed_days_later, is_backport) in cases {
+			let original_commit = dated_commit("a1", 0);
+			let picked_commit = dated_commit("b2", committed_days_later);
+			let mut search_results_only_on_object_a = vec![included(&original_commit, Vec::new())];
+			let mut search_results_only_on_object_b = vec![included(&picked_commit, Vec::new())];
+
+			let removed_count = remove_probable_backports(
+				&mut search_results_only_on_object_a,
+				&mut search_results_only_on_object_b,
+				WINDOW_SECONDS,
+			);
+
+			assert_eq!(
+				removed_count,
+				usize::from(is_backport),
+				"{committed_days_later}"
+			);
+			assert_eq!(
+				search_results_only_on_object_a.is_empty(),
+				is_backport,
+				"{committed_days_later}"
+			);
+			assert_eq!(
+				search_results_only_on_object_b.is_empty(),
+				is_backport,
+				"{committed_days_later}"
+			);
+		}
+	}
+
+	#[test]
+	fn cherry_picks_by_date_need_the_same_authorship() {
+		const AUTHORED_AT: i64 = 1_700_000_000;
+		const COMMITTED_AT: i64 = AUTHORED_AT + 120 * SECONDS_PER_DAY;
+
+		let original_commit = {
+			let mut commit = commit(full_hash("a1").as_str(), &[]);
+			commit.author.timestamp = AUTHORED_AT;
+			commit.committer.timestamp = AUTHORED_AT;
+			commit
+		};
+		// Months later, but authored a second later or by someone else
+		let mut later_commit = commit(full_hash("b2").as_str(), &[]);
+		later_commit.author.timestamp = AUTHORED_AT + 1;
+		later_commit.committer.timestamp = COMMITTED_AT;
+		let mut other_author_commit = commit(full_hash("c3").as_str(), &[]);
+		other_author_commit.author.timestamp = AUTHORED_AT;
+		other_author_commit.author.name = "Other Author".to_owned();
+		other_author_commit.committer.timestamp = COMMITTED_AT;
+		let mut search_results_only_on_object_a = vec![included(&original_commit, Vec::new())];
+		let mut search_results_only_on_object_b = vec![
+			included(&later_commit, Vec::new()),
+			included(&other_author_commit, Vec::new()),
+		];
+
+		assert_eq!(
+			remove_probable_backports(
+				&mut search_results_only_on_object_a,
+				&mut search_results_only_on_object_b,
+				30 * SECONDS_PER_DAY,
+			),
+			0
+		);
+		assert_eq!(search_results_only_on_object_a.len(), 1);
+		assert_eq!(search_results_only_on_object_b.len(), 2);
+	}
+
+	#[test]
+	fn ticket_prefixes_with_backticks_are_escaped() {
+		let fixture_repo = FixtureRepo::new("list-backtick-prefix");
+		let first_revision = fixture_repo.commit("PROJ-1: Initial commit");
+		let second_revision = fixture_repo.commit("PROJ-2: Add a feature");
+
+		assert_eq!(
+			run_clog(&[
+				"list",
+				"--repo",
+				fixture_repo.path_str(),
+				"--ticket-prefix",
+				"#`",
+				"--show-commits=true",
+				"main",
+			])
+			.expect("the list succeeds"),
+			[
+				"Using the following revspec: `main`".to_owned(),
+				String::new(),
+				"Jira tickets: (2 total)".to_owned(),
+				"- #\\`PROJ-1:".to_owned(),
+				format!("\t- `{}`", &first_revision[..8]),
+				"- #\\`PROJ-2:".to_owned(),
+				format!("\t- `{}`", &second_revision[..8]),
+				String::new(),
+			]
+			.join("\n")
+		);
+	}
+
+	#[test]
+	fn json_lines_carry_the_raw_displayed_and_linked_ticket() {
+		let fixture_repo = FixtureRepo::new("list-json-lines-tickets");
+		let revision = fixture_repo.commit("PROJ-12: Initial commit");
+		let commit_json = format!(
+			"{{\"hash\":\"{revision}\",\"subject\":\"PROJ-12: Initial \
+			 commit\",\"is_merge\":false,\"svn_revision\":null,\"svn_url\":null,\"duplicates\":[],\
+			 \"linked_commits\":[]}}"
+		);
+
+		// The display arguments, and the ticket line they produce
+		let cases = [
+			(
+				&["--ticket-url", "https://jira.example.com/browse/{ticket}"][..],
+				format!(
+					"{{\"type\":\"ticket\",\"key\":\"PROJ-12\",\"display\":\"#PROJ-12\",\"url\":\"\
+					 https://jira.example.com/browse/PROJ-12\",\"commit_count\":1,\"commits\":\
+					 [{commit_json}]}}"
+				),
+			),
+			// The key is always the whole ticket, even when the display leaves some out
+			(
+				&["--strip-project=true"][..],
+				format!(
+					"{{\"type\":\"ticket\",\"key\":\"PROJ-12\",\"display\":\"#12\",\"url\":null,\"\
+					 commit_count\":1,\"commits\":[{commit_json}]}}"
+				),
+			),
+		];
+		for (display_args, expected) in cases {
+			let output = run_clog(
+				&[
+					&[
+						"list",
+						"--repo",
+						fixture_repo.path_str(),
+						"--format",
+						"jsonl",
+						"--ticket-prefix",
+						"#",
+					],
+					display_args,
+					&["main"],
+				]
+				.concat(),
+			)
+			.expect("the list succeeds");
+			let lines = output.lines().collect::<Vec<_>>();
+			assert_eq!(lines.len(), 3, "{display_args:?}");
+			assert!(
+				lines[0].starts_with("{\"type\":\"meta\","),
+				"{display_args:?}"
+			);
+			assert_eq!(lines[1], expected, "{display_args:?}");
+		}
+	}
+
+	#[test]
+	fn nested_commits_use_the_custom_indentation_and_merge_marker() {
+		// The merge brings in `b2`, which is a back-port of `c3`
+		let merge_commit = commit(
+			full_hash("a1").as_str(),
+			&[&full_hash("e5"), &full_hash("b2")],
+		);
+		let backport_commit = commit(full_hash("b2").as_str(), &[]);
+		let original_commit = commit(full_hash("c3").as_str(), &[]);
+		let other_commit = commit(full_hash("d4").as_str(), &[]);
+		let jira_ticket_group = vec![
+			included(
+				&merge_commit,
+				vec![included(
+					&backport_commit,
+					vec![included(&original_commit, Vec::new())],
+				)],
+			),
+			included(&other_commit, Vec::new()),
+		];
+		let no_jira_ticket_group = vec![included(&other_commit, Vec::new())];
+		let jira_tickets = [
+			(&Some("PROJ-1"), &jira_ticket_group),
+			(&None, &no_jira_ticket_group),
+		];
+		let svn_layout = SvnLayout::default();
+		let commit_display_options = CommitDisplayOptions {
+			hash_length:       8,
+			show_svn_path:     false,
+			show_svn_revision: false,
+			svn_layout:        &svn_layout,
+			indentation:       get_indentation_str(false, Some("> ")),
+			merge_marker:      " [merge]",
+		};
+		let ticket_display = TicketDisplay {
+			prefix:        "",
+			strip_project: false,
+			url_template:  None,
+		};
+
+		let mut output = Vec::new();
+		let mut multi_writer = MultiWriter::new(vec![&mut output]);
+		display_jira_ticket_commit_list(
+			&mut multi_writer,
+			&jira_tickets,
+			true,
+			None,
+			None,
+			&commit_display_options,
+			&ticket_display,
+		)
+		.expect("writing to memory doesn't fail");
+		drop(multi_writer);
+
+		assert_eq!(
+			String::from_utf8(output).expect("the output is UTF-8"),
+			[
+				"- PROJ-1:",
+				"> - `a1000000` [merge]",
+				"> > - `b2000000`",
+				"> > > - `c3000000`",
+				"> - `d4000000`",
+				"- *No Jira Ticket*:",
+				"> - `d4000000`",
+				"",
+			]
+			.join("\n")
+		);
+	}
+
+	#[test]
+	fn indent_and_merge_marker_options_reach_the_output() {
+		let fixture_repo = FixtureRepo::new("list-indent");
+		fixture_repo.commit("PROJ-1: Initial commit");
+		fixture_repo.git(&["checkout", "--quiet", "-b", "feature"]);
+		let feature_revision = fixture_repo.commit("PROJ-2: Add a feature");
+		fixture_repo.git(&["checkout", "--quiet", "main"]);
+		fixture_repo.git(&[
+			"merge",
+			"--quiet",
+			"--no-ff",
+			"--message=PROJ-2: Merge the feature",
+			"feature",
+		]);
+		let merge_revision = fixture_repo.git(&["rev-parse", "HEAD"]);
+
+		assert_eq!(
+			run_clog(&[
+				"list",
+				"--repo",
+				fixture_repo.path_str(),
+				"--show-commits=true",
+				"--include-merge-commits=true",
+				"--indent",
+				">> ",
+				"--merge-marker",
+				" [merge]",
+				"HEAD^1..HEAD",
+			])
+			.expect("the list succeeds"),
+			[
+				"Using the following revspec: `HEAD^1..HEAD`".to_owned(),
+				String::new(),
+				"Jira tickets: (1 total)".to_owned(),
+				"- PROJ-2:".to_owned(),
+				format!(">> - `{}` [merge]", &merge_revision[..8]),
+				format!(">> - `{}`", &feature_revision[..8]),
+				String::new(),
+			]
+			.join("\n")
+		);
+	}
+
+	#[test]
+	fn output_is_the_same_with_and_without_capturing() {
+		let commits = ["a1", "b2", "c3"]
+			.iter()
+			.map(|prefix| commit(full_hash(prefix).as_str(), &[]))
+			.collect::<Vec<_>>();
+		let jira_ticket_group = vec![
+			included(&commits[0], vec![included(&commits[1], Vec::new())]),
+			included(&commits[2], Vec::new()),
+		];
+		let jira_tickets = [(&Some("PROJ-1"), &jira_ticket_group)];
+		let svn_layout = SvnLayout::default();
+		let commit_display_options = CommitDisplayOptions {
+			hash_length:       8,
+			show_svn_path:     false,
+			show_svn_revision: false,
+			svn_layout:        &svn_layout,
+			indentation:       INDENTATION_STR,
+			merge_marker:      MERGE_COMMIT_MARKER_STR,
+		};
+		let ticket_display = TicketDisplay {
+			prefix:        "",
+			strip_project: false,
+			url_template:  None,
+		};
+		let write_output = |multi_writer: &mut MultiWriter| {
+			display_jira_ticket_commit_list(
+				multi_writer,
+				&jira_tickets,
+				true,
+				None,
+				None,
+				&commit_display_options,
+				&ticket_display,
+			)
+			.expect("writing to memory doesn't fail");
+			multi_writer.bytes_written()
+		};
+
+		let mut uncaptured_output = Vec::new();
+		let uncaptured_byte_count =
+			write_output(&mut MultiWriter::new(vec![&mut uncaptured_output]));
+		let mut output = Vec::new();
+		let mut captured_output = Vec::new();
+		let byte_count = write_output(&mut MultiWriter::new(vec![
+			&mut output,
+			&mut captured_output,
+		]));
+
+		assert!(!uncaptured_output.is_empty());
+		assert_eq!(output, uncaptured_output);
+		assert_eq!(captured_output, uncaptured_output);
+		// The section ranges for the clipboard are based on this
+		assert_eq!(byte_count, uncaptured_byte_count);
+	}
+
+	#[test]
+	fn search_leaves_merges_out_of_the_seed_unless_asked() {
+		// The ticket is only mentioned on the merge, not on the work it merges
+		let fixture_repo = FixtureRepo::new("search-merge-seed");
+		fixture_repo.commit("PROJ-1: Initial commit");
+		fixture_repo.git(&["checkout", "--quiet", "-b", "feature"]);
+		fixture_repo.commit("Add a feature");
+		fixture_repo.git(&["checkout", "--quiet", "main"]);
+		fixture_repo.git(&[
+			"merge",
+			"--quiet",
+			"--no-ff",
+			"--message=PROJ-7: Merge the feature",
+			"feature",
+		]);
+		let merge_revision = fixture_repo.git(&["rev-parse", "HEAD"]);
+		let search = |include_merge_commits: &str| {
+			run_clog(&[
+				"search",
+				"--repo",
+				fixture_repo.path_str(),
+				"--local-branches=true",
+				format!("--include-merge-commits={include_merge_commits}").as_str(),
+				"PROJ-7",
+			])
+			.expect("the search succeeds")
+		};
+
+		assert_eq!(
+			search("false"),
+			[
+				"Searching for all locations where any commits were merged for the following:",
+				"- PROJ-7",
+				"",
+				"Excluded 1 merge commit(s) from the commits being searched (use \
+				 `--include-merge-commits` to include them).",
+				"",
+				"Commit list being searched, with commits that merge them elsewhere as \
+				 sub-entries:",
+				"",
+				"Results:",
+				"",
+			]
+			.join("\n")
+		);
+		let merge_commit_line = format!("`{}` (M)", &merge_revision[..8]);
+		assert_eq!(
+			search("true"),
+			[
+				"Searching for all locations where any commits were merged for the following:",
+				"- PROJ-7",
+				"",
+				"Commit list being searched, with commits that merge them elsewhere as \
+				 sub-entries:",
+				format!("- {merge_commit_line}").as_str(),
+				"",
+				"Results:",
+				"- Set 0:",
+				"\t- Commits:",
+				format!("\t\t- {merge_commit_line}").as_str(),
+				"\t- Branches:",
+				"\t\t- `* main` (updated 2024-03-10)",
+				"",
+			]
+			.join("\n")
+		);
+	}
+
+	#[test]
+	fn version_output_names_the_package_version() {
+		let output = run_clog(&["version"]).expect("the version is printed");
+
+		assert_eq!(
+			output.lines().next(),
+			Some(format!("{APPLICATION_PROPER_NAME} {}", env!("CARGO_PKG_VERSION")).as_str())
+		);
+		for label in ["Commit: ", "Target: ", "Compiler: ", "Git: "] {
+			assert!(
+				output.lines().any(|line| line.starts_with(label)),
+				"{label}"
+			);
+		}
+	}
+
+	#[test]
+	fn json_lines_meta_records_the_git_commands() {
+		let fixture_repo = FixtureRepo::new("list-json-lines-commands");
+		fixture_repo.commit("PROJ-1: Initial commit");
+
+		// Whether merges are included, and the search command that's run for it
+		let cases = [
+			(
+				"false",
+				r#"["git","log","--pretty=format:%H","main","--no-merges"]"#,
+			),
+			("true", r#"["git","log","--pretty=format:%H","main"]"#),
+		];
+		for (include_merge_commits, expected_command) in cases {
+			let output = run_clog(&[
+				"list",
+				"--repo",
+				fixture_repo.path_str(),
+				"--format",
+				"jsonl",
+				format!("--include-merge-commits={include_merge_commits}").as_str(),
+				"main",
+			])
+			.expect("the list succeeds");
+			let meta_line = output.lines().next().expect("there's a meta line");
+
+			assert!(
+				meta_line.contains(
+					format!(
+						"\"git_version\":\"{}\"",
+						get_git_version().expect("Git is installed")
+					)
+					.as_str()
+				),
+				"{meta_line}"
+			);
+			let commands = meta_line
+				.split_once("\"commands\":")
+				.expect("the commands are recorded")
+				.1;
+			assert!(
+				commands.contains(format!("{expected_command},").as_str())
+					|| commands.contains(format!("{expected_command}]").as_str()),
+				"{include_merge_commits}: {commands}"
+			);
+			// The commits are collected from the repo before they're searched
+			assert!(
+				commands.starts_with(r#"[["git","log","--all","#)
+					|| commands.contains(r#"],["git","log","--all","#),
+				"{include_merge_commits}: {commands}"
+			);
+		}
+	}
+}
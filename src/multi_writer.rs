@@ -2,46 +2,78 @@
 //! simultaneously.
 
 // Uses
-use std::io::{Error, ErrorKind, Result, Write};
+use std::io::{Result, Write};
 
 /// A writer for writing to multiple destinations simultaneously.
 pub struct MultiWriter<'a> {
-	children: Vec<&'a mut dyn Write>,
+	children:      Vec<&'a mut dyn Write>,
+	bytes_written: usize,
 }
 
 impl<'a> MultiWriter<'a> {
 	pub fn new(children: Vec<&'a mut dyn Write>) -> Self {
-		Self { children }
+		Self {
+			children,
+			bytes_written: 0,
+		}
+	}
+
+	/// The total number of bytes written so far, which can be used to mark
+	/// positions in the output.
+	pub fn bytes_written(&self) -> usize {
+		self.bytes_written
 	}
 }
 
 impl<'a> Write for MultiWriter<'a> {
 	fn write(&mut self, buf: &[u8]) -> Result<usize> {
-		if self.children.is_empty() {
-			return Ok(buf.len());
-		}
-
-		let written_byte_counts = self
-			.children
-			.iter_mut()
-			.map(|w| w.write(buf))
-			.collect::<Result<Vec<_>>>()?;
-
-		let first_byte_count = written_byte_counts[0];
-		if !written_byte_counts
-			.iter()
-			.all(|byte_count| *byte_count == first_byte_count)
-		{
-			return Err(Error::new(
-				ErrorKind::Other,
-				"underlying writers wrote different byte counts",
-			));
+		// Each destination gets the whole buffer, even if it only takes part of it
+		// at a time (like a pipe), so they all end up with the same output
+		for child in &mut self.children {
+			child.write_all(buf)?;
 		}
 
-		Ok(first_byte_count)
+		self.bytes_written += buf.len();
+		Ok(buf.len())
 	}
 
 	fn flush(&mut self) -> Result<()> {
 		self.children.iter_mut().try_for_each(Write::flush)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A writer that takes at most a few bytes per call, like a full pipe.
+	struct ShortWriter(Vec<u8>);
+
+	impl Write for ShortWriter {
+		fn write(&mut self, buf: &[u8]) -> Result<usize> {
+			let byte_count = buf.len().min(3);
+			self.0.extend_from_slice(&buf[..byte_count]);
+			Ok(byte_count)
+		}
+
+		fn flush(&mut self) -> Result<()> {
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn every_destination_gets_the_whole_output() {
+		let mut short_writer = ShortWriter(Vec::new());
+		let mut captured_output = Vec::new();
+		let mut multi_writer = MultiWriter::new(vec![&mut short_writer, &mut captured_output]);
+
+		multi_writer
+			.write_all(b"- PROJ-1 (2)\n")
+			.expect("writing to memory doesn't fail");
+		assert_eq!(multi_writer.bytes_written(), 13);
+		drop(multi_writer);
+
+		assert_eq!(short_writer.0, b"- PROJ-1 (2)\n");
+		assert_eq!(captured_output, b"- PROJ-1 (2)\n");
+	}
+}
@@ -0,0 +1,123 @@
+//! The module for showing long output through a pager, the way Git does.
+
+// Uses
+use std::{
+	env,
+	io::{stdout, ErrorKind, IsTerminal, Result as IoResult, Write},
+	process::{Child, ChildStdin, Command, Stdio},
+};
+
+use anyhow::{anyhow, Context, Result};
+use shell_words::split as split_shell_words;
+
+use crate::util::is_verbose;
+
+// Constants
+/// The pager to use when none is configured. `-F` has `less` quit straight away
+/// if the output fits on the screen, so short output isn't paged at all.
+const DEFAULT_PAGER: &str = "less -FRX";
+/// The options to give `less` when the environment doesn't have any, in case
+/// it's configured without them.
+const DEFAULT_LESS_OPTIONS: &str = "FRX";
+
+// Types and Structures
+/// A running pager, which the output is written to instead of stdout.
+///
+/// Dropping it closes its input and waits for it to exit, so the terminal is
+/// back to normal before anything else is printed.
+pub struct Pager {
+	child: Child,
+	input: Option<ChildStdin>,
+}
+
+impl Pager {
+	/// Starts the pager from `$CLOG_PAGER` or `$PAGER`, if stdout is a terminal
+	/// that can use one.
+	///
+	/// An empty pager (or `cat`) turns paging off, the same as with Git.
+	pub fn start_if_appropriate() -> Result<Option<Self>> {
+		if !stdout().is_terminal() || env::var("TERM").map_or(false, |term| term == "dumb") {
+			return Ok(None);
+		}
+
+		let pager = env::var("CLOG_PAGER")
+			.or_else(|_| env::var("PAGER"))
+			.unwrap_or_else(|_| DEFAULT_PAGER.to_owned());
+		let pager_words = split_shell_words(pager.as_str())
+			.with_context(|| format!("unable to parse the pager command `{pager}`"))?;
+		let Some((program, args)) = pager_words.split_first() else {
+			return Ok(None);
+		};
+		if program == "cat" {
+			return Ok(None);
+		}
+
+		let mut command = Command::new(program);
+		command.args(args).stdin(Stdio::piped());
+		if env::var_os("LESS").is_none() {
+			command.env("LESS", DEFAULT_LESS_OPTIONS);
+		}
+		if is_verbose() {
+			eprintln!("[VERBOSE] Paging the output with: {pager}");
+		}
+		let mut child = match command.spawn() {
+			Ok(child) => child,
+			Err(error) => {
+				eprintln!(
+					"[WARNING] Unable to start the pager `{pager}`, so the output isn't paged: \
+					 {error}"
+				);
+				return Ok(None);
+			}
+		};
+		let input = child
+			.stdin
+			.take()
+			.ok_or_else(|| anyhow!("the pager's input wasn't piped (impossible)"))?;
+
+		Ok(Some(Self {
+			child,
+			input: Some(input),
+		}))
+	}
+}
+
+/// Output written after the pager has been closed (like when someone quits
+/// `less` early) is dropped, instead of being treated as an error.
+impl Write for Pager {
+	fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+		let Some(input) = &mut self.input else {
+			return Ok(buf.len());
+		};
+		match input.write(buf) {
+			Err(error) if error.kind() == ErrorKind::BrokenPipe => {
+				self.input = None;
+				Ok(buf.len())
+			}
+			result => result,
+		}
+	}
+
+	fn flush(&mut self) -> IoResult<()> {
+		let Some(input) = &mut self.input else {
+			return Ok(());
+		};
+		match input.flush() {
+			Err(error) if error.kind() == ErrorKind::BrokenPipe => {
+				self.input = None;
+				Ok(())
+			}
+			result => result,
+		}
+	}
+}
+
+impl Drop for Pager {
+	fn drop(&mut self) {
+		// Closing the input is what tells the pager there's nothing more coming
+		drop(self.input.take());
+		if let Err(error) = self.child.wait() {
+			eprintln!("[WARNING] Unable to wait for the pager to exit: {error}");
+		}
+	}
+}
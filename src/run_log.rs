@@ -0,0 +1,176 @@
+//! The module for appending a record of each run to a log file, so that there's
+//! an audit trail of every report that was generated.
+
+// Uses
+use std::{
+	fs::OpenOptions,
+	io::Write,
+	path::Path,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+
+use crate::util::json_string;
+
+// Types and Structures
+/// The details of a run that are only known partway through it, like the
+/// resolved revspec and the number of results.
+#[derive(Debug, Default)]
+pub struct RunDetails {
+	/// What the run looked at, like the revspec or the objects being compared.
+	targets: Vec<(&'static str, String)>,
+	/// Summary counts of the results.
+	counts:  Vec<(&'static str, usize)>,
+}
+
+impl RunDetails {
+	/// Records what the run looked at.
+	pub fn record_target(&mut self, name: &'static str, value: &str) {
+		self.targets.push((name, value.to_owned()));
+	}
+
+	/// Records a summary count of the results of the run.
+	pub fn record_count(&mut self, name: &'static str, count: usize) {
+		self.counts.push((name, count));
+	}
+}
+
+/// Everything about a run that's known from the outside.
+#[derive(Debug)]
+pub struct RunRecord<'a> {
+	pub started_at: SystemTime,
+	pub duration:   Duration,
+	pub subcommand: Option<&'a str>,
+	pub arguments:  &'a [String],
+	pub repo_dir:   Option<&'a str>,
+	pub details:    &'a RunDetails,
+	pub result:     &'a Result<()>,
+}
+
+/// Appends a record of a run to the log file, as a single line of JSON.
+///
+/// The whole line is written with a single call on a file opened in append
+/// mode, so concurrent runs logging to the same file don't interleave their
+/// lines.
+pub fn append_run_record<P>(path: P, run_record: &RunRecord) -> Result<()>
+where
+	P: AsRef<Path>,
+{
+	let run_details = run_record.details;
+	let mut line = format!(
+		"{{\"timestamp\":{},\"subcommand\":{},\"arguments\":[{}],\"repo\":{},\"targets\":{{{}}},\"\
+		 counts\":{{{}}},\"duration_ms\":{},",
+		run_record
+			.started_at
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |since_epoch| since_epoch.as_secs()),
+		run_record
+			.subcommand
+			.map_or_else(|| "null".to_owned(), json_string),
+		run_record
+			.arguments
+			.iter()
+			.map(|argument| json_string(argument))
+			.collect::<Vec<_>>()
+			.join(","),
+		run_record
+			.repo_dir
+			.map_or_else(|| "null".to_owned(), json_string),
+		run_details
+			.targets
+			.iter()
+			.map(|(name, value)| format!("{}:{}", json_string(name), json_string(value)))
+			.collect::<Vec<_>>()
+			.join(","),
+		run_details
+			.counts
+			.iter()
+			.map(|(name, count)| format!("{}:{count}", json_string(name)))
+			.collect::<Vec<_>>()
+			.join(","),
+		run_record.duration.as_millis(),
+	);
+	match run_record.result {
+		Ok(()) => line.push_str("\"status\":\"success\",\"exit_code\":0,\"error\":null}\n"),
+		Err(error) => line.push_str(
+			format!(
+				"\"status\":\"failure\",\"exit_code\":1,\"error\":{}}}\n",
+				json_string(format!("{error:#}").as_str())
+			)
+			.as_str(),
+		),
+	}
+
+	let mut log_file = OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(path.as_ref())
+		.with_context(|| format!("unable to open `{}`", path.as_ref().display()))?;
+	log_file
+		.write_all(line.as_bytes())
+		.with_context(|| format!("unable to write to `{}`", path.as_ref().display()))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs::read_to_string;
+
+	use anyhow::anyhow;
+
+	use super::*;
+	use crate::test_util::FixtureRepo;
+
+	#[test]
+	fn run_records_are_appended_as_json_lines() {
+		let log_dir = FixtureRepo::empty_dir("run-log");
+		let log_path = log_dir.path().join("runs.jsonl");
+		let mut run_details = RunDetails::default();
+		run_details.record_target("revspec", "origin/main..HEAD");
+		run_details.record_count("jira_tickets", 2);
+		let arguments = [
+			"list".to_owned(),
+			"--repo".to_owned(),
+			"my \"repo\"".to_owned(),
+		];
+		let successful_result = Ok(());
+		let failed_result = Err(anyhow!("the tag `v2` doesn't point to a commit"));
+
+		// The details of each run are its own, so the failing one doesn't repeat them
+		for (run_details, result) in [
+			(&run_details, &successful_result),
+			(&RunDetails::default(), &failed_result),
+		] {
+			let run_record = RunRecord {
+				started_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+				duration: Duration::from_millis(1234),
+				subcommand: Some("list"),
+				arguments: arguments.as_slice(),
+				repo_dir: Some("my \"repo\""),
+				details: run_details,
+				result,
+			};
+			append_run_record(log_path.as_path(), &run_record).expect("the record is appended");
+		}
+
+		let log = read_to_string(log_path).expect("the log can be read");
+		assert_eq!(
+			log.lines().collect::<Vec<_>>(),
+			[
+				concat!(
+					r#"{"timestamp":1700000000,"subcommand":"list","#,
+					r#""arguments":["list","--repo","my \"repo\""],"repo":"my \"repo\"","#,
+					r#""targets":{"revspec":"origin/main..HEAD"},"counts":{"jira_tickets":2},"#,
+					r#""duration_ms":1234,"status":"success","exit_code":0,"error":null}"#,
+				),
+				concat!(
+					r#"{"timestamp":1700000000,"subcommand":"list","#,
+					r#""arguments":["list","--repo","my \"repo\""],"repo":"my \"repo\"","#,
+					r#""targets":{},"counts":{},"duration_ms":1234,"status":"failure","#,
+					r#""exit_code":1,"error":"the tag `v2` doesn't point to a commit"}"#,
+				),
+			]
+		);
+		assert!(log.ends_with('\n'));
+	}
+}
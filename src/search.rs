@@ -2,27 +2,57 @@
 
 // Uses
 use std::{
-	collections::{HashSet, VecDeque},
+	cmp::Reverse,
+	collections::{BTreeMap, HashMap, HashSet, VecDeque},
 	hash::{Hash, Hasher},
 	path::Path,
 	process::Command,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use linked_hash_set::LinkedHashSet;
 use shell_words::split as split_shell_words;
 
 use crate::{
 	collection::Commit,
+	constants::GIT_SVN_ID_STR,
+	ids::CommitId,
 	index::Index,
-	util::{inside_out_result, run_command},
+	util::{
+		abbreviate,
+		inside_out_result,
+		run_command,
+		run_command_for_exit_code,
+		run_command_with_input,
+	},
 };
 
+/// A set of commits, along with the branches and tags that contain exactly
+/// those commits.
+pub type LocationSet<'a> = (Vec<&'a Commit>, (Vec<String>, Vec<String>));
+
 /// A commit with its references packed alongside it, ready for display as a
 /// search result.
 #[derive(Clone, Debug)]
 pub struct IncludedCommit<'a> {
-	pub commit:         &'a Commit,
-	pub linked_commits: Vec<IncludedCommit<'a>>,
+	pub commit:            &'a Commit,
+	pub linked_commits:    Vec<IncludedCommit<'a>>,
+	/// Other commits that make the same change, collapsed into this one for
+	/// display.
+	pub duplicate_commits: Vec<&'a Commit>,
+}
+
+/// A commit from a flattened inclusion tree, along with where in the tree it
+/// was first found.
+#[derive(Clone, Copy, Debug)]
+pub struct FlattenedCommit<'a> {
+	pub commit: &'a Commit,
+	/// How many links away from the top level of the tree the commit is, where
+	/// the top-level commits are `0`.
+	pub depth:  usize,
+	/// The top-level commit the commit was found under, which is the commit
+	/// itself at the top level.
+	pub seed:   &'a Commit,
 }
 
 // Since the Git revision is already a hash and will be unique, this
@@ -41,12 +71,786 @@ impl<'a> Hash for IncludedCommit<'a> {
 	}
 }
 
+/// Which references to follow when nesting linked commits under a search
+/// result.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LinkTraversal {
+	/// Follow the commits that each commit references (what it merged or
+	/// cherry-picked).
+	Forward,
+	/// Follow the commits that reference each commit (where it was merged or
+	/// cherry-picked).
+	Backward,
+	/// Don't follow any references, leaving the results flat.
+	None,
+}
+
+/// How to find the branches and tags that contain each commit.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContainmentStrategy {
+	/// Ask Git with `git branch --contains` and `git tag --contains`, once per
+	/// commit.
+	Git,
+	/// Walk the commit graph once from every branch and tag tip, using the
+	/// parent links that are already in the index.
+	Graph,
+}
+
+/// The kind of tag a reference names.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TagKind {
+	/// A tag object with its own hash, message, etc.
+	Annotated,
+	/// A plain ref pointing directly at an object.
+	Lightweight,
+}
+
+/// The state of a repo's `HEAD`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HeadState {
+	/// `HEAD` is on a branch, which has at least one commit.
+	Branch(String),
+	/// `HEAD` points directly at a commit instead of a branch.
+	Detached(String),
+	/// `HEAD` is on a branch that doesn't have any commits yet.
+	Unborn(String),
+}
+
+impl HeadState {
+	/// Returns the name of the branch `HEAD` is on, or an error explaining why
+	/// there isn't one.
+	pub fn require_branch(&self) -> Result<&str> {
+		match self {
+			Self::Branch(branch) => Ok(branch.as_str()),
+			Self::Detached(git_revision) => Err(anyhow!(
+				"HEAD is detached at {}; pass an explicit ref",
+				abbreviate(git_revision, 7).unwrap_or(git_revision)
+			)),
+			Self::Unborn(branch) => Err(unborn_head_error(branch.as_str())),
+		}
+	}
+
+	/// Ensures that `HEAD` resolves to a commit, returning an error explaining
+	/// why it doesn't otherwise.
+	pub fn ensure_has_commits(&self) -> Result<()> {
+		match self {
+			Self::Branch(_) | Self::Detached(_) => Ok(()),
+			Self::Unborn(branch) => Err(unborn_head_error(branch.as_str())),
+		}
+	}
+}
+
+fn unborn_head_error(branch: &str) -> anyhow::Error {
+	anyhow!("HEAD is on `{branch}`, which doesn't have any commits yet; pass an explicit ref")
+}
+
+/// Classifies the repo's `HEAD` as being on a branch, detached, or unborn.
+pub fn get_head_state<P>(repo_dir: P) -> Result<HeadState>
+where
+	P: AsRef<Path>,
+{
+	// `git symbolic-ref` fails if `HEAD` isn't pointing to a branch
+	let mut command = Command::new("git");
+	command
+		.arg("symbolic-ref")
+		.arg("--quiet")
+		.arg("HEAD")
+		.current_dir(repo_dir.as_ref());
+	let is_detached = run_command_for_exit_code(command)
+		.with_context(|| "unable to check whether HEAD is detached")?
+		!= 0;
+
+	if is_detached {
+		let mut command = Command::new("git");
+		command
+			.arg("rev-parse")
+			.arg("--verify")
+			.arg("HEAD")
+			.current_dir(repo_dir.as_ref());
+		let git_revision = run_command(command)
+			.with_context(|| "unable to resolve the detached HEAD")?
+			.trim()
+			.to_owned();
+
+		return Ok(HeadState::Detached(git_revision));
+	}
+
+	let mut command = Command::new("git");
+	command
+		.arg("symbolic-ref")
+		.arg("--short")
+		.arg("HEAD")
+		.current_dir(repo_dir.as_ref());
+	let branch = run_command(command)
+		.with_context(|| "unable to get the branch HEAD is on")?
+		.trim()
+		.to_owned();
+
+	// `git rev-parse --verify` fails if the branch doesn't point to anything yet
+	let mut command = Command::new("git");
+	command
+		.arg("rev-parse")
+		.arg("--verify")
+		.arg("--quiet")
+		.arg("HEAD")
+		.current_dir(repo_dir.as_ref());
+	let is_unborn = run_command_for_exit_code(command)
+		.with_context(|| "unable to check whether HEAD has any commits")?
+		!= 0;
+
+	Ok(if is_unborn {
+		HeadState::Unborn(branch)
+	} else {
+		HeadState::Branch(branch)
+	})
+}
+
+/// Determines whether a reference names a tag, and if so, which kind.
+pub fn get_tag_kind<P>(repo_dir: P, reference: &str) -> Result<Option<TagKind>>
+where
+	P: AsRef<Path>,
+{
+	// Check whether the tag ref exists first
+	let tag_ref = format!("refs/tags/{}", reference.trim_start_matches("refs/tags/"));
+	let mut command = Command::new("git");
+	command
+		.arg("show-ref")
+		.arg("--verify")
+		.arg("--quiet")
+		.arg(tag_ref.as_str())
+		.current_dir(repo_dir.as_ref());
+	if run_command_for_exit_code(command)
+		.with_context(|| format!("unable to check whether `{reference}` is a tag"))?
+		!= 0
+	{
+		return Ok(None);
+	}
+
+	// Annotated tags are objects of their own, while lightweight tags point
+	// directly to (usually) a commit
+	let mut command = Command::new("git");
+	command
+		.arg("cat-file")
+		.arg("-t")
+		.arg(tag_ref.as_str())
+		.current_dir(repo_dir.as_ref());
+	let object_type = run_command(command)
+		.with_context(|| format!("unable to get the object type of the tag `{reference}`"))?;
+
+	Ok(Some(if object_type.trim() == "tag" {
+		TagKind::Annotated
+	} else {
+		TagKind::Lightweight
+	}))
+}
+
+/// Gets the tags matching a glob, newest version first.
+///
+/// The tags are sorted by the version numbers in their names rather than by
+/// date, so the order doesn't depend on when they happened to be created.
+pub fn get_tags_by_version<P>(repo_dir: P, pattern: &str) -> Result<Vec<String>>
+where
+	P: AsRef<Path>,
+{
+	// Prepare the `git tag` command for listing the tags
+	let mut command = Command::new("git");
+	command
+		.arg("tag")
+		.arg("--sort=-v:refname")
+		.arg("--list")
+		.arg(pattern)
+		.current_dir(repo_dir);
+
+	// Run the command
+	let tag_list_raw = run_command(command)
+		.with_context(|| format!("unable to list the tags matching `{pattern}`"))?;
+	let tag_list = tag_list_raw
+		.lines()
+		.filter_map(|line| {
+			let line = line.trim();
+			(!line.is_empty()).then(|| line.to_owned())
+		})
+		.collect::<Vec<_>>();
+
+	Ok(tag_list)
+}
+
+/// Gets the most recent tag reachable from HEAD, if there is one.
+pub fn get_latest_tag<P>(repo_dir: P) -> Result<Option<String>>
+where
+	P: AsRef<Path>,
+{
+	// `git describe` also fails when HEAD doesn't have any commits, which would
+	// otherwise look the same as there not being any tags
+	get_head_state(repo_dir.as_ref())
+		.with_context(|| "unable to determine the state of HEAD to find its latest tag")?
+		.ensure_has_commits()
+		.with_context(|| "there's no tag reachable from HEAD")?;
+
+	// `git describe` fails when there aren't any tags to describe HEAD with, so
+	// check that it succeeds first
+	let build_command = || {
+		let mut command = Command::new("git");
+		command
+			.arg("describe")
+			.arg("--tags")
+			.arg("--abbrev=0")
+			.arg("HEAD")
+			.current_dir(repo_dir.as_ref());
+		command
+	};
+	if run_command_for_exit_code(build_command())
+		.with_context(|| "unable to check for a tag reachable from HEAD")?
+		!= 0
+	{
+		return Ok(None);
+	}
+
+	Ok(Some(
+		run_command(build_command())
+			.with_context(|| "unable to get the most recent tag reachable from HEAD")?
+			.trim()
+			.to_owned(),
+	))
+}
+
+/// Checks whether a directory is inside a Git repo at all.
+pub fn is_git_repo<P>(repo_dir: P) -> Result<bool>
+where
+	P: AsRef<Path>,
+{
+	let mut command = Command::new("git");
+	command
+		.arg("rev-parse")
+		.arg("--git-dir")
+		.current_dir(repo_dir);
+
+	Ok(run_command_for_exit_code(command)
+		.with_context(|| "unable to check whether the directory is a Git repo")?
+		== 0)
+}
+
+/// Asks `git rev-parse` a yes-or-no question about the repo, like
+/// `--is-bare-repository`.
+///
+/// Versions of Git that don't know the flag just echo it back, which counts as
+/// a no.
+pub fn rev_parse_flag<P>(repo_dir: P, flag: &str) -> Result<bool>
+where
+	P: AsRef<Path>,
+{
+	let mut command = Command::new("git");
+	command.arg("rev-parse").arg(flag).current_dir(repo_dir);
+
+	Ok(run_command(command)
+		.with_context(|| format!("unable to check `git rev-parse {flag}`"))?
+		.trim() == "true")
+}
+
+/// Checks whether any commit in the repo has `git-svn` metadata, which is what
+/// revision maps are built from.
+pub fn has_svn_metadata<P>(repo_dir: P) -> Result<bool>
+where
+	P: AsRef<Path>,
+{
+	let mut command = Command::new("git");
+	command
+		.arg("log")
+		.arg("--all")
+		.arg("--max-count=1")
+		.arg(format!("--grep=^{GIT_SVN_ID_STR}:"))
+		.arg("--format=%H")
+		.current_dir(repo_dir);
+
+	Ok(!run_command(command)
+		.with_context(|| "unable to look for commits with SVN metadata")?
+		.trim()
+		.is_empty())
+}
+
+/// Gets the absolute path to the root of the repo's working tree.
+pub fn get_repo_root<P>(repo_dir: P) -> Result<String>
+where
+	P: AsRef<Path>,
+{
+	let mut command = Command::new("git");
+	command
+		.arg("rev-parse")
+		.arg("--show-toplevel")
+		.current_dir(repo_dir);
+
+	Ok(run_command(command)
+		.with_context(|| "unable to get the root of the repo")?
+		.trim()
+		.to_owned())
+}
+
+/// Checks whether one revision is an ancestor of (or the same as) another.
+pub fn is_ancestor<P>(repo_dir: P, ancestor: &str, descendant: &str) -> Result<bool>
+where
+	P: AsRef<Path>,
+{
+	// `git merge-base --is-ancestor` answers with its exit code: 0 for yes, 1 for
+	// no, and anything else for an error
+	let mut command = Command::new("git");
+	command
+		.arg("merge-base")
+		.arg("--is-ancestor")
+		.arg(ancestor)
+		.arg(descendant)
+		.current_dir(repo_dir);
+	match run_command_for_exit_code(command).with_context(|| {
+		format!("unable to check whether `{ancestor}` is an ancestor of `{descendant}`")
+	})? {
+		0 => Ok(true),
+		1 => Ok(false),
+		exit_code => Err(anyhow!(
+			"unable to check whether `{ancestor}` is an ancestor of `{descendant}`: exit code \
+			 {exit_code}"
+		)),
+	}
+}
+
+/// Resolves a reference to the commit it points to, peeling any tags along
+/// the way.
+pub fn peel_to_commit<P>(repo_dir: P, reference: &str) -> Result<String>
+where
+	P: AsRef<Path>,
+{
+	let mut command = Command::new("git");
+	command
+		.arg("rev-parse")
+		.arg("--verify")
+		.arg("--quiet")
+		.arg(format!("{reference}^{{commit}}"))
+		.current_dir(repo_dir);
+
+	Ok(run_command(command)
+		.with_context(|| format!("unable to resolve `{reference}` to a commit"))?
+		.trim()
+		.to_owned())
+}
+
+/// Gets the upstream of a reference, if it's a local branch that has one.
+///
+/// Anything else (remote branches, tags, commits, a detached `HEAD`) simply
+/// has no upstream, so `None` is returned instead of an error.
+pub fn upstream_ref_if_possible<P>(repo_dir: P, reference: &str) -> Result<Option<String>>
+where
+	P: AsRef<Path>,
+{
+	// Check that there is an upstream first, since `git rev-parse` prints an
+	// error otherwise
+	let upstream_spec = format!("{reference}@{{upstream}}");
+	let mut command = Command::new("git");
+	command
+		.arg("rev-parse")
+		.arg("--verify")
+		.arg("--quiet")
+		.arg(upstream_spec.as_str())
+		.current_dir(repo_dir.as_ref());
+	if run_command_for_exit_code(command)
+		.with_context(|| format!("unable to check whether `{reference}` has an upstream"))?
+		!= 0
+	{
+		return Ok(None);
+	}
+
+	let mut command = Command::new("git");
+	command
+		.arg("rev-parse")
+		.arg("--abbrev-ref")
+		.arg("--symbolic-full-name")
+		.arg(upstream_spec.as_str())
+		.current_dir(repo_dir.as_ref());
+	let upstream = run_command(command)
+		.with_context(|| format!("unable to get the upstream of `{reference}`"))?
+		.trim()
+		.to_owned();
+
+	Ok((!upstream.is_empty()).then_some(upstream))
+}
+
+/// Counts the commits that are only on each side of a pair of references,
+/// returning them in the same order as the references.
+pub fn count_divergent_commits<P>(
+	repo_dir: P,
+	reference_a: &str,
+	reference_b: &str,
+) -> Result<(usize, usize)>
+where
+	P: AsRef<Path>,
+{
+	// Prepare the `git rev-list` command for counting the commits
+	let mut command = Command::new("git");
+	command
+		.arg("rev-list")
+		.arg("--left-right")
+		.arg("--count")
+		.arg(format!("{reference_a}...{reference_b}"))
+		.current_dir(repo_dir);
+
+	// Run the command
+	// The output looks like this (without quotes): `<LEFT>\t<RIGHT>`
+	let counts_raw = run_command(command).with_context(|| {
+		format!("unable to count the commits between `{reference_a}` and `{reference_b}`")
+	})?;
+	let (count_a, count_b) = counts_raw
+		.trim()
+		.split_once('\t')
+		.ok_or_else(|| anyhow!("unexpected output from `git rev-list --count`"))?;
+
+	Ok((
+		count_a
+			.parse()
+			.with_context(|| "unable to parse the commit count as an integer")?,
+		count_b
+			.parse()
+			.with_context(|| "unable to parse the commit count as an integer")?,
+	))
+}
+
+/// The newest commit that two revisions have in common.
+#[derive(Clone, Debug)]
+pub struct MergeBase {
+	pub git_revision: String,
+	/// The commit time, as a Unix timestamp.
+	pub timestamp:    i64,
+	/// The commit date, like `2024-02-01`.
+	pub date:         String,
+}
+
+/// Finds the newest merge base of two revisions, along with the commit times
+/// of the revisions themselves.
+///
+/// When there are several merge bases (like after criss-cross merges), the one
+/// committed most recently is used. If the histories are unrelated, there's no
+/// merge base, and `None` is returned in its place.
+pub fn get_newest_merge_base<P>(
+	repo_dir: P,
+	git_revision_a: &str,
+	git_revision_b: &str,
+) -> Result<(Option<MergeBase>, i64, i64)>
+where
+	P: AsRef<Path>,
+{
+	// `git merge-base` exits with 1 when there isn't one
+	let mut command = Command::new("git");
+	command
+		.arg("merge-base")
+		.arg(git_revision_a)
+		.arg(git_revision_b)
+		.current_dir(repo_dir.as_ref());
+	let has_merge_base = match run_command_for_exit_code(command).with_context(|| {
+		format!("unable to find the merge base of `{git_revision_a}` and `{git_revision_b}`")
+	})? {
+		0 => true,
+		1 => false,
+		exit_code => {
+			return Err(anyhow!(
+				"unable to find the merge base of `{git_revision_a}` and `{git_revision_b}`: exit \
+				 code {exit_code}"
+			))
+		}
+	};
+	let merge_base_revisions = if has_merge_base {
+		let mut command = Command::new("git");
+		command
+			.arg("merge-base")
+			.arg("--all")
+			.arg(git_revision_a)
+			.arg(git_revision_b)
+			.current_dir(repo_dir.as_ref());
+		run_command(command)
+			.with_context(|| {
+				format!(
+					"unable to find the merge bases of `{git_revision_a}` and `{git_revision_b}`"
+				)
+			})?
+			.lines()
+			.map(ToOwned::to_owned)
+			.collect::<Vec<_>>()
+	} else {
+		Vec::new()
+	};
+
+	// Get the commit times of the revisions and the merge bases all at once
+	// The output looks like this (without quotes), one line per commit in the
+	// order they were provided: `<HASH>\t<TIMESTAMP>\t<DATE>`
+	let mut command = Command::new("git");
+	command
+		.arg("log")
+		.arg("--no-walk=unsorted")
+		.arg("--date=short")
+		.arg("--format=%H\t%ct\t%cd")
+		.arg(git_revision_a)
+		.arg(git_revision_b)
+		.args(merge_base_revisions.as_slice())
+		.current_dir(repo_dir.as_ref());
+	let commit_times = run_command(command)
+		.with_context(|| "unable to get the commit times")?
+		.lines()
+		.map(|line| {
+			let mut line_parts = line.splitn(3, '\t');
+			match (line_parts.next(), line_parts.next(), line_parts.next()) {
+				(Some(git_revision), Some(timestamp_str), Some(date)) => Ok(MergeBase {
+					git_revision: git_revision.to_owned(),
+					timestamp:    timestamp_str
+						.parse()
+						.with_context(|| "unable to parse the timestamp as an integer")?,
+					date:         date.to_owned(),
+				}),
+				_ => Err(anyhow!("unexpected output from `git log`")),
+			}
+		})
+		.collect::<Result<Vec<_>>>()?;
+	let timestamp_of = |git_revision: &str| {
+		commit_times
+			.iter()
+			.find(|commit_time| commit_time.git_revision == git_revision)
+			.map(|commit_time| commit_time.timestamp)
+			.ok_or_else(|| anyhow!("`git log` left out `{git_revision}`"))
+	};
+	let timestamp_a = timestamp_of(git_revision_a)?;
+	let timestamp_b = timestamp_of(git_revision_b)?;
+
+	let newest_merge_base = commit_times
+		.iter()
+		.filter(|commit_time| merge_base_revisions.contains(&commit_time.git_revision))
+		.max_by_key(|commit_time| commit_time.timestamp)
+		.cloned();
+
+	Ok((newest_merge_base, timestamp_a, timestamp_b))
+}
+
+/// Gets the patch IDs of a set of commits, which are the same for commits that
+/// make the same change (like cherry-picks).
+///
+/// Commits without a patch ID (merges and empty commits) are left out.
+pub fn get_patch_ids<P>(repo_dir: P, git_revisions: &[&str]) -> Result<HashMap<String, String>>
+where
+	P: AsRef<Path>,
+{
+	if git_revisions.is_empty() {
+		return Ok(HashMap::new());
+	}
+
+	// Prepare the `git log` command for collecting the diffs, all at once
+	// The revisions are provided over stdin, since there can be a lot of them
+	let mut command = Command::new("git");
+	command
+		.arg("log")
+		.arg("--stdin")
+		.arg("--no-walk=unsorted")
+		.arg("--no-merges")
+		.arg("--pretty=format:commit %H")
+		.arg("--patch")
+		.current_dir(repo_dir.as_ref());
+	let diffs = run_command_with_input(command, git_revisions.join("\n").as_str())
+		.with_context(|| "unable to get the commit diffs")?;
+
+	// Prepare the `git patch-id` command for processing the diffs
+	let mut command = Command::new("git");
+	command
+		.arg("patch-id")
+		.arg("--stable")
+		.current_dir(repo_dir.as_ref());
+
+	// Run the command
+	// The output looks like this (without quotes): `<PATCH_ID> <REVISION>`
+	let patch_ids = run_command_with_input(command, diffs.as_str())
+		.with_context(|| "unable to get the commit patch IDs")?
+		.lines()
+		.filter_map(|line| line.split_once(' '))
+		.map(|(patch_id, git_revision)| (git_revision.to_owned(), patch_id.to_owned()))
+		.collect();
+
+	Ok(patch_ids)
+}
+
+/// Finds the commits in a set that don't change anything meaningful, because
+/// their changes are only whitespace, or are confined to generated paths.
+///
+/// The generated paths are Git glob pathspecs (like `gen/**` or `*.lock`),
+/// relative to the root of the repo. Merges should be left out of the set,
+/// since `git log` doesn't show a diff for them.
+pub fn get_trivial_commits<P>(
+	repo_dir: P,
+	git_revisions: &[&str],
+	generated_paths: &[String],
+) -> Result<HashSet<String>>
+where
+	P: AsRef<Path>,
+{
+	if git_revisions.is_empty() {
+		return Ok(HashSet::new());
+	}
+
+	// Prepare the `git log` command for collecting the changed files, all at once
+	// Whitespace changes are ignored, and the generated paths are excluded, so a
+	// trivial commit ends up with no changed files at all
+	// The revisions are provided over stdin, since there can be a lot of them
+	let mut command = Command::new("git");
+	command
+		.arg("log")
+		.arg("--stdin")
+		.arg("--no-walk=unsorted")
+		.arg("--pretty=format:commit %H")
+		.arg("--numstat")
+		.arg("--ignore-all-space")
+		.arg("--ignore-blank-lines")
+		.arg("--")
+		.arg(":(top)")
+		.args(
+			generated_paths
+				.iter()
+				.map(|generated_path| format!(":(top,exclude,glob){generated_path}")),
+		)
+		.current_dir(repo_dir);
+	let changed_files_raw = run_command_with_input(command, git_revisions.join("\n").as_str())
+		.with_context(|| "unable to get the files changed by the commits")?;
+
+	// Commits that only touch excluded paths don't show up at all, so everything
+	// starts out as trivial until a changed file is found for it
+	let mut trivial_commits = git_revisions
+		.iter()
+		.map(|git_revision| (*git_revision).to_owned())
+		.collect::<HashSet<_>>();
+	let mut current_git_revision = None;
+	for line in changed_files_raw.lines() {
+		if let Some(git_revision) = line.strip_prefix("commit ") {
+			current_git_revision = Some(git_revision);
+		} else if !line.trim().is_empty() {
+			if let Some(git_revision) = current_git_revision {
+				trivial_commits.remove(git_revision);
+			}
+		}
+	}
+
+	Ok(trivial_commits)
+}
+
+/// Gets the top-level directory that each commit in a set touched the most
+/// files in, like `src/`, so that the commits can be routed by area.
+///
+/// Files at the top of the repo count as `/`, and ties go to the directory
+/// that's first alphabetically. Commits without any changed files (merges and
+/// empty commits) are left out.
+pub fn get_primary_directories<P>(
+	repo_dir: P,
+	git_revisions: &[&str],
+) -> Result<HashMap<String, String>>
+where
+	P: AsRef<Path>,
+{
+	if git_revisions.is_empty() {
+		return Ok(HashMap::new());
+	}
+
+	// Prepare the `git log` command for collecting the changed files, all at once
+	// The revisions are provided over stdin, since there can be a lot of them
+	let mut command = Command::new("git");
+	command
+		.arg("log")
+		.arg("--stdin")
+		.arg("--no-walk=unsorted")
+		.arg("--pretty=format:commit %H")
+		.arg("--name-only")
+		.current_dir(repo_dir);
+	let changed_files_raw = run_command_with_input(command, git_revisions.join("\n").as_str())
+		.with_context(|| "unable to get the files changed by the commits")?;
+
+	// Count the changed files in each top-level directory, per commit
+	let mut directory_counts_per_commit: HashMap<&str, BTreeMap<&str, usize>> = HashMap::new();
+	let mut current_git_revision = None;
+	for line in changed_files_raw.lines() {
+		if let Some(git_revision) = line.strip_prefix("commit ") {
+			current_git_revision = Some(git_revision);
+		} else if !line.trim().is_empty() {
+			if let Some(git_revision) = current_git_revision {
+				let directory = line.split_once('/').map_or("", |(directory, _)| directory);
+				*directory_counts_per_commit
+					.entry(git_revision)
+					.or_default()
+					.entry(directory)
+					.or_default() += 1;
+			}
+		}
+	}
+
+	// `max_by_key()` returns the last of the maximums, so the directories are
+	// walked in reverse to have ties go to the first alphabetically
+	Ok(directory_counts_per_commit
+		.into_iter()
+		.filter_map(|(git_revision, directory_counts)| {
+			directory_counts
+				.into_iter()
+				.rev()
+				.max_by_key(|(_, file_count)| *file_count)
+				.map(|(directory, _)| (git_revision.to_owned(), format!("{directory}/")))
+		})
+		.collect())
+}
+
+/// Collapses commits that make the same change into a single entry, keeping
+/// the first one in the list.
+///
+/// Commits are considered duplicates if their patch IDs match, or failing that,
+/// if their subjects match (since a cherry-pick onto different context can
+/// change the patch ID).
+pub fn collapse_duplicate_commits<'a>(
+	included_commits: &[IncludedCommit<'a>],
+	patch_ids: &HashMap<String, String>,
+) -> Vec<IncludedCommit<'a>> {
+	let mut collapsed_commits: Vec<IncludedCommit> = Vec::with_capacity(included_commits.len());
+	let mut patch_id_entries = HashMap::new();
+	let mut subject_entries = HashMap::new();
+	for included_commit in included_commits {
+		let patch_id = patch_ids.get(included_commit.commit.git_revision.as_str());
+		let subject = included_commit.commit.subject.as_str();
+		let existing_entry = patch_id
+			.and_then(|patch_id| patch_id_entries.get(patch_id))
+			.or_else(|| {
+				(!subject.trim().is_empty())
+					.then(|| subject_entries.get(subject))
+					.flatten()
+			})
+			.copied();
+
+		let entry = if let Some(entry) = existing_entry {
+			let collapsed_commit: &mut IncludedCommit = &mut collapsed_commits[entry];
+			collapsed_commit
+				.duplicate_commits
+				.push(included_commit.commit);
+			for linked_commit in &included_commit.linked_commits {
+				if !collapsed_commit.linked_commits.contains(linked_commit) {
+					collapsed_commit.linked_commits.push(linked_commit.clone());
+				}
+			}
+			entry
+		} else {
+			collapsed_commits.push(included_commit.clone());
+			collapsed_commits.len() - 1
+		};
+
+		// Record both keys, so that a later commit can match on either of them
+		if let Some(patch_id) = patch_id {
+			patch_id_entries.entry(patch_id).or_insert(entry);
+		}
+		subject_entries.entry(subject).or_insert(entry);
+	}
+
+	collapsed_commits
+}
+
 pub fn get_search_results<'a, P>(
 	index: &Index<'a>,
 	repo_dir: P,
 	revspec: &str,
 	include_merge_commits: bool,
 	affected_filepaths: &[String],
+	link_traversal: LinkTraversal,
+	only_link_merges: bool,
 ) -> Result<Vec<IncludedCommit<'a>>>
 where
 	P: AsRef<Path>,
@@ -73,19 +877,34 @@ where
 
 	// Run the command
 	let commit_list_raw = run_command(command).with_context(|| "unable to get the repo log")?;
+	// Commits can only be missing from the index if it was limited to some refs, in
+	// which case the ones outside of them are left out
+	let mut out_of_scope_commit_count = 0;
 	let commit_list = commit_list_raw
 		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty())
 		.filter_map(|line| {
-			let line = line.trim();
-			(!line.is_empty()).then(|| {
-				index
-					.lookup_git_revision(line)
-					.expect("all commits returned as search results should be in the index")
-			})
+			let commit = index.lookup_full_git_revision(line);
+			if commit.is_none() {
+				out_of_scope_commit_count += 1;
+			}
+			commit
 		})
 		.collect::<Vec<_>>();
+	if out_of_scope_commit_count > 0 {
+		eprintln!(
+			"[WARNING] {out_of_scope_commit_count} commit(s) in the results aren't on any of the \
+			 refs in scope, so they were left out."
+		);
+	}
 
-	build_commit_inclusion_tree(index, commit_list.as_slice(), true, false)
+	build_commit_inclusion_tree(
+		index,
+		commit_list.as_slice(),
+		link_traversal,
+		only_link_merges,
+	)
 }
 
 pub fn get_branches_containing<P>(
@@ -110,15 +929,48 @@ where
 	// Run the command
 	let branch_list_raw = run_command(command)
 		.with_context(|| format!("unable to get the branches that contain {commit_revision}"))?;
-	let branch_list = branch_list_raw
-		.lines()
-		.filter_map(|line| {
-			let line = line.trim();
-			(!line.is_empty()).then(|| line.to_owned())
-		})
-		.collect::<Vec<_>>();
 
-	Ok(branch_list)
+	Ok(parse_branch_list(branch_list_raw.as_str(), local_branches))
+}
+
+/// Parses the output of `git branch`, with one branch per line.
+///
+/// Depending on the Git version and configuration, remote branches can be
+/// listed with a leading `remotes/`, so it's stripped to keep the same branch
+/// from showing up under two names.
+fn parse_branch_list(branch_list_raw: &str, local_branches: bool) -> Vec<String> {
+	let mut branch_list = LinkedHashSet::new();
+	for line in branch_list_raw.lines() {
+		let line = line.trim();
+		if line.is_empty() {
+			continue;
+		}
+		branch_list.insert_if_absent(if local_branches {
+			line.to_owned()
+		} else {
+			normalize_remote_branch_name(line)
+		});
+	}
+
+	branch_list.into_iter().collect()
+}
+
+/// Strips the leading `remotes/` from a remote branch name as listed by `git
+/// branch`, including both sides of a symbolic branch like `origin/HEAD ->
+/// origin/main`.
+///
+/// Any marker in front of the name (like the `*` for the current branch) is
+/// kept.
+pub fn normalize_remote_branch_name(branch: &str) -> String {
+	let name_start = branch.len() - branch.trim_start_matches(['*', '+', ' ']).len();
+	let (marker, name) = branch.split_at(name_start);
+	let name = name
+		.split(" -> ")
+		.map(|side| side.strip_prefix("remotes/").unwrap_or(side))
+		.collect::<Vec<_>>()
+		.join(" -> ");
+
+	format!("{marker}{name}")
 }
 
 pub fn get_tags_containing<P>(repo_dir: P, commit_revision: &str) -> Result<Vec<String>>
@@ -147,10 +999,495 @@ where
 	Ok(tag_list)
 }
 
+/// Gets the tips of the branches, named the same way `git branch` lists them,
+/// so that the results of both containment strategies line up.
+///
+/// That means the current branch is prefixed with `* `, and symbolic branches
+/// look like `origin/HEAD -> origin/main`.
+pub fn get_branch_tips_for_containment<P>(
+	repo_dir: P,
+	local_branches: bool,
+) -> Result<Vec<(String, String)>>
+where
+	P: AsRef<Path>,
+{
+	// Prepare the `git for-each-ref` command for collecting the tips
+	// `lstrip` is used instead of `short` because the latter shortens
+	// `origin/HEAD` to just `origin`
+	let mut command = Command::new("git");
+	command
+		.arg("for-each-ref")
+		.arg("--format=%(HEAD)\t%(refname:lstrip=2)\t%(objectname)\t%(symref:lstrip=2)")
+		.arg(if local_branches {
+			"refs/heads"
+		} else {
+			"refs/remotes"
+		})
+		.current_dir(repo_dir);
+
+	// Run the command
+	// The output looks like this (without quotes):
+	// `<*>\t<BRANCH>\t<REVISION>\t<SYMREF>`
+	let branch_list_raw = run_command(command).with_context(|| "unable to get the branch tips")?;
+	let branch_tips = branch_list_raw
+		.lines()
+		.filter_map(|line| {
+			let mut line_parts = line.split('\t');
+			match (
+				line_parts.next(),
+				line_parts.next(),
+				line_parts.next(),
+				line_parts.next(),
+			) {
+				(Some(head), Some(branch), Some(git_revision), Some(symref)) => {
+					let branch_display = match (head, symref) {
+						("*", _) => format!("* {branch}"),
+						(_, "") => branch.to_owned(),
+						(_, symref) => format!("{branch} -> {symref}"),
+					};
+					Some((branch_display, git_revision.to_owned()))
+				}
+				_ => None,
+			}
+		})
+		.collect();
+
+	Ok(branch_tips)
+}
+
+/// Gets the tips of the tags. Annotated tags are left as the hash of the tag
+/// object, since the index can follow those to the commits they point to.
+pub fn get_tag_tips<P>(repo_dir: P) -> Result<Vec<(String, String)>>
+where
+	P: AsRef<Path>,
+{
+	// Prepare the `git for-each-ref` command for collecting the tips
+	let mut command = Command::new("git");
+	command
+		.arg("for-each-ref")
+		.arg("--format=%(refname:lstrip=2)\t%(objectname)")
+		.arg("refs/tags")
+		.current_dir(repo_dir);
+
+	// Run the command
+	// The output looks like this (without quotes): `<TAG>\t<REVISION>`
+	let tag_list_raw = run_command(command).with_context(|| "unable to get the tag tips")?;
+	let tag_tips = tag_list_raw
+		.lines()
+		.filter_map(|line| {
+			line.split_once('\t')
+				.map(|(tag, git_revision)| (tag.to_owned(), git_revision.to_owned()))
+		})
+		.collect();
+
+	Ok(tag_tips)
+}
+
+/// Finds the locations that contain each of the target commits, given the
+/// tips of those locations.
+///
+/// Rather than asking Git once per commit, this walks the commit graph a single
+/// time, remembering which of the targets are reachable from each commit along
+/// the way. Parents that aren't in the index (like ones outside the refs in
+/// scope) end the walk along that line, and tips that aren't in the index are
+/// skipped entirely.
+pub fn get_locations_containing<'a>(
+	index: &Index<'a>,
+	location_tips: &[(String, String)],
+	target_commits: &[&'a Commit],
+) -> HashMap<&'a Commit, Vec<String>> {
+	// The same commit can be a target more than once, but it only needs one
+	// position
+	let mut target_positions = HashMap::new();
+	for commit in target_commits {
+		let next_position = target_positions.len();
+		target_positions.entry(*commit).or_insert(next_position);
+	}
+	let bitset_length = target_positions.len().div_ceil(u64::BITS as usize);
+
+	// The targets reachable from each visited commit, as a bitset of their
+	// positions
+	let mut reachable_targets: HashMap<&Commit, Vec<u64>> = HashMap::new();
+	let mut locations_per_commit: HashMap<&Commit, Vec<String>> = HashMap::new();
+	for (location, git_revision) in location_tips {
+		// Tag tips can be annotated tags, which need the full lookup
+		let Ok(tip_commit) = git_revision
+			.parse::<CommitId>()
+			.and_then(|commit_id| index.lookup_git_revision(&commit_id))
+		else {
+			continue;
+		};
+
+		// Visit the ancestors depth-first, only settling a commit once all of its
+		// parents have been settled
+		let mut commits_to_visit = vec![(tip_commit, false)];
+		while let Some((commit, parents_settled)) = commits_to_visit.pop() {
+			if reachable_targets.contains_key(commit) {
+				continue;
+			}
+
+			let parent_commits = commit
+				.parent_revisions
+				.iter()
+				.filter_map(|parent_revision| index.lookup_full_git_revision(parent_revision));
+			if parents_settled {
+				let mut reachable_from_commit = vec![0_u64; bitset_length];
+				if let Some(position) = target_positions.get(commit) {
+					reachable_from_commit[position / u64::BITS as usize] |=
+						1 << (position % u64::BITS as usize);
+				}
+				for parent_commit in parent_commits {
+					for (bits, parent_bits) in reachable_from_commit
+						.iter_mut()
+						.zip(&reachable_targets[parent_commit])
+					{
+						*bits |= parent_bits;
+					}
+				}
+				reachable_targets.insert(commit, reachable_from_commit);
+			} else {
+				commits_to_visit.push((commit, true));
+				commits_to_visit.extend(
+					parent_commits
+						.filter(|parent_commit| !reachable_targets.contains_key(parent_commit))
+						.map(|parent_commit| (parent_commit, false)),
+				);
+			}
+		}
+
+		// Record the location for every target its tip can reach
+		for (target_commit, position) in &target_positions {
+			if reachable_targets[tip_commit][position / u64::BITS as usize]
+				& (1 << (position % u64::BITS as usize))
+				!= 0
+			{
+				locations_per_commit
+					.entry(*target_commit)
+					.or_default()
+					.push(location.clone());
+			}
+		}
+	}
+
+	locations_per_commit
+}
+
+/// Groups the branches and tags by the set of commits they contain, returning
+/// each set with its branches and tags.
+///
+/// The locations in each set are sorted by name. The sets are ordered by how
+/// many locations they have (most first), then by the first of their locations
+/// alphabetically, then by their commit hashes, so that the order never
+/// depends on `HashMap` iteration order.
+pub fn group_locations_by_commit_set<'a>(
+	commits_per_branch: HashMap<String, Vec<&'a Commit>>,
+	commits_per_tag: HashMap<String, Vec<&'a Commit>>,
+) -> Vec<LocationSet<'a>> {
+	let mut locations_per_commit_set: HashMap<Vec<&Commit>, (Vec<String>, Vec<String>)> =
+		HashMap::new();
+	for (branch, commit_set) in commits_per_branch {
+		locations_per_commit_set
+			.entry(commit_set)
+			.and_modify(|(branch_list, _)| branch_list.push(branch.clone()))
+			.or_insert_with(|| (vec![branch], Vec::new()));
+	}
+	for (tag, commit_set) in commits_per_tag {
+		locations_per_commit_set
+			.entry(commit_set)
+			.and_modify(|(_, tag_list)| tag_list.push(tag.clone()))
+			.or_insert_with(|| (Vec::new(), vec![tag]));
+	}
+
+	let mut locations_per_commit_set_ordered = locations_per_commit_set
+		.into_iter()
+		.map(|(commit_set, (mut branch_list, mut tag_list))| {
+			branch_list.sort();
+			tag_list.sort();
+			(commit_set, (branch_list, tag_list))
+		})
+		.collect::<Vec<_>>();
+	locations_per_commit_set_ordered.sort_by_cached_key(|(commit_set, (branch_list, tag_list))| {
+		let mut commit_hashes = commit_set
+			.iter()
+			.map(|commit| commit.git_revision.clone())
+			.collect::<Vec<_>>();
+		commit_hashes.sort_unstable();
+		(
+			Reverse(branch_list.len() + tag_list.len()),
+			branch_list.iter().chain(tag_list).min().cloned(),
+			commit_hashes,
+		)
+	});
+
+	locations_per_commit_set_ordered
+}
+
+/// Checks whether any branch or tag contains a commit.
+///
+/// Commits that aren't on any ref are ones only kept alive by the reflog or
+/// similar, which makes them "ghost" commits as far as the results are
+/// concerned.
+pub fn is_on_any_ref<P>(repo_dir: P, commit_revision: &str) -> Result<bool>
+where
+	P: AsRef<Path>,
+{
+	// Prepare the `git for-each-ref` command for the search
+	// Only one matching ref is needed to answer the question
+	let mut command = Command::new("git");
+	command
+		.arg("for-each-ref")
+		.arg("--count=1")
+		.arg("--format=%(refname)")
+		.arg("--contains")
+		.arg(commit_revision)
+		.arg("refs/heads")
+		.arg("refs/remotes")
+		.arg("refs/tags")
+		.current_dir(repo_dir);
+
+	// Run the command
+	let ref_list_raw = run_command(command)
+		.with_context(|| format!("unable to get the refs that contain {commit_revision}"))?;
+
+	Ok(ref_list_raw.lines().any(|line| !line.trim().is_empty()))
+}
+
+/// Removes commits that aren't on any ref from an inclusion tree, along with
+/// everything nested beneath them.
+///
+/// The results of each containment check are cached in `ref_cache`, since the
+/// same commit can show up in several places.
+pub fn remove_unreferenced_commits<'a, P>(
+	repo_dir: P,
+	inclusion_tree: Vec<IncludedCommit<'a>>,
+	ref_cache: &mut HashMap<&'a str, bool>,
+) -> Result<Vec<IncludedCommit<'a>>>
+where
+	P: AsRef<Path>,
+{
+	let mut filtered_tree = Vec::with_capacity(inclusion_tree.len());
+	for mut included_commit in inclusion_tree {
+		let git_revision = included_commit.commit.git_revision.as_str();
+		let is_referenced = if let Some(is_referenced) = ref_cache.get(git_revision) {
+			*is_referenced
+		} else {
+			let is_referenced = is_on_any_ref(repo_dir.as_ref(), git_revision)?;
+			ref_cache.insert(git_revision, is_referenced);
+			is_referenced
+		};
+		if !is_referenced {
+			continue;
+		}
+
+		included_commit.linked_commits = remove_unreferenced_commits(
+			repo_dir.as_ref(),
+			included_commit.linked_commits,
+			ref_cache,
+		)?;
+		filtered_tree.push(included_commit);
+	}
+
+	Ok(filtered_tree)
+}
+
+/// Gets the commit at the tip of each branch, keyed by the same short names
+/// `git branch` uses.
+///
+/// Symbolic refs (like `origin/HEAD`) are left out, since they're just aliases
+/// of other branches.
+pub fn get_branch_tips<P>(repo_dir: P, local_branches: bool) -> Result<HashMap<String, String>>
+where
+	P: AsRef<Path>,
+{
+	// Prepare the `git for-each-ref` command for collecting the tips
+	let mut command = Command::new("git");
+	command
+		.arg("for-each-ref")
+		.arg("--format=%(refname:short)\t%(objectname)\t%(symref)")
+		.arg(if local_branches {
+			"refs/heads"
+		} else {
+			"refs/remotes"
+		})
+		.current_dir(repo_dir);
+
+	// Run the command
+	// The output looks like this (without quotes): `<BRANCH>\t<REVISION>\t<SYMREF>`
+	let branch_list_raw = run_command(command).with_context(|| "unable to get the branch tips")?;
+	let branch_tips = branch_list_raw
+		.lines()
+		.filter_map(|line| {
+			let mut line_parts = line.split('\t');
+			match (line_parts.next(), line_parts.next(), line_parts.next()) {
+				(Some(branch), Some(git_revision), Some("")) => {
+					Some((branch.to_owned(), git_revision.to_owned()))
+				}
+				_ => None,
+			}
+		})
+		.collect();
+
+	Ok(branch_tips)
+}
+
+/// Gets the committer dates of the branch tips, like `2024-03-10`, keyed by
+/// branch name the same way `git branch` lists them.
+pub fn get_branch_tip_dates<P>(repo_dir: P, local_branches: bool) -> Result<HashMap<String, String>>
+where
+	P: AsRef<Path>,
+{
+	// Prepare the `git for-each-ref` command for collecting the dates
+	// `lstrip` is used instead of `short` because the latter shortens
+	// `origin/HEAD` to just `origin`
+	let mut command = Command::new("git");
+	command
+		.arg("for-each-ref")
+		.arg("--format=%(refname:lstrip=2)\t%(committerdate:short)")
+		.arg(if local_branches {
+			"refs/heads"
+		} else {
+			"refs/remotes"
+		})
+		.current_dir(repo_dir);
+
+	// Run the command
+	let branch_list_raw =
+		run_command(command).with_context(|| "unable to get the branch tip dates")?;
+
+	Ok(parse_branch_tip_dates(branch_list_raw.as_str()))
+}
+
+/// Parses the output of `git for-each-ref` with a format of
+/// `%(refname:lstrip=2)\t%(committerdate:short)`.
+///
+/// The output looks like this (without quotes): `<BRANCH>\t<DATE>`
+///
+/// Lines without a branch or a date like `2024-03-10` are skipped, so that
+/// nothing unexpected ends up displayed as a date.
+pub fn parse_branch_tip_dates(branch_list_raw: &str) -> HashMap<String, String> {
+	branch_list_raw
+		.lines()
+		.filter_map(|line| {
+			line.split_once('\t')
+				.filter(|(branch, date)| !branch.is_empty() && is_short_date(date))
+				.map(|(branch, date)| (branch.to_owned(), date.to_owned()))
+		})
+		.collect()
+}
+
+/// Whether a string is a date in the format Git's `short` dates use, like
+/// `2024-03-10`.
+fn is_short_date(date: &str) -> bool {
+	date.len() == 10
+		&& date.bytes().enumerate().all(|(index, byte)| match index {
+			4 | 7 => byte == b'-',
+			_ => byte.is_ascii_digit(),
+		})
+}
+
+/// Groups branches that point to the same commit, so that they can be listed
+/// once.
+///
+/// Returns the branches each representative branch stands in for, keyed by the
+/// representative. Branches on `origin` are preferred as representatives, then
+/// the first alphabetically.
+pub fn group_identical_branches(branch_tips: &HashMap<String, String>) -> HashMap<&str, Vec<&str>> {
+	let mut branches_per_tip: HashMap<&str, Vec<&str>> = HashMap::new();
+	for (branch, git_revision) in branch_tips {
+		branches_per_tip
+			.entry(git_revision.as_str())
+			.or_default()
+			.push(branch.as_str());
+	}
+
+	branches_per_tip
+		.into_values()
+		.map(|mut branches| {
+			branches.sort_unstable_by_key(|branch| (!branch.starts_with("origin/"), *branch));
+			let representative = branches.remove(0);
+			(representative, branches)
+		})
+		.collect()
+}
+
+pub fn get_remotes<P>(repo_dir: P) -> Result<Vec<String>>
+where
+	P: AsRef<Path>,
+{
+	// Prepare the `git remote` command for listing the remotes
+	let mut command = Command::new("git");
+	command.arg("remote").current_dir(repo_dir);
+
+	// Run the command
+	let remote_list_raw =
+		run_command(command).with_context(|| "unable to get the list of remotes")?;
+	let remote_list = remote_list_raw
+		.lines()
+		.filter_map(|line| {
+			let line = line.trim();
+			(!line.is_empty()).then(|| line.to_owned())
+		})
+		.collect::<Vec<_>>();
+
+	Ok(remote_list)
+}
+
+/// Strips the leading `<remote>/` from each remote branch name in a set.
+///
+/// The remote is matched against the actual list of remotes instead of just
+/// splitting on the first `/`, so that both remote names and branch names
+/// containing slashes survive intact. If stripping would make two branches in
+/// the set indistinguishable (`origin/foo` and `mirror/foo`), both are left
+/// fully-qualified. Both sides of a symbolic branch like `origin/HEAD ->
+/// origin/main` are stripped.
+pub fn strip_remote_prefixes(branch_list: &[String], remotes: &[String]) -> Vec<String> {
+	let strip_remote_prefix = |branch: &str| -> String {
+		// Use the longest matching remote, in case one remote name is a prefix of
+		// another
+		remotes
+			.iter()
+			.filter_map(|remote| {
+				branch
+					.strip_prefix(remote.as_str())
+					.and_then(|rest| rest.strip_prefix('/'))
+					.map(|stripped| (remote.len(), stripped))
+			})
+			.max_by_key(|&(remote_length, _)| remote_length)
+			.map_or(branch, |(_, stripped)| stripped)
+			.to_owned()
+	};
+	let stripped_branch_list = branch_list
+		.iter()
+		.map(|branch| {
+			branch
+				.split(" -> ")
+				.map(strip_remote_prefix)
+				.collect::<Vec<_>>()
+				.join(" -> ")
+		})
+		.collect::<Vec<_>>();
+
+	stripped_branch_list
+		.iter()
+		.zip(branch_list)
+		.map(|(stripped, branch)| {
+			let is_ambiguous = stripped_branch_list
+				.iter()
+				.filter(|other| *other == stripped)
+				.count() > 1;
+			if is_ambiguous {
+				branch.clone()
+			} else {
+				stripped.clone()
+			}
+		})
+		.collect()
+}
+
 pub fn build_commit_inclusion_tree<'a>(
 	index: &Index<'a>,
 	commit_list: &[&'a Commit],
-	traverse_forward_references: bool,
+	link_traversal: LinkTraversal,
 	only_consider_likely_merges: bool,
 ) -> Result<Vec<IncludedCommit<'a>>> {
 	// This exists to prevent circular references and processing the same commit
@@ -178,7 +1515,7 @@ pub fn build_commit_inclusion_tree<'a>(
 			visit_commit(
 				index,
 				&mut visited_commits,
-				traverse_forward_references,
+				link_traversal,
 				only_consider_likely_merges,
 				false,
 				commit,
@@ -191,14 +1528,38 @@ pub fn build_commit_inclusion_tree<'a>(
 	Ok(included_commits)
 }
 
-pub fn flatten_inclusion_tree<'a>(inclusion_tree: &[IncludedCommit<'a>]) -> Vec<&'a Commit> {
+/// Flattens an inclusion tree into a list of unique commits, breadth-first.
+///
+/// The commits are in the order they're first visited, so the top-level
+/// commits always come first, and each commit is only listed where it was
+/// first found.
+pub fn flatten_inclusion_tree<'a>(
+	inclusion_tree: &[IncludedCommit<'a>],
+) -> Vec<FlattenedCommit<'a>> {
 	let mut flattened_commit_list = Vec::new();
+	let mut visited_commits = HashSet::new();
 	let mut commits_to_visit = VecDeque::new();
-	commits_to_visit.extend(inclusion_tree);
+	commits_to_visit.extend(
+		inclusion_tree
+			.iter()
+			.map(|included_commit| (included_commit, 0, included_commit.commit)),
+	);
 
-	while let Some(included_commit) = commits_to_visit.pop_front() {
-		flattened_commit_list.push(included_commit.commit);
-		commits_to_visit.extend(&included_commit.linked_commits);
+	while let Some((included_commit, depth, seed)) = commits_to_visit.pop_front() {
+		if !visited_commits.insert(included_commit.commit.git_revision.as_str()) {
+			continue;
+		}
+		flattened_commit_list.push(FlattenedCommit {
+			commit: included_commit.commit,
+			depth,
+			seed,
+		});
+		commits_to_visit.extend(
+			included_commit
+				.linked_commits
+				.iter()
+				.map(|linked_commit| (linked_commit, depth + 1, seed)),
+		);
 	}
 
 	flattened_commit_list
@@ -207,7 +1568,7 @@ pub fn flatten_inclusion_tree<'a>(inclusion_tree: &[IncludedCommit<'a>]) -> Vec<
 fn visit_commit<'a>(
 	index: &Index<'a>,
 	visited_commits: &mut HashSet<&'a str>,
-	traverse_forward_references: bool,
+	link_traversal: LinkTraversal,
 	only_consider_likely_merges: bool,
 	recursion_has_happened: bool,
 	commit: &'a Commit,
@@ -218,11 +1579,11 @@ fn visit_commit<'a>(
 		return Ok(None);
 	}
 
-	// Process all forward references of the commit
-	let raw_references = if traverse_forward_references {
-		index.get_commit_forward_references(commit)
-	} else {
-		index.get_commit_backward_references(commit)
+	// Process all references of the commit in the traversal direction
+	let raw_references = match link_traversal {
+		LinkTraversal::Forward => index.get_commit_forward_references(commit),
+		LinkTraversal::Backward => index.get_commit_backward_references(commit),
+		LinkTraversal::None => Vec::new(),
 	};
 	let linked_commits = raw_references
 		.iter()
@@ -233,7 +1594,7 @@ fn visit_commit<'a>(
 			visit_commit(
 				index,
 				visited_commits,
-				traverse_forward_references,
+				link_traversal,
 				only_consider_likely_merges,
 				true,
 				referenced_commit,
@@ -246,5 +1607,577 @@ fn visit_commit<'a>(
 	Ok(Some(IncludedCommit {
 		commit,
 		linked_commits,
+		duplicate_commits: Vec::new(),
 	}))
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		collection::{get_complete_commit_list, TicketParsingOptions},
+		index::ALL_LINK_KINDS,
+		test_util::{commit, included, FixtureRepo},
+	};
+
+	fn to_strings(strs: &[&str]) -> Vec<String> {
+		strs.iter().map(|s| (*s).to_owned()).collect()
+	}
+
+	#[test]
+	fn strip_remote_prefixes_strips_unique_names() {
+		let remotes = to_strings(&["origin", "mirror"]);
+		let branch_list = to_strings(&[
+			"origin/main",
+			"origin/release/customer-x/2024.03-hotfix",
+			"mirror/feature",
+		]);
+
+		assert_eq!(
+			strip_remote_prefixes(&branch_list, &remotes),
+			to_strings(&["main", "release/customer-x/2024.03-hotfix", "feature"])
+		);
+	}
+
+	#[test]
+	fn strip_remote_prefixes_keeps_ambiguous_names_qualified() {
+		let remotes = to_strings(&["origin", "mirror"]);
+		let branch_list = to_strings(&["origin/foo", "mirror/foo", "origin/bar"]);
+
+		assert_eq!(
+			strip_remote_prefixes(&branch_list, &remotes),
+			to_strings(&["origin/foo", "mirror/foo", "bar"])
+		);
+	}
+
+	#[test]
+	fn strip_remote_prefixes_uses_the_actual_remotes() {
+		// A remote with a slash in its name, and one that's a prefix of another
+		let remotes = to_strings(&["team/upstream", "up", "upstream"]);
+		let branch_list = to_strings(&["team/upstream/main", "upstream/dev", "other/branch"]);
+
+		assert_eq!(
+			strip_remote_prefixes(&branch_list, &remotes),
+			to_strings(&["main", "dev", "other/branch"])
+		);
+	}
+
+	#[test]
+	fn strip_remote_prefixes_handles_symbolic_branches() {
+		let remotes = to_strings(&["origin"]);
+		let branch_list = to_strings(&["origin/HEAD -> origin/main", "origin/main"]);
+
+		assert_eq!(
+			strip_remote_prefixes(&branch_list, &remotes),
+			to_strings(&["HEAD -> main", "main"])
+		);
+	}
+
+	/// Boils a flattened tree down to each commit's hash, depth, and seed hash.
+	fn summarize_flattened(flattened: Vec<FlattenedCommit>) -> Vec<(&str, usize, &str)> {
+		flattened
+			.into_iter()
+			.map(|flattened_commit| {
+				(
+					flattened_commit.commit.git_revision.as_str(),
+					flattened_commit.depth,
+					flattened_commit.seed.git_revision.as_str(),
+				)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn flatten_inclusion_tree_lists_diamond_commits_once() {
+		// `a` links to `b` and `c`, which both link to `d`
+		let a = commit("a", &[]);
+		let b = commit("b", &[]);
+		let c = commit("c", &[]);
+		let d = commit("d", &[]);
+		let inclusion_tree = vec![included(
+			&a,
+			vec![
+				included(&b, vec![included(&d, Vec::new())]),
+				included(&c, vec![included(&d, Vec::new())]),
+			],
+		)];
+
+		assert_eq!(
+			summarize_flattened(flatten_inclusion_tree(inclusion_tree.as_slice())),
+			vec![("a", 0, "a"), ("b", 1, "a"), ("c", 1, "a"), ("d", 2, "a")]
+		);
+	}
+
+	#[test]
+	fn flatten_inclusion_tree_keeps_the_shallowest_seed() {
+		// `d` is two links under `a`, but only one under `e`, so it belongs to `e`
+		// even though `a` comes first
+		let a = commit("a", &[]);
+		let b = commit("b", &[]);
+		let d = commit("d", &[]);
+		let e = commit("e", &[]);
+		let inclusion_tree = vec![
+			included(&a, vec![included(&b, vec![included(&d, Vec::new())])]),
+			included(&e, vec![included(&d, Vec::new())]),
+		];
+
+		assert_eq!(
+			summarize_flattened(flatten_inclusion_tree(inclusion_tree.as_slice())),
+			vec![("a", 0, "a"), ("e", 0, "e"), ("b", 1, "a"), ("d", 1, "e")]
+		);
+	}
+
+	/// Builds a repo with a merged feature branch, a cherry-pick onto a release
+	/// branch, and a branch without any commits of its own.
+	fn containment_fixture_repo(name: &str) -> FixtureRepo {
+		let fixture_repo = FixtureRepo::new(name);
+		let initial_revision = fixture_repo.commit("Initial commit");
+		fixture_repo.git(&["branch", "feature"]);
+		fixture_repo.commit("PROJ-1: Work on main");
+		fixture_repo.git(&["branch", "no-new-commits"]);
+
+		fixture_repo.git(&["checkout", "--quiet", "feature"]);
+		let feature_revision = fixture_repo.commit("PROJ-2: Add the feature");
+		fixture_repo.commit("PROJ-2: Finish the feature");
+		fixture_repo.git(&["checkout", "--quiet", "main"]);
+		fixture_repo.git(&[
+			"merge",
+			"--quiet",
+			"--no-ff",
+			"--message",
+			"Merge feature",
+			"feature",
+		]);
+
+		fixture_repo.git(&[
+			"checkout",
+			"--quiet",
+			"-b",
+			"release",
+			initial_revision.as_str(),
+		]);
+		fixture_repo.git(&[
+			"cherry-pick",
+			"--allow-empty",
+			"-x",
+			feature_revision.as_str(),
+		]);
+		fixture_repo.git(&["checkout", "--quiet", "main"]);
+
+		fixture_repo
+	}
+
+	/// Checks that both containment strategies find the same branches for every
+	/// commit in the repo.
+	fn assert_containment_strategies_agree(repo_dir: &str, local_branches: bool) {
+		let commits =
+			get_complete_commit_list(repo_dir, &TicketParsingOptions::ignored(), &[], false)
+				.expect("unable to get the commits");
+		let index =
+			Index::new(commits.as_slice(), &[], ALL_LINK_KINDS).expect("unable to build the index");
+		let target_commits = commits.iter().collect::<Vec<_>>();
+
+		let branch_tips = get_branch_tips_for_containment(repo_dir, local_branches)
+			.expect("unable to get the branch tips");
+		let branches_per_commit =
+			get_locations_containing(&index, branch_tips.as_slice(), target_commits.as_slice());
+
+		for commit in &commits {
+			let mut git_branches =
+				get_branches_containing(repo_dir, commit.git_revision.as_str(), local_branches)
+					.expect("unable to get the branches containing the commit");
+			let mut graph_branches = branches_per_commit.get(commit).cloned().unwrap_or_default();
+			git_branches.sort_unstable();
+			graph_branches.sort_unstable();
+
+			assert!(!git_branches.is_empty());
+			assert_eq!(graph_branches, git_branches, "{}", commit.subject);
+		}
+	}
+
+	#[test]
+	fn containment_strategies_agree_on_local_branches() {
+		let fixture_repo = containment_fixture_repo("containment-local");
+
+		assert_containment_strategies_agree(fixture_repo.path_str(), true);
+	}
+
+	#[test]
+	fn containment_strategies_agree_on_remote_branches() {
+		let origin_repo = containment_fixture_repo("containment-origin");
+		let fixture_repo = FixtureRepo::clone_of(&origin_repo, "containment-remote");
+
+		assert_containment_strategies_agree(fixture_repo.path_str(), false);
+	}
+
+	#[test]
+	fn branch_tip_dates_are_parsed() {
+		// Captured from `git for-each-ref`, with some lines that have to be skipped
+		let branch_list_raw = [
+			"origin/HEAD\t2024-03-10",
+			"origin/main\t2024-03-10",
+			"",
+			"origin/release/1.2\t2023-11-02",
+			"origin/no-date\t",
+			"origin/bad-date\tlast Tuesday",
+			"origin/long-date\t2024-03-10 12:00:00",
+			"\t2024-03-10",
+			"origin/no-tab 2024-03-10",
+		]
+		.join("\n");
+
+		let branch_tip_dates = parse_branch_tip_dates(branch_list_raw.as_str());
+
+		assert_eq!(
+			branch_tip_dates,
+			HashMap::from([
+				("origin/HEAD".to_owned(), "2024-03-10".to_owned()),
+				("origin/main".to_owned(), "2024-03-10".to_owned()),
+				("origin/release/1.2".to_owned(), "2023-11-02".to_owned()),
+			])
+		);
+		assert!(parse_branch_tip_dates("").is_empty());
+		assert!(parse_branch_tip_dates("\n\n").is_empty());
+	}
+
+	#[test]
+	fn branch_tip_dates_include_symbolic_branches() {
+		let origin_repo = FixtureRepo::new("tip-dates-origin");
+		origin_repo.commit("Initial commit");
+		let fixture_repo = FixtureRepo::clone_of(&origin_repo, "tip-dates");
+
+		let branch_tip_dates = get_branch_tip_dates(fixture_repo.path(), false)
+			.expect("unable to get the branch tip dates");
+
+		assert_eq!(
+			branch_tip_dates,
+			HashMap::from([
+				("origin/HEAD".to_owned(), "2024-03-10".to_owned()),
+				("origin/main".to_owned(), "2024-03-10".to_owned()),
+			])
+		);
+	}
+
+	#[test]
+	fn latest_tag_is_found_from_a_detached_head() {
+		let fixture_repo = FixtureRepo::new("latest-tag-detached");
+		fixture_repo.commit("Initial commit");
+		fixture_repo.git(&["tag", "v1.0.0"]);
+		let detached_revision = fixture_repo.commit("Add a feature");
+		fixture_repo.commit("Add another feature");
+		fixture_repo.git(&[
+			"checkout",
+			"--quiet",
+			"--detach",
+			detached_revision.as_str(),
+		]);
+
+		assert_eq!(
+			get_head_state(fixture_repo.path()).expect("unable to get the state of HEAD"),
+			HeadState::Detached(detached_revision)
+		);
+		assert_eq!(
+			get_latest_tag(fixture_repo.path()).expect("unable to get the latest tag"),
+			Some("v1.0.0".to_owned())
+		);
+	}
+
+	#[test]
+	fn latest_tag_is_none_without_tags() {
+		let fixture_repo = FixtureRepo::new("latest-tag-none");
+		fixture_repo.commit("Initial commit");
+
+		assert_eq!(
+			get_latest_tag(fixture_repo.path()).expect("unable to get the latest tag"),
+			None
+		);
+	}
+
+	#[test]
+	fn latest_tag_explains_an_unborn_head() {
+		let fixture_repo = FixtureRepo::new("latest-tag-unborn");
+
+		let error = get_latest_tag(fixture_repo.path())
+			.expect_err("an unborn HEAD shouldn't look like a repo without tags");
+
+		assert_eq!(
+			format!("{error:#}"),
+			"there's no tag reachable from HEAD: HEAD is on `main`, which doesn't have any \
+			 commits yet; pass an explicit ref"
+		);
+	}
+
+	/// Builds a map of locations to the commits they contain, looking the
+	/// commits up by hash.
+	fn location_map<'a>(
+		commits: &'a [Commit],
+		locations: &[(&str, &[&str])],
+	) -> HashMap<String, Vec<&'a Commit>> {
+		locations
+			.iter()
+			.map(|(location, git_revisions)| {
+				let commit_set = git_revisions
+					.iter()
+					.map(|git_revision| {
+						commits
+							.iter()
+							.find(|commit| commit.git_revision == *git_revision)
+							.expect("the commit exists")
+					})
+					.collect();
+				((*location).to_owned(), commit_set)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn location_sets_have_a_total_order() {
+		let commits = ["a", "b", "c", "d", "e", "f", "g"]
+			.iter()
+			.map(|git_revision| commit(git_revision, &[]))
+			.collect::<Vec<_>>();
+		let branches: [(&str, &[&str]); 6] = [
+			("main", &["a", "b"]),
+			("release", &["a", "b"]),
+			("feature-x", &["c"]),
+			("feature-z", &["e"]),
+			("feature-y", &["d"]),
+			// The same name as a tag in another set, so only the commits tell them apart
+			("v2", &["g"]),
+		];
+		let tags: [(&str, &[&str]); 2] = [("v1", &["c"]), ("v2", &["f"])];
+
+		// Every rotation of the input, both forwards and backwards, has to give the
+		// same output
+		for rotation in 0..branches.len() {
+			for reverse in [false, true] {
+				let mut shuffled_branches = branches.to_vec();
+				shuffled_branches.rotate_left(rotation);
+				let mut shuffled_tags = tags.to_vec();
+				shuffled_tags.rotate_left(rotation % tags.len());
+				if reverse {
+					shuffled_branches.reverse();
+					shuffled_tags.reverse();
+				}
+
+				let snapshot = group_locations_by_commit_set(
+					location_map(commits.as_slice(), shuffled_branches.as_slice()),
+					location_map(commits.as_slice(), shuffled_tags.as_slice()),
+				)
+				.iter()
+				.map(|(commit_set, (branch_list, tag_list))| {
+					format!(
+						"{} | {} | {}",
+						commit_set
+							.iter()
+							.map(|commit| commit.git_revision.as_str())
+							.collect::<Vec<_>>()
+							.join(" "),
+						branch_list.join(" "),
+						tag_list.join(" ")
+					)
+				})
+				.collect::<Vec<_>>();
+
+				assert_eq!(
+					snapshot,
+					[
+						"c | feature-x | v1",
+						"a b | main release | ",
+						"d | feature-y | ",
+						"e | feature-z | ",
+						"f |  | v2",
+						"g | v2 | ",
+					],
+					"rotated by {rotation}, reversed: {reverse}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn remote_branch_names_are_normalized() {
+		// Both forms of each branch, which have to end up the same
+		let cases = [
+			("remotes/origin/feature", "origin/feature"),
+			("remotes/origin/release/1.2", "origin/release/1.2"),
+			(
+				"remotes/origin/HEAD -> remotes/origin/main",
+				"origin/HEAD -> origin/main",
+			),
+			(
+				"remotes/origin/HEAD -> origin/main",
+				"origin/HEAD -> origin/main",
+			),
+			("* remotes/origin/main", "* origin/main"),
+		];
+
+		for (prefixed_branch, branch) in cases {
+			assert_eq!(normalize_remote_branch_name(prefixed_branch), branch);
+			assert_eq!(normalize_remote_branch_name(branch), branch);
+		}
+		// Only a leading `remotes/` is stripped
+		assert_eq!(
+			normalize_remote_branch_name("origin/remotes/feature"),
+			"origin/remotes/feature"
+		);
+	}
+
+	#[test]
+	fn branch_lists_dedupe_both_remote_forms() {
+		let branch_list_raw = [
+			"  remotes/origin/HEAD -> remotes/origin/main",
+			"  remotes/origin/feature",
+			"  origin/feature",
+			"  origin/main",
+			"  remotes/origin/main",
+			"",
+		]
+		.join("\n");
+
+		assert_eq!(
+			parse_branch_list(branch_list_raw.as_str(), false),
+			to_strings(&[
+				"origin/HEAD -> origin/main",
+				"origin/feature",
+				"origin/main"
+			])
+		);
+		// Local branches are left alone, since a branch can be named `remotes/...`
+		assert_eq!(
+			parse_branch_list("* main\n  remotes/origin/feature\n", true),
+			to_strings(&["* main", "remotes/origin/feature"])
+		);
+	}
+
+	#[test]
+	fn divergent_commits_are_counted_on_each_side() {
+		let fixture_repo = FixtureRepo::new("divergent-counts");
+		fixture_repo.commit("Initial commit");
+		fixture_repo.git(&["branch", "release"]);
+		fixture_repo.commit("Add a feature");
+		fixture_repo.commit("Add another feature");
+		fixture_repo.git(&["checkout", "--quiet", "release"]);
+		fixture_repo.commit("Fix a bug");
+
+		assert_eq!(
+			count_divergent_commits(fixture_repo.path(), "main", "release")
+				.expect("unable to count the commits"),
+			(2, 1)
+		);
+		assert_eq!(
+			count_divergent_commits(fixture_repo.path(), "release", "main")
+				.expect("unable to count the commits"),
+			(1, 2)
+		);
+		assert_eq!(
+			count_divergent_commits(fixture_repo.path(), "main", "main~2")
+				.expect("unable to count the commits"),
+			(2, 0)
+		);
+	}
+
+	#[test]
+	fn tags_are_ordered_by_version_rather_than_date() {
+		let fixture_repo = FixtureRepo::new("tags-by-version");
+		let first_revision = fixture_repo.commit("Initial commit");
+		let second_revision = fixture_repo.commit("Add a feature");
+		fixture_repo.commit("Add another feature");
+		// The newest version is tagged first, and the tags are a mix of annotated and
+		// lightweight ones
+		fixture_repo.git_at(
+			&["tag", "--annotate", "--message=Release", "v1.10.0"],
+			"2024-03-01T12:00:00Z",
+		);
+		fixture_repo.git(&["tag", "v1.2.0", first_revision.as_str()]);
+		fixture_repo.git_at(
+			&[
+				"tag",
+				"--annotate",
+				"--message=Release",
+				"v1.9.0",
+				second_revision.as_str(),
+			],
+			"2024-03-20T12:00:00Z",
+		);
+		fixture_repo.git(&["tag", "nightly"]);
+
+		// The pattern, and the matching tags in order
+		let cases: [(&str, &[&str]); 3] = [
+			("v*", &["v1.10.0", "v1.9.0", "v1.2.0"]),
+			("v1.9*", &["v1.9.0"]),
+			("release-*", &[]),
+		];
+		for (pattern, expected) in cases {
+			assert_eq!(
+				get_tags_by_version(fixture_repo.path(), pattern).expect("unable to get the tags"),
+				expected,
+				"{pattern}"
+			);
+		}
+	}
+
+	#[test]
+	fn duplicates_fall_back_to_subjects_when_patch_ids_differ() {
+		// `b` is a cherry-pick of `a` onto different context, so only the subject
+		// matches, and `c` is a reworded cherry-pick of `b`, so only the patch ID
+		// does
+		// `d` and `e` don't have subjects, so they're never matched that way
+		let commits = [
+			("a", "PROJ-1: Fix the parser"),
+			("b", "PROJ-1: Fix the parser"),
+			("c", "PROJ-1: Fix the parser (back-port)"),
+			("d", ""),
+			("e", " "),
+			("f", "PROJ-1: Add a test"),
+		]
+		.map(|(git_revision, subject)| {
+			let mut commit = commit(git_revision, &[]);
+			commit.subject = subject.to_owned();
+			commit
+		});
+		let linked_commit = commit("linked", &[]);
+		let patch_ids = [("a", "1"), ("b", "2"), ("c", "2"), ("d", "3"), ("e", "4")]
+			.iter()
+			.map(|(git_revision, patch_id)| ((*git_revision).to_owned(), (*patch_id).to_owned()))
+			.collect::<HashMap<_, _>>();
+		let mut included_commits = commits
+			.iter()
+			.map(|commit| included(commit, Vec::new()))
+			.collect::<Vec<_>>();
+		included_commits[1]
+			.linked_commits
+			.push(included(&linked_commit, Vec::new()));
+
+		let collapsed_commits = collapse_duplicate_commits(&included_commits, &patch_ids);
+
+		// Each remaining commit, its duplicates, and its linked commits
+		let summary = collapsed_commits
+			.iter()
+			.map(|included_commit| {
+				(
+					included_commit.commit.git_revision.as_str(),
+					included_commit
+						.duplicate_commits
+						.iter()
+						.map(|commit| commit.git_revision.as_str())
+						.collect::<Vec<_>>(),
+					included_commit
+						.linked_commits
+						.iter()
+						.map(|linked_commit| linked_commit.commit.git_revision.as_str())
+						.collect::<Vec<_>>(),
+				)
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			summary,
+			[
+				("a", vec!["b", "c"], vec!["linked"]),
+				("d", vec![], vec![]),
+				("e", vec![], vec![]),
+				("f", vec![], vec![]),
+			]
+		);
+	}
+}
@@ -0,0 +1,186 @@
+//! The module for things the unit tests share, like building commits without
+//! a repo.
+
+// Uses
+use std::{
+	env,
+	fs::{create_dir_all, remove_dir_all},
+	path::{Path, PathBuf},
+	process::Command,
+};
+
+use crate::{
+	collection::{Commit, ReferencedCommits, Signature},
+	ids::CommitId,
+	search::IncludedCommit,
+};
+
+// Constants
+/// When fixture repo commits are made, unless a date is given, so that the
+/// output doesn't depend on when the tests run.
+const FIXTURE_DATE: &str = "2024-03-10T12:00:00Z";
+
+/// Builds a commit with the given hash and parents, and nothing else of note.
+pub fn commit(git_revision: &str, parent_revisions: &[&str]) -> Commit {
+	let signature = || Signature {
+		name:      "Test Author".to_owned(),
+		email:     "test@example.com".to_owned(),
+		timestamp: 0,
+	};
+
+	Commit {
+		git_revision:           git_revision.to_owned(),
+		parent_revisions:       parent_revisions
+			.iter()
+			.map(|parent_revision| (*parent_revision).to_owned())
+			.collect(),
+		author:                 signature(),
+		committer:              signature(),
+		subject:                String::new(),
+		svn_info:               None,
+		jira_tickets:           Vec::new(),
+		mentioned_jira_tickets: Vec::new(),
+		referenced_commits:     ReferencedCommits {
+			git_commits: Vec::new(),
+			svn_commits: Vec::new(),
+		},
+		is_likely_a_merge:      parent_revisions.len() > 1,
+	}
+}
+
+/// Puts a commit in an inclusion tree, with the given commits linked under it.
+pub fn included<'a>(
+	commit: &'a Commit,
+	linked_commits: Vec<IncludedCommit<'a>>,
+) -> IncludedCommit<'a> {
+	IncludedCommit {
+		commit,
+		linked_commits,
+		duplicate_commits: Vec::new(),
+	}
+}
+
+/// Makes a full 40-character hash out of a short hex string, so tests can
+/// refer to commits by something readable.
+pub fn full_hash(prefix: &str) -> String {
+	format!("{prefix:0<40}")
+}
+
+/// Parses a hash (or abbreviation) that's known to be valid.
+pub fn commit_id(revision: &str) -> CommitId {
+	revision.parse().expect("the test hash is valid")
+}
+
+/// A throwaway Git repo in the temp directory, which is deleted when it's
+/// dropped.
+pub struct FixtureRepo {
+	path: PathBuf,
+}
+
+impl FixtureRepo {
+	/// Creates an empty repo with `main` checked out. The name only has to be
+	/// unique among the tests.
+	pub fn new(name: &str) -> Self {
+		let fixture_repo = Self::empty_dir(name);
+		fixture_repo.git(&["init", "--quiet", "--initial-branch=main"]);
+
+		fixture_repo
+	}
+
+	/// Clones another fixture repo, so that its branches are remote branches
+	/// here.
+	pub fn clone_of(other: &Self, name: &str) -> Self {
+		let fixture_repo = Self::empty_dir(name);
+		fixture_repo.git(&[
+			"clone",
+			"--quiet",
+			other
+				.path
+				.to_str()
+				.expect("the temp directory is valid UTF-8"),
+			".",
+		]);
+
+		fixture_repo
+	}
+
+	/// Creates an empty directory that's cleaned up the same way, for tests
+	/// that only need somewhere to write files.
+	pub fn empty_dir(name: &str) -> Self {
+		let path = env::temp_dir().join(format!("clog-test-{}-{name}", std::process::id()));
+		if path.exists() {
+			remove_dir_all(path.as_path()).expect("unable to clear out the fixture repo");
+		}
+		create_dir_all(path.as_path()).expect("unable to create the fixture repo");
+
+		Self { path }
+	}
+
+	pub fn path(&self) -> &Path {
+		self.path.as_path()
+	}
+
+	pub fn path_str(&self) -> &str {
+		self.path
+			.to_str()
+			.expect("the temp directory is valid UTF-8")
+	}
+
+	/// Runs a Git command in the repo, panicking if it fails, and returns its
+	/// trimmed output.
+	///
+	/// The user's own configuration is ignored, so that things like commit
+	/// signing don't get in the way.
+	pub fn git(&self, args: &[&str]) -> String {
+		self.git_at(args, FIXTURE_DATE)
+	}
+
+	/// Runs a Git command the same way as `git`, but with the author and
+	/// committer dates set to `date` (in any format Git accepts).
+	pub fn git_at(&self, args: &[&str], date: &str) -> String {
+		let output = Command::new("git")
+			.args(args)
+			.current_dir(self.path.as_path())
+			.env("GIT_CONFIG_GLOBAL", "/dev/null")
+			.env("GIT_CONFIG_NOSYSTEM", "1")
+			.env("GIT_AUTHOR_NAME", "Test Author")
+			.env("GIT_AUTHOR_EMAIL", "author@example.com")
+			.env("GIT_AUTHOR_DATE", date)
+			.env("GIT_COMMITTER_NAME", "Test Committer")
+			.env("GIT_COMMITTER_EMAIL", "committer@example.com")
+			.env("GIT_COMMITTER_DATE", date)
+			.output()
+			.expect("unable to run Git");
+		assert!(
+			output.status.success(),
+			"`git {}` failed: {}",
+			args.join(" "),
+			String::from_utf8_lossy(output.stderr.as_slice())
+		);
+
+		String::from_utf8_lossy(output.stdout.as_slice())
+			.trim()
+			.to_owned()
+	}
+
+	/// Makes an empty commit on the current branch, and returns its hash.
+	pub fn commit(&self, message: &str) -> String {
+		self.commit_at(message, FIXTURE_DATE)
+	}
+
+	/// Makes an empty commit like `commit`, but made at `date`.
+	pub fn commit_at(&self, message: &str, date: &str) -> String {
+		self.git_at(
+			&["commit", "--quiet", "--allow-empty", "--message", message],
+			date,
+		);
+		self.git(&["rev-parse", "HEAD"])
+	}
+}
+
+impl Drop for FixtureRepo {
+	fn drop(&mut self) {
+		// Failing to clean up the temp directory isn't worth failing a test over
+		let _ = remove_dir_all(self.path.as_path());
+	}
+}
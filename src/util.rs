@@ -2,20 +2,288 @@
 
 // Uses
 use std::{
+	borrow::Cow,
+	cell::RefCell,
+	io::{stdin, BufRead, Result as IoResult, Write},
+	iter,
 	num::ParseIntError,
-	process::Command,
+	process::{Command, Output, Stdio},
 	result::Result as StdResult,
-	str::from_utf8 as str_from_utf8,
+	sync::atomic::{AtomicBool, AtomicU32, Ordering},
+	thread::sleep,
+	time::Duration,
 };
 
 use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use shell_words::quote as quote_shell_word;
+
+// Constants
+/// The maximum number of arguments to display when logging a command, so that
+/// long lists of filepaths don't drown out everything else.
+const MAX_LOGGED_COMMAND_ARGS: usize = 24;
+/// How long to wait before the first retry of a failed Git command. This
+/// doubles with every retry after it.
+const GIT_RETRY_INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+/// The longest to wait between retries, no matter how many there have been.
+const GIT_RETRY_MAX_BACKOFF: Duration = Duration::from_secs(5);
+/// Fragments of Git's error output that point to a failure that might not
+/// happen again, like a lock held by a concurrent `git gc` or a hiccup on a
+/// network filesystem.
+///
+/// Anything else (like a bad revision) fails the same way every time, so it's
+/// never retried.
+const TRANSIENT_GIT_ERROR_FRAGMENTS: &[&str] = &[
+	".lock': File exists",
+	"cannot lock ref",
+	"unable to create temporary file",
+	"Resource temporarily unavailable",
+	"Stale file handle",
+	"Input/output error",
+	"Connection timed out",
+	"unable to read",
+	"failed to read object",
+	"packfile",
+];
+
+// Statics
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+static GIT_RETRIES: AtomicU32 = AtomicU32::new(0);
+static STRICT_PARSING: AtomicBool = AtomicBool::new(false);
+thread_local! {
+	/// The commands run on this thread, for structured outputs to report.
+	static RECORDED_COMMANDS: RefCell<Vec<Vec<String>>> = RefCell::new(Vec::new());
+}
+
+/// Enables or disables verbose output for the rest of the run.
+pub fn set_verbose(verbose: bool) {
+	VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+/// Whether verbose output is enabled.
+pub fn is_verbose() -> bool {
+	VERBOSE.load(Ordering::Relaxed)
+}
+
+/// Sets how many times a Git command that fails transiently is retried for the
+/// rest of the run.
+pub fn set_git_retries(git_retries: u32) {
+	GIT_RETRIES.store(git_retries, Ordering::Relaxed);
+}
+
+/// Enables or disables strict parsing for the rest of the run, where anything
+/// unexpected in the repo data is an error instead of being worked around.
+pub fn set_strict_parsing(strict_parsing: bool) {
+	STRICT_PARSING.store(strict_parsing, Ordering::Relaxed);
+}
+
+/// Whether strict parsing is enabled.
+pub fn is_strict_parsing() -> bool {
+	STRICT_PARSING.load(Ordering::Relaxed)
+}
+
+/// Formats a command as it would be typed into a shell, truncating the argument
+/// list if it's excessively long.
+pub fn format_command(command: &Command) -> String {
+	let mut words = vec![quote_shell_word(&command.get_program().to_string_lossy()).into_owned()];
+	let args = command.get_args().collect::<Vec<_>>();
+	words.extend(
+		args.iter()
+			.take(MAX_LOGGED_COMMAND_ARGS)
+			.map(|arg| quote_shell_word(&arg.to_string_lossy()).into_owned()),
+	);
+	if args.len() > MAX_LOGGED_COMMAND_ARGS {
+		words.push(format!(
+			"... ({} more arguments)",
+			args.len() - MAX_LOGGED_COMMAND_ARGS
+		));
+	}
+
+	words.join(" ")
+}
+
+/// Gets the program and arguments of a command, truncating the argument list
+/// the same way as `format_command`.
+fn get_command_argv(command: &Command) -> Vec<String> {
+	let args = command.get_args().collect::<Vec<_>>();
+	let mut argv = iter::once(command.get_program())
+		.chain(args.iter().copied().take(MAX_LOGGED_COMMAND_ARGS))
+		.map(|word| word.to_string_lossy().into_owned())
+		.collect::<Vec<_>>();
+	if args.len() > MAX_LOGGED_COMMAND_ARGS {
+		argv.push(format!(
+			"... ({} more arguments)",
+			args.len() - MAX_LOGGED_COMMAND_ARGS
+		));
+	}
+
+	argv
+}
+
+/// Takes the commands that have been run on this thread so far, leaving the
+/// record empty.
+///
+/// Commands run on other threads (like the parallel searches of `compare`)
+/// aren't included.
+pub fn take_recorded_commands() -> Vec<Vec<String>> {
+	RECORDED_COMMANDS.with(RefCell::take)
+}
+
+/// Records a command that's about to be run, so that the results can be
+/// re-derived later. It's printed with `--verbose`, and kept for
+/// `take_recorded_commands`.
+fn record_command(command: &Command) {
+	if is_verbose() {
+		eprintln!("[VERBOSE] Running: {}", format_command(command));
+	}
+	RECORDED_COMMANDS.with(|recorded_commands| {
+		recorded_commands
+			.borrow_mut()
+			.push(get_command_argv(command));
+	});
+}
+
+/// Formats just the program and subcommand of a command, for warnings where
+/// the full command would be too long.
+fn format_subcommand(command: &Command) -> String {
+	iter::once(command.get_program())
+		.chain(command.get_args().take(1))
+		.map(|word| word.to_string_lossy())
+		.collect::<Vec<_>>()
+		.join(" ")
+}
 
 /// Runs a provided command and returns the stdout in UTF-8.
 pub fn run_command(mut command: Command) -> Result<String> {
+	let output = run_command_for_bytes(&mut command)?;
+
+	// Convert the command output into a usable string of UTF-8
+	output_to_string(&command, output)
+}
+
+/// Runs a provided command and returns the stdout as it is, for when invalid
+/// UTF-8 needs to be handled by the caller.
+pub fn run_command_for_bytes(command: &mut Command) -> Result<Vec<u8>> {
+	record_command(command);
+
 	// Run the command
-	let command_result = command
-		.output()
+	let command_result = get_output_with_retries(command)?;
+	if !command_result.status.success() {
+		return Err(anyhow!(
+			"command failed: {:?}",
+			command_result.status.code()
+		));
+	}
+
+	Ok(command_result.stdout)
+}
+
+/// Runs a command and collects its output, retrying it with a backoff if it
+/// fails in a way that looks transient, up to the number of retries set with
+/// `set_git_retries`.
+fn get_output_with_retries(command: &mut Command) -> Result<Output> {
+	let command_description = format_subcommand(command);
+	retry_transient_failures(
+		command_description.as_str(),
+		GIT_RETRIES.load(Ordering::Relaxed),
+		GIT_RETRY_INITIAL_BACKOFF,
+		|| command.output(),
+	)
+}
+
+/// Runs a command with `run`, retrying it with a backoff if it fails in a way
+/// that looks transient, up to `git_retries` times.
+///
+/// The command is run through a closure so that the retry logic can be tested
+/// without running anything.
+fn retry_transient_failures<R>(
+	command_description: &str,
+	git_retries: u32,
+	initial_backoff: Duration,
+	mut run: R,
+) -> Result<Output>
+where
+	R: FnMut() -> IoResult<Output>,
+{
+	let mut backoff = initial_backoff;
+	for retry in 1..=git_retries {
+		let command_result = run().with_context(|| "unable to run the command")?;
+		if command_result.status.success() || !is_transient_git_failure(&command_result.stderr) {
+			return Ok(command_result);
+		}
+
+		eprintln!(
+			"[WARNING] `{command_description}` failed in a way that looks transient, so it's \
+			 being retried ({retry}/{git_retries}): {}",
+			String::from_utf8_lossy(&command_result.stderr).trim()
+		);
+		sleep(backoff);
+		backoff = (backoff * 2).min(GIT_RETRY_MAX_BACKOFF);
+	}
+
+	run().with_context(|| "unable to run the command")
+}
+
+/// Whether a failed Git command's error output suggests that running it again
+/// might succeed.
+fn is_transient_git_failure(stderr: &[u8]) -> bool {
+	let stderr = String::from_utf8_lossy(stderr);
+	TRANSIENT_GIT_ERROR_FRAGMENTS
+		.iter()
+		.any(|fragment| stderr.contains(fragment))
+}
+
+/// Converts command output into a string, replacing any invalid UTF-8.
+///
+/// Old commit messages (especially ones migrated from SVN) are sometimes in
+/// another encoding without saying so, and one bad commit shouldn't stop the
+/// whole run, unless strict parsing is enabled.
+pub fn output_to_string(command: &Command, output: Vec<u8>) -> Result<String> {
+	String::from_utf8(output).or_else(|error| {
+		if is_strict_parsing() {
+			return Err(anyhow!(
+				"the output of `{}` isn't valid UTF-8, starting at byte {}",
+				format_subcommand(command),
+				error.utf8_error().valid_up_to()
+			));
+		}
+		eprintln!(
+			"[WARNING] The output of `{}` wasn't entirely valid UTF-8, so the invalid parts were \
+			 replaced.",
+			format_subcommand(command)
+		);
+		Ok(String::from_utf8_lossy(error.as_bytes()).into_owned())
+	})
+}
+
+/// Runs a provided command with the given input on stdin, and returns the
+/// stdout in UTF-8.
+pub fn run_command_with_input(mut command: Command, input: &str) -> Result<String> {
+	record_command(&command);
+
+	// Start the command and feed it the input
+	// The input is written from another thread so that a command that produces
+	// output as it reads can't fill up its stdout and deadlock
+	let mut child = command
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
 		.with_context(|| "unable to run the command")?;
+	let mut stdin = child
+		.stdin
+		.take()
+		.expect("stdin was configured to be piped");
+	let command_result = std::thread::scope(|scope| {
+		let writer = scope.spawn(move || stdin.write_all(input.as_bytes()));
+		let output = child.wait_with_output();
+		writer
+			.join()
+			.expect("the writer thread doesn't panic")
+			.with_context(|| "unable to write the command input")?;
+		output.with_context(|| "unable to run the command")
+	})?;
 	if !command_result.status.success() {
 		return Err(anyhow!(
 			"command failed: {:?}",
@@ -24,9 +292,168 @@ pub fn run_command(mut command: Command) -> Result<String> {
 	}
 
 	// Convert the command output into a usable string of UTF-8
-	str_from_utf8(&command_result.stdout)
-		.with_context(|| "unable to parse command output as UTF-8")
-		.map(ToOwned::to_owned)
+	output_to_string(&command, command_result.stdout)
+}
+
+/// Runs a provided command and returns its exit code.
+///
+/// Unlike `run_command`, a non-zero exit code is not considered a failure,
+/// since some commands (like `git merge-base --is-ancestor`) use it to return
+/// their answer.
+pub fn run_command_for_exit_code(mut command: Command) -> Result<i32> {
+	record_command(&command);
+
+	// Run the command
+	let command_result = get_output_with_retries(&mut command)?;
+
+	command_result
+		.status
+		.code()
+		.ok_or_else(|| anyhow!("command was terminated by a signal"))
+}
+
+/// Reads one line from stdin per argument, trimmed, so that arguments can be
+/// passed without any shell quoting.
+///
+/// The names are only used to describe the arguments in errors.
+pub fn read_stdin_lines(line_names: &[&str]) -> Result<Vec<String>> {
+	let mut stdin_lines = stdin().lock().lines();
+	line_names
+		.iter()
+		.map(|line_name| {
+			let line = stdin_lines
+				.next()
+				.ok_or_else(|| anyhow!("stdin ended before the {line_name} was provided"))?
+				.with_context(|| format!("unable to read the {line_name} from stdin"))?;
+			let line = line.trim();
+			if line.is_empty() {
+				return Err(anyhow!("the {line_name} provided on stdin is empty"));
+			}
+			Ok(line.to_owned())
+		})
+		.collect()
+}
+
+/// Abbreviates a revision hash to the given length for display.
+///
+/// Returns an error instead of panicking if the revision is too short (or
+/// otherwise can't be cut at that length), since that means something
+/// malformed made it all the way to display.
+pub fn abbreviate(revision: &str, length: usize) -> Result<&str> {
+	revision.get(0..length).ok_or_else(|| {
+		anyhow!("unable to abbreviate the revision `{revision}` to {length} characters")
+	})
+}
+
+/// Quotes and escapes a string as a JSON string literal.
+pub fn json_string(value: &str) -> String {
+	let mut quoted = String::with_capacity(value.len() + 2);
+
+	quoted.push('"');
+	for c in value.chars() {
+		match c {
+			'"' => quoted.push_str("\\\""),
+			'\\' => quoted.push_str("\\\\"),
+			'\n' => quoted.push_str("\\n"),
+			'\r' => quoted.push_str("\\r"),
+			'\t' => quoted.push_str("\\t"),
+			c if c.is_control() => quoted.push_str(format!("\\u{:04x}", c as u32).as_str()),
+			c => quoted.push(c),
+		}
+	}
+	quoted.push('"');
+
+	quoted
+}
+
+/// Makes commit-derived text safe to print to a terminal or copy to the
+/// clipboard.
+///
+/// ANSI escape sequences are removed entirely, and any remaining control
+/// characters (C0, DEL, and C1) are stripped, except for tabs.
+pub fn sanitize_for_display(text: &str) -> Cow<str> {
+	lazy_static! {
+		/// Matches CSI sequences (colours, cursor movement), OSC sequences (window titles, hyperlinks), and the remaining two-character escapes
+		static ref ANSI_ESCAPE_SEQUENCE_REGEX: Regex =
+			Regex::new(r"\x1b(?:\[[0-?]*[ -/]*[@-~]|\][^\x07\x1b]*(?:\x07|\x1b\\)?|[@-Z\\-_])").unwrap();
+	}
+
+	if !text
+		.chars()
+		.any(|character| character.is_control() && character != '\t')
+	{
+		return Cow::Borrowed(text);
+	}
+
+	Cow::Owned(
+		ANSI_ESCAPE_SEQUENCE_REGEX
+			.replace_all(text, "")
+			.chars()
+			.filter(|character| !character.is_control() || *character == '\t')
+			.collect(),
+	)
+}
+
+/// Cuts text down to at most `max_length` characters, ending it with an
+/// ellipsis if anything was cut.
+pub fn truncate_with_ellipsis(text: &str, max_length: usize) -> Cow<str> {
+	if text.chars().count() <= max_length {
+		return Cow::Borrowed(text);
+	}
+
+	let kept_text = text
+		.char_indices()
+		.nth(max_length.saturating_sub(1))
+		.map_or(text, |(index, _)| &text[..index]);
+	Cow::Owned(format!("{}\u{2026}", kept_text.trim_end()))
+}
+
+/// Whether a path is absolute, in either POSIX or Windows style, regardless of
+/// the current platform.
+pub fn is_absolute_path_str(path: &str) -> bool {
+	path.starts_with('/') || is_windows_absolute_path_str(path)
+}
+
+/// Whether a path is an absolute Windows path, either with a drive letter
+/// (`C:\repo`) or UNC (`\\server\share`).
+pub fn is_windows_absolute_path_str(path: &str) -> bool {
+	let path_bytes = path.as_bytes();
+	path.starts_with("\\\\")
+		|| (path_bytes.len() >= 3
+			&& path_bytes[0].is_ascii_alphabetic()
+			&& path_bytes[1] == b':'
+			&& (path_bytes[2] == b'\\' || path_bytes[2] == b'/'))
+}
+
+/// Makes an absolute path relative to a root directory, if it's inside it.
+///
+/// Separators are normalised to `/`, and a trailing separator is kept, since
+/// it's meaningful for matching directories. Windows paths are compared
+/// case-insensitively.
+pub fn relative_path_under(path: &str, root: &str) -> Option<String> {
+	let case_insensitive = is_windows_absolute_path_str(path);
+	let normalise = |path: &str| {
+		let path = path.replace('\\', "/");
+		if case_insensitive {
+			path.to_ascii_lowercase()
+		} else {
+			path
+		}
+	};
+
+	let path_normalised = path.replace('\\', "/");
+	let path_comparable = normalise(path);
+	let root_comparable = normalise(root).trim_end_matches('/').to_owned();
+	if path_comparable.trim_end_matches('/') == root_comparable {
+		return Some(".".to_owned());
+	}
+
+	// The ASCII-only case conversion keeps the byte offsets the same, so the
+	// original casing can be kept in the result
+	let root_prefix = format!("{root_comparable}/");
+	path_comparable
+		.starts_with(root_prefix.as_str())
+		.then(|| path_normalised[root_prefix.len()..].to_owned())
 }
 
 /// Swaps the nesting order of a `Result<Option<T>, E>` to an `Option<Result<T,
@@ -100,3 +527,390 @@ pub fn sortable_jira_ticket(jira_ticket: &str) -> (&str, u32) {
 	// Return the pair, so they can be used as a sorting key
 	(project, issue_num)
 }
+
+/// Takes a Jira ticket and returns a key that's the same for all variants of
+/// it, ignoring case, underscores and hyphens in the project, and leading zeros
+/// in the issue number.
+///
+/// Returns `None` if the ticket doesn't have a numeric issue number.
+pub fn jira_ticket_variant_key(jira_ticket: &str) -> Option<(String, u64)> {
+	// Split on the last separator, since the project may contain either
+	let (project, issue) = jira_ticket.rsplit_once(['-', '_'])?;
+
+	let project_key = project
+		.chars()
+		.filter(|c| !matches!(c, '-' | '_'))
+		.flat_map(char::to_uppercase)
+		.collect::<String>();
+	let issue_num = issue.parse::<u64>().ok()?;
+
+	Some((project_key, issue_num))
+}
+
+/// Whether the whole text matches a glob pattern, where `*` matches any run of
+/// characters (including none) and `?` matches exactly one.
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+	let pattern = pattern.chars().collect::<Vec<_>>();
+	let text = text.chars().collect::<Vec<_>>();
+
+	// Walk both at once, remembering the last `*` so that it can be made to match
+	// one more character whenever the rest of the pattern doesn't line up
+	let (mut pattern_index, mut text_index) = (0, 0);
+	let mut last_star = None;
+	while text_index < text.len() {
+		match pattern.get(pattern_index) {
+			Some('*') => {
+				last_star = Some((pattern_index, text_index));
+				pattern_index += 1;
+			}
+			Some(&pattern_char) if pattern_char == '?' || pattern_char == text[text_index] => {
+				pattern_index += 1;
+				text_index += 1;
+			}
+			_ => {
+				let Some((star_pattern_index, star_text_index)) = last_star else {
+					return false;
+				};
+				pattern_index = star_pattern_index + 1;
+				text_index = star_text_index + 1;
+				last_star = Some((star_pattern_index, star_text_index + 1));
+			}
+		}
+	}
+
+	pattern[pattern_index..]
+		.iter()
+		.all(|&pattern_char| pattern_char == '*')
+}
+
+/// Decodes the `%XX` escapes in a URL component, like the `%20` in
+/// `branches/release%201.2`.
+///
+/// Malformed escapes are left as they are, and anything that doesn't decode
+/// to valid UTF-8 is replaced.
+pub fn percent_decode(text: &str) -> String {
+	let bytes = text.as_bytes();
+	let mut decoded = Vec::with_capacity(bytes.len());
+	let mut index = 0;
+	while index < bytes.len() {
+		let escaped_byte = (bytes[index] == b'%')
+			.then(|| text.get(index + 1..index + 3))
+			.flatten()
+			.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+		if let Some(escaped_byte) = escaped_byte {
+			decoded.push(escaped_byte);
+			index += 3;
+		} else {
+			decoded.push(bytes[index]);
+			index += 1;
+		}
+	}
+
+	String::from_utf8_lossy(decoded.as_slice()).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn abbreviate_cuts_revisions_to_length() {
+		let revision = "0123456789abcdef0123456789abcdef01234567";
+
+		assert_eq!(abbreviate(revision, 8).unwrap(), "01234567");
+		assert_eq!(abbreviate(revision, 40).unwrap(), revision);
+		assert_eq!(abbreviate(revision, 0).unwrap(), "");
+	}
+
+	#[test]
+	fn abbreviate_rejects_short_revisions() {
+		assert!(abbreviate("0123", 8).is_err());
+		assert!(abbreviate("", 1).is_err());
+		// Exactly the length is fine
+		assert_eq!(abbreviate("01234567", 8).unwrap(), "01234567");
+	}
+
+	#[test]
+	fn abbreviate_rejects_cutting_multibyte_characters() {
+		// The length is in bytes, and each of these characters is two bytes long
+		let text = "\u{e9}\u{e8}\u{ea}";
+
+		assert!(abbreviate(text, 1).is_err());
+		assert!(abbreviate(text, 3).is_err());
+		assert_eq!(abbreviate(text, 2).unwrap(), "\u{e9}");
+		assert_eq!(abbreviate(text, 6).unwrap(), text);
+	}
+
+	#[test]
+	fn sanitize_for_display_removes_escape_sequences_and_control_characters() {
+		// The input and what it should become
+		let cases = [
+			(
+				"\x1b[31mPROJ-1: Fix the build\x1b[0m",
+				"PROJ-1: Fix the build",
+			),
+			("Ring the bell\x07 twice\x07", "Ring the bell twice"),
+			("\x1b[1;31mred\x1b[0m and a bell\x07", "red and a bell"),
+			("\x1b]0;window title\x07Subject", "Subject"),
+			(
+				"Carriage\r return and C1\u{9b} control",
+				"Carriage return and C1 control",
+			),
+			("Tabs\tstay", "Tabs\tstay"),
+			("\u{e9}l\u{e8}ve \u{fffd}", "\u{e9}l\u{e8}ve \u{fffd}"),
+		];
+
+		for (text, expected) in cases {
+			assert_eq!(sanitize_for_display(text), expected, "{text:?}");
+		}
+	}
+
+	#[test]
+	fn sanitize_for_display_borrows_clean_text() {
+		assert!(matches!(
+			sanitize_for_display("PROJ-1: Fix the build"),
+			Cow::Borrowed(_)
+		));
+	}
+
+	#[test]
+	fn truncate_with_ellipsis_cuts_long_text() {
+		// The text, the maximum length, and the expected result
+		let cases = [
+			("Fix the build", 20, "Fix the build"),
+			("Fix the build", 13, "Fix the build"),
+			("Fix the build", 12, "Fix the bui\u{2026}"),
+			("Fix the build", 9, "Fix the\u{2026}"),
+			("Caf\u{e9} au lait", 5, "Caf\u{e9}\u{2026}"),
+			("Fix", 1, "\u{2026}"),
+		];
+		for (text, max_length, expected) in cases {
+			assert_eq!(
+				truncate_with_ellipsis(text, max_length),
+				expected,
+				"{text:?} at {max_length}"
+			);
+		}
+	}
+
+	#[test]
+	fn absolute_paths_are_detected_in_both_styles() {
+		// The path and whether it's absolute
+		let cases = [
+			("/home/me/repo/src/lib.rs", true),
+			("/", true),
+			("src/lib.rs", false),
+			("./src/", false),
+			("C:\\repo\\src\\lib.rs", true),
+			("c:/repo/src", true),
+			("\\\\server\\share\\repo", true),
+			("src\\lib.rs", false),
+			// Drive-relative, which is relative to the current directory on that drive
+			("C:repo", false),
+		];
+
+		for (path, expected) in cases {
+			assert_eq!(is_absolute_path_str(path), expected, "{path}");
+		}
+	}
+
+	#[test]
+	fn paths_are_made_relative_to_the_root() {
+		// The path, the root, and the path relative to the root (if it's inside it)
+		let cases = [
+			// POSIX
+			(
+				"/home/me/repo/src/lib.rs",
+				"/home/me/repo",
+				Some("src/lib.rs"),
+			),
+			("/home/me/repo/src/", "/home/me/repo", Some("src/")),
+			("/home/me/repo/src", "/home/me/repo/", Some("src")),
+			("/home/me/repo", "/home/me/repo", Some(".")),
+			("/home/me/repo/", "/home/me/repo", Some(".")),
+			("/home/me/repository/src", "/home/me/repo", None),
+			("/home/other/src", "/home/me/repo", None),
+			("/Home/Me/Repo/src", "/home/me/repo", None),
+			// Windows
+			("C:\\repo\\src\\lib.rs", "C:/repo", Some("src/lib.rs")),
+			("c:\\Repo\\Src\\", "C:/repo", Some("Src/")),
+			("C:/repo/src", "C:/repo", Some("src")),
+			("C:\\repo", "C:/repo", Some(".")),
+			("C:\\repository\\src", "C:/repo", None),
+			("D:\\repo\\src", "C:/repo", None),
+			(
+				"\\\\server\\share\\repo\\src",
+				"//server/share/repo",
+				Some("src"),
+			),
+			("\\\\server\\other\\src", "//server/share/repo", None),
+		];
+
+		for (path, root, expected) in cases {
+			assert_eq!(
+				relative_path_under(path, root).as_deref(),
+				expected,
+				"{path} under {root}"
+			);
+		}
+	}
+
+	#[test]
+	fn jira_ticket_variant_keys_match_variants() {
+		// Groups of tickets that should all have the same key
+		let variant_groups: [&[&str]; 4] = [
+			// Case
+			&["PROJ-123", "proj-123", "Proj-123"],
+			// Separators
+			&[
+				"PROJ-123",
+				"PROJ_123",
+				"MY_PROJ-12",
+				"MYPROJ-12",
+				"MY-PROJ_12",
+			][..2],
+			&["MY_PROJ-12", "MYPROJ-12", "MY-PROJ_12", "my_proj-12"],
+			// Leading zeros
+			&["PROJ-123", "PROJ-0123", "PROJ-000123", "proj_0123"],
+		];
+
+		for variants in variant_groups {
+			let variant_keys = variants
+				.iter()
+				.map(|jira_ticket| jira_ticket_variant_key(jira_ticket))
+				.collect::<Vec<_>>();
+			assert!(variant_keys[0].is_some(), "{variants:?}");
+			assert!(
+				variant_keys.iter().all(|key| *key == variant_keys[0]),
+				"{variants:?} -> {variant_keys:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn jira_ticket_variant_keys_keep_different_tickets_apart() {
+		// Pairs of tickets that aren't variants of each other
+		let cases = [
+			("PROJ-123", "PROJ-1230"),
+			("PROJ-123", "PROJ-12"),
+			("PROJ-123", "PROJX-123"),
+			("PROJ-10", "PROJ-100"),
+		];
+
+		for (jira_ticket, other_jira_ticket) in cases {
+			assert_ne!(
+				jira_ticket_variant_key(jira_ticket),
+				jira_ticket_variant_key(other_jira_ticket),
+				"{jira_ticket} and {other_jira_ticket}"
+			);
+		}
+	}
+
+	#[test]
+	fn jira_ticket_variant_keys_need_a_numeric_issue() {
+		assert_eq!(
+			jira_ticket_variant_key("PROJ-0123"),
+			Some(("PROJ".to_owned(), 123))
+		);
+		assert_eq!(jira_ticket_variant_key("PROJ"), None);
+		assert_eq!(jira_ticket_variant_key("PROJ-12a"), None);
+	}
+
+	#[test]
+	fn percent_escapes_are_decoded() {
+		// The text and what it decodes to
+		let cases = [
+			("release%201.2", "release 1.2"),
+			("team%2Ffeature", "team/feature"),
+			("caf%C3%A9", "caf\u{e9}"),
+			("%41%62c", "Abc"),
+			("100%", "100%"),
+			("100%2", "100%2"),
+			("%zz%", "%zz%"),
+			("%FF", "\u{fffd}"),
+			("plain", "plain"),
+		];
+
+		for (text, expected) in cases {
+			assert_eq!(percent_decode(text), expected, "{text}");
+		}
+	}
+
+	// Building exit statuses by hand needs the raw `waitpid` format, which only
+	// exists on Unix
+	#[cfg(unix)]
+	mod retries {
+		use std::{cell::Cell, os::unix::process::ExitStatusExt, process::ExitStatus};
+
+		use super::*;
+
+		/// Builds the output of a command that exited with `exit_code`.
+		fn output(exit_code: i32, stderr: &str) -> Output {
+			Output {
+				// The raw status is in the format `waitpid` gives it, which has the exit
+				// code in the second byte
+				status: ExitStatus::from_raw(exit_code << 8),
+				stdout: Vec::new(),
+				stderr: stderr.as_bytes().to_vec(),
+			}
+		}
+
+		#[test]
+		fn transient_failures_are_retried_until_they_succeed() {
+			let attempts = Cell::new(0);
+			let command_result = retry_transient_failures("git log", 3, Duration::ZERO, || {
+				attempts.set(attempts.get() + 1);
+				Ok(if attempts.get() <= 2 {
+					output(
+						128,
+						"fatal: Unable to create '.git/index.lock': File exists.",
+					)
+				} else {
+					output(0, "")
+				})
+			})
+			.expect("the command runs");
+
+			assert!(command_result.status.success());
+			assert_eq!(attempts.get(), 3);
+		}
+
+		#[test]
+		fn other_failures_are_not_retried() {
+			let attempts = Cell::new(0);
+			let command_result = retry_transient_failures("git log", 3, Duration::ZERO, || {
+				attempts.set(attempts.get() + 1);
+				Ok(output(128, "fatal: bad revision 'nope'"))
+			})
+			.expect("the command runs");
+
+			assert_eq!(command_result.status.code(), Some(128));
+			assert_eq!(attempts.get(), 1);
+		}
+
+		#[test]
+		fn transient_failures_give_up_after_the_retries() {
+			let attempts = Cell::new(0);
+			let command_result = retry_transient_failures("git log", 2, Duration::ZERO, || {
+				attempts.set(attempts.get() + 1);
+				Ok(output(128, "error: Resource temporarily unavailable"))
+			})
+			.expect("the command runs");
+
+			assert_eq!(command_result.status.code(), Some(128));
+			assert_eq!(attempts.get(), 3);
+		}
+
+		#[test]
+		fn transient_failures_are_not_retried_by_default() {
+			let attempts = Cell::new(0);
+			retry_transient_failures("git log", 0, Duration::ZERO, || {
+				attempts.set(attempts.get() + 1);
+				Ok(output(128, "error: Resource temporarily unavailable"))
+			})
+			.expect("the command runs");
+
+			assert_eq!(attempts.get(), 1);
+		}
+	}
+}
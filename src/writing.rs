@@ -1,58 +1,338 @@
 //! The module that handles writing revision maps to disk.
 
 // Uses
-use std::{fs::File, io::Write, path::Path};
+use std::{
+	fs::{remove_file, rename, File},
+	io::{sink, stdout, BufWriter, Result as IoResult, Write},
+	path::{Path, PathBuf},
+	process,
+};
 
 use anyhow::{Context, Result};
 
-use crate::util::parse_hex_str;
+use crate::{
+	collection::RevisionMapEntry,
+	constants::STDOUT_PATH,
+	util::{abbreviate, parse_hex_str, sanitize_for_display},
+};
 
+// Types and Structures
+/// How a revision map is laid out in Markdown.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MarkdownStyle {
+	/// A flat list, with one entry per revision.
+	List,
+	/// A GitHub-flavoured Markdown table, which renders better than a list in
+	/// some wikis.
+	Table,
+	/// A list split into one section per `chunk_size` SVN revisions, so large
+	/// maps can be paginated.
+	Split { chunk_size: u32 },
+}
+
+/// Where output is being written: either stdout, or a temporary file that's
+/// only moved into place once it's complete.
+///
+/// This way, a run that fails partway through (or a reader that looks at the
+/// file while it's being written) never sees a partial file.
+struct Output {
+	writer:       BufWriter<Box<dyn Write>>,
+	/// The temporary file being written, and the path it's moved to when
+	/// finished.
+	pending_file: Option<(PathBuf, PathBuf)>,
+}
+
+impl Output {
+	/// Flushes the output, and moves the file into place if it's going to one.
+	fn finish(mut self) -> Result<()> {
+		self.writer
+			.flush()
+			.with_context(|| "unable to finish writing the output")?;
+		if let Some((temporary_path, path)) = self.pending_file.take() {
+			// Close the file before it's moved, since some platforms don't allow
+			// moving open files
+			self.writer = BufWriter::new(Box::new(sink()));
+			rename(temporary_path.as_path(), path.as_path()).with_context(|| {
+				format!("unable to move the finished output to `{}`", path.display())
+			})?;
+		}
+
+		Ok(())
+	}
+}
+
+impl Write for Output {
+	fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+		self.writer.write(buf)
+	}
+
+	fn flush(&mut self) -> IoResult<()> {
+		self.writer.flush()
+	}
+}
+
+// Clean up the temporary file if the output was never finished
+impl Drop for Output {
+	fn drop(&mut self) {
+		if let Some((temporary_path, _)) = self.pending_file.take() {
+			self.writer = BufWriter::new(Box::new(sink()));
+			let _ = remove_file(temporary_path);
+		}
+	}
+}
+
+/// Writes a revision map in the same layout as git-svn's `.rev_map.<UUID>`
+/// files.
+///
+/// There's no header or version marker - the file is just a list of records,
+/// each of which is the SVN revision as a big-endian 32-bit integer followed by
+/// the raw bytes of the Git hash (so 24 bytes per record for SHA-1, and 36 for
+/// SHA-256). git-svn refuses to append to a map where the revisions aren't
+/// strictly increasing, so any later entries for a revision that's already
+/// been written are left out.
+///
 /// Based on: <https://github.com/hexmode/git-1/blob/master/perl/Git/SVN.pm#L2170>
-pub fn write_to_bin<P>(path: P, revision_map: &[(u32, &str, &str)]) -> Result<()>
+pub fn write_to_bin<P>(path: P, revision_map: &[RevisionMapEntry]) -> Result<()>
 where
 	P: AsRef<Path>,
 {
 	let mut output_bin = Vec::new();
+	let mut last_svn_revision = None;
+	let mut skipped_entry_count = 0;
+
+	for entry in revision_map {
+		if last_svn_revision.map_or(false, |last_svn_revision| {
+			entry.svn_revision <= last_svn_revision
+		}) {
+			skipped_entry_count += 1;
+			continue;
+		}
+		last_svn_revision = Some(entry.svn_revision);
 
-	for revision_map in revision_map {
-		let svn_bytes = revision_map.0.to_be_bytes();
+		let svn_bytes = entry.svn_revision.to_be_bytes();
 		output_bin.extend_from_slice(&svn_bytes);
 
-		let git_bytes = parse_hex_str(revision_map.2)
+		let git_bytes = parse_hex_str(entry.git_revision)
 			.expect("this should always be valid hex because it comes from Git directly");
 		output_bin.extend_from_slice(git_bytes.as_slice());
 	}
 
-	let mut output_file = File::create(path).with_context(|| "unable to open path for writing")?;
-	output_file
-		.write_all(output_bin.as_slice())
-		.with_context(|| "unable to write bytes to the file")
+	// git-svn keeps one map per SVN repo (and per branch), so these are worth
+	// pointing out even though they don't make the file unreadable
+	if skipped_entry_count > 0 {
+		eprintln!(
+			"[WARNING] Left {skipped_entry_count} entries out of the binary revision map, since \
+			 their SVN revisions were already mapped to other commits"
+		);
+	}
+	if revision_map
+		.iter()
+		.any(|entry| entry.svn_uuid != revision_map[0].svn_uuid)
+	{
+		eprintln!(
+			"[WARNING] The binary revision map mixes commits from more than one SVN repo, so it \
+			 isn't a drop-in replacement for a git-svn map"
+		);
+	}
+
+	write_output(path, output_bin.as_slice())
 }
 
 pub fn write_to_markdown<P>(
 	path: P,
-	revision_map: &[(u32, &str, &str)],
+	revision_map: &[RevisionMapEntry],
 	hash_length: usize,
+	style: MarkdownStyle,
 ) -> Result<()>
 where
 	P: AsRef<Path>,
 {
-	let mut output_str = String::new();
-
-	for revision_map in revision_map {
-		output_str.push_str(
-			format!(
-				"- `{}` -> `{}` (`{}`)\n",
-				revision_map.0,
-				&revision_map.2[0..hash_length],
-				revision_map.1,
-			)
-			.as_str(),
-		);
+	let mut output = open_output(path)?;
+	let mut current_chunk = None;
+
+	if style == MarkdownStyle::Table {
+		output.write_all(b"| SVN revision | Git hash | SVN URL | SVN location |\n")?;
+		output.write_all(b"| --- | --- | --- | --- |\n")?;
+	}
+	for entry in revision_map {
+		let git_hash = abbreviate(entry.git_revision, hash_length)?;
+		let svn_url = sanitize_for_display(entry.svn_url);
+		let svn_location = entry.svn_location.to_string();
+		let svn_location = sanitize_for_display(svn_location.as_str());
+		match style {
+			MarkdownStyle::List => writeln!(
+				output,
+				"- `{}` -> `{git_hash}` (`{svn_url}`, {svn_location})",
+				entry.svn_revision,
+			)?,
+			MarkdownStyle::Table => writeln!(
+				output,
+				"| `{}` | `{git_hash}` | `{}` | {} |",
+				entry.svn_revision,
+				escape_markdown_table_cell(&svn_url),
+				escape_markdown_table_cell(&svn_location),
+			)?,
+			MarkdownStyle::Split { chunk_size } => {
+				// Start a new section whenever the revision crosses into the next chunk
+				let chunk = entry.svn_revision / chunk_size;
+				if current_chunk != Some(chunk) {
+					if current_chunk.is_some() {
+						writeln!(output)?;
+					}
+					writeln!(
+						output,
+						"## Revisions {}-{}\n",
+						chunk * chunk_size,
+						(chunk * chunk_size).saturating_add(chunk_size - 1),
+					)?;
+					current_chunk = Some(chunk);
+				}
+				writeln!(
+					output,
+					"- `{}` -> `{git_hash}` (`{svn_url}`, {svn_location})",
+					entry.svn_revision,
+				)?;
+			}
+		}
+	}
+
+	output.finish()
+}
+
+/// Writes a Markdown report of the Git commits associated with more than one
+/// SVN revision, which are usually squashes or merges from the migration.
+///
+/// Each entry is the Git hash and all the SVN revisions associated with it.
+pub fn write_svn_fanin_report<P>(
+	path: P,
+	svn_fanin: &[(&str, Vec<u32>)],
+	hash_length: usize,
+) -> Result<()>
+where
+	P: AsRef<Path>,
+{
+	let mut output = open_output(path)?;
+
+	for (git_revision, svn_revisions) in svn_fanin {
+		writeln!(
+			output,
+			"- `{}`: {} SVN revisions ({})",
+			abbreviate(git_revision, hash_length)?,
+			svn_revisions.len(),
+			svn_revisions
+				.iter()
+				.map(|svn_revision| format!("`{svn_revision}`"))
+				.collect::<Vec<_>>()
+				.join(", "),
+		)?;
+	}
+
+	output.finish()
+}
+
+/// Escapes the pipe characters in a string so that it doesn't break the
+/// Markdown table it's in.
+///
+/// This is necessary even inside code spans, since the table is split into
+/// cells before anything else is parsed.
+fn escape_markdown_table_cell(cell: &str) -> String {
+	cell.replace('|', "\\|")
+}
+
+/// Writes the output to the file at the path, or to stdout if the path is `-`.
+fn write_output<P>(path: P, output_bytes: &[u8]) -> Result<()>
+where
+	P: AsRef<Path>,
+{
+	let mut output = open_output(path)?;
+	output
+		.write_all(output_bytes)
+		.with_context(|| "unable to write the output")?;
+	output.finish()
+}
+
+/// Opens a buffered writer to the file at the path, or to stdout if the path
+/// is `-`.
+///
+/// Files are written to a temporary file next to the path first, which is
+/// moved into place by `Output::finish`.
+fn open_output<P>(path: P) -> Result<Output>
+where
+	P: AsRef<Path>,
+{
+	let path = path.as_ref();
+	if path == Path::new(STDOUT_PATH) {
+		return Ok(Output {
+			writer:       BufWriter::new(Box::new(stdout().lock())),
+			pending_file: None,
+		});
 	}
 
-	let mut output_file = File::create(path).with_context(|| "unable to open path for writing")?;
-	output_file
-		.write_all(output_str.as_bytes())
-		.with_context(|| "unable to write bytes to the file")
+	// The temporary file is in the same directory, so moving it into place is
+	// atomic
+	let file_name = path
+		.file_name()
+		.with_context(|| format!("`{}` isn't a path to a file", path.display()))?;
+	let temporary_path = path.with_file_name(format!(
+		".{}.{}.tmp",
+		file_name.to_string_lossy(),
+		process::id()
+	));
+	let file = File::create(temporary_path.as_path())
+		.with_context(|| "unable to open path for writing")?;
+
+	Ok(Output {
+		writer:       BufWriter::new(Box::new(file)),
+		pending_file: Some((temporary_path, path.to_path_buf())),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs::read;
+
+	use super::*;
+	use crate::{
+		collection::{SvnLocation, SvnPathKind},
+		test_util::{full_hash, FixtureRepo},
+	};
+
+	fn revision_map_entry(svn_revision: u32, git_revision: &str) -> RevisionMapEntry {
+		RevisionMapEntry {
+			svn_revision,
+			svn_url: "https://svn.example.com/repo/trunk",
+			svn_location: SvnLocation {
+				kind: SvnPathKind::Trunk,
+				name: "trunk".to_owned(),
+			},
+			svn_uuid: "00000000-0000-0000-0000-000000000000",
+			git_revision,
+		}
+	}
+
+	#[test]
+	fn binary_revision_maps_match_the_git_svn_layout() {
+		let first_revision = full_hash("0123456789abcdef");
+		let second_revision = full_hash("fedcba98");
+		let duplicate_revision = full_hash("a1");
+		// The third entry repeats the second one's SVN revision, so it's left out
+		let revision_map = [
+			revision_map_entry(7, first_revision.as_str()),
+			revision_map_entry(0x0102_0304, second_revision.as_str()),
+			revision_map_entry(0x0102_0304, duplicate_revision.as_str()),
+		];
+		let output_dir = FixtureRepo::empty_dir("binary-revision-map");
+		let path = output_dir.path().join("rev_map");
+
+		write_to_bin(path.as_path(), &revision_map).expect("the map is written");
+
+		let mut expected = Vec::new();
+		expected.extend_from_slice(&[0x00, 0x00, 0x00, 0x07]);
+		expected.extend_from_slice(&[0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]);
+		expected.extend_from_slice(&[0x00; 12]);
+		expected.extend_from_slice(&[0x01, 0x02, 0x03, 0x04]);
+		expected.extend_from_slice(&[0xFE, 0xDC, 0xBA, 0x98]);
+		expected.extend_from_slice(&[0x00; 16]);
+		assert_eq!(read(path).expect("the map can be read"), expected);
+	}
 }